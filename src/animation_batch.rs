@@ -0,0 +1,401 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    AnimVariableContext, DecodeLimitError, DecodeLimited, DecodeLimits, IdMapping,
+    MAX_ANIMATION_BYTES, StoredNewAnim, SyncNewAnim, Validate, ViolationKind, Violations,
+    field_path, indexed_path,
+};
+
+/// Most animations a single [`SyncNewAnimBatch`] may carry. Their bytes together stay under
+/// [`MAX_ANIMATION_BYTES`].
+pub const MAX_SYNC_NEW_ANIM_BATCH_LEN: usize = 16;
+
+/// [`SyncNewAnimBatchItem`] is one animation of a batch, with the placement to create for it.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
+pub struct SyncNewAnimBatchItem {
+    anim: SyncNewAnim,
+    placement: Option<AnimVariableContext>,
+}
+impl SyncNewAnimBatchItem {
+    pub fn new(anim: SyncNewAnim, placement: Option<AnimVariableContext>) -> Self {
+        Self { anim, placement }
+    }
+    pub fn anim(&self) -> &SyncNewAnim {
+        &self.anim
+    }
+    pub fn placement(&self) -> Option<&AnimVariableContext> {
+        self.placement.as_ref()
+    }
+    pub fn into_parts(self) -> (SyncNewAnim, Option<AnimVariableContext>) {
+        (self.anim, self.placement)
+    }
+}
+
+/// [`SyncNewAnimBatch`] uploads several animations in one request. The server stores each item
+/// on its own: one failing item does not reject the others, see [`SyncNewAnimBatchResponse`].
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
+pub struct SyncNewAnimBatch {
+    batch_id: [u8; 16],
+    items: Vec<SyncNewAnimBatchItem>,
+}
+impl SyncNewAnimBatch {
+    pub fn new(items: Vec<SyncNewAnimBatchItem>) -> Self {
+        Self {
+            batch_id: Uuid::now_v7().into_bytes(),
+            items,
+        }
+    }
+    pub fn batch_id(&self) -> Uuid {
+        Uuid::from_bytes(self.batch_id)
+    }
+    pub fn items(&self) -> &[SyncNewAnimBatchItem] {
+        &self.items
+    }
+    pub fn take_items(&mut self) -> Vec<SyncNewAnimBatchItem> {
+        std::mem::take(&mut self.items)
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    fn anim_bytes_len(&self) -> usize {
+        self.items
+            .iter()
+            .map(|item| {
+                item.anim.data_len()
+                    + item.anim.still_frame_jpeg_as_slice().len()
+                    + item.anim.mask_png_buffer_len()
+            })
+            .sum()
+    }
+}
+
+/// Why the server did not store one item of a batch.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq, Eq)]
+pub enum SyncNewAnimError {
+    /// Field paths of the violations found by [`Validate`], relative to the item.
+    Invalid {
+        field_paths: Vec<String>,
+    },
+    /// An animation with this id is already stored.
+    DuplicateAnimId,
+    ContextNotFound {
+        context_id: [u8; 16],
+    },
+    Forbidden,
+    /// The placement context moved on while the batch was stored.
+    ContextVersionConflict {
+        expected: u64,
+        current: u64,
+    },
+    /// Server side failure, the item can be sent again as is.
+    StorageFailed,
+}
+impl SyncNewAnimError {
+    /// Whether sending the same item again can succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::StorageFailed)
+    }
+}
+
+/// Why a [`SyncNewAnimBatchResponse`] cannot be applied to a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncNewAnimBatchError {
+    /// The response answers another batch.
+    WrongBatch { expected: Uuid, received: Uuid },
+}
+
+impl std::fmt::Display for SyncNewAnimBatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongBatch { expected, received } => write!(
+                f,
+                "SyncNewAnimBatchError WrongBatch expected [{}] received [{}]",
+                expected, received
+            ),
+        }
+    }
+}
+impl std::error::Error for SyncNewAnimBatchError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq, Eq)]
+pub enum SyncNewAnimItemOutcome {
+    Stored(Box<StoredNewAnim>),
+    Failed(SyncNewAnimError),
+}
+
+/// Result of the item at `index` in the batch. `provisional_anim_id` is the id the client sent.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq, Eq)]
+pub struct SyncNewAnimItemResult {
+    index: u32,
+    provisional_anim_id: [u8; 16],
+    outcome: SyncNewAnimItemOutcome,
+}
+impl SyncNewAnimItemResult {
+    pub fn stored(index: usize, stored: StoredNewAnim) -> Self {
+        Self {
+            index: index as u32,
+            provisional_anim_id: stored.provisional_anim_id().into_bytes(),
+            outcome: SyncNewAnimItemOutcome::Stored(Box::new(stored)),
+        }
+    }
+    pub fn failed(index: usize, provisional_anim_id: Uuid, error: SyncNewAnimError) -> Self {
+        Self {
+            index: index as u32,
+            provisional_anim_id: provisional_anim_id.into_bytes(),
+            outcome: SyncNewAnimItemOutcome::Failed(error),
+        }
+    }
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+    pub fn provisional_anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.provisional_anim_id)
+    }
+    pub fn outcome(&self) -> &SyncNewAnimItemOutcome {
+        &self.outcome
+    }
+    pub fn stored_anim(&self) -> Option<&StoredNewAnim> {
+        match &self.outcome {
+            SyncNewAnimItemOutcome::Stored(stored) => Some(stored),
+            SyncNewAnimItemOutcome::Failed(_) => None,
+        }
+    }
+    pub fn error(&self) -> Option<&SyncNewAnimError> {
+        match &self.outcome {
+            SyncNewAnimItemOutcome::Stored(_) => None,
+            SyncNewAnimItemOutcome::Failed(error) => Some(error),
+        }
+    }
+}
+
+/// [`SyncNewAnimBatchResponse`] holds one result per item of the batch, in the batch order.
+/// `id_mapping` lists the other objects the server assigned ids to while storing the batch,
+/// such as compositions and contexts the placements were created offline in.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq, Eq)]
+pub struct SyncNewAnimBatchResponse {
+    batch_id: [u8; 16],
+    results: Vec<SyncNewAnimItemResult>,
+    id_mapping: IdMapping,
+}
+impl SyncNewAnimBatchResponse {
+    pub fn new(batch_id: Uuid, results: Vec<SyncNewAnimItemResult>) -> Self {
+        Self {
+            batch_id: batch_id.into_bytes(),
+            results,
+            id_mapping: IdMapping::new_empty(),
+        }
+    }
+    pub fn with_id_mapping(mut self, id_mapping: IdMapping) -> Self {
+        self.id_mapping = id_mapping;
+        self
+    }
+    /// Every local to server id pair of the batch, stored animations included.
+    pub fn id_mapping(&self) -> IdMapping {
+        let mut mapping = self.id_mapping.clone();
+        for stored in self.results.iter().filter_map(|it| it.stored_anim()) {
+            mapping.extend(&stored.id_mapping());
+        }
+        mapping
+    }
+    pub fn batch_id(&self) -> Uuid {
+        Uuid::from_bytes(self.batch_id)
+    }
+    pub fn results(&self) -> &[SyncNewAnimItemResult] {
+        &self.results
+    }
+    /// Whether every item of `batch` was stored. Items the response does not mention count
+    /// as not stored.
+    pub fn is_complete_success(&self, batch: &SyncNewAnimBatch) -> bool {
+        self.batch_id == batch.batch_id
+            && (0..batch.items.len()).all(|index| {
+                self.results
+                    .iter()
+                    .any(|it| it.index() == index && it.error().is_none())
+            })
+    }
+    pub fn stored(&self) -> impl Iterator<Item = &SyncNewAnimItemResult> {
+        self.results.iter().filter(|it| it.error().is_none())
+    }
+    pub fn failed(&self) -> impl Iterator<Item = &SyncNewAnimItemResult> {
+        self.results.iter().filter(|it| it.error().is_some())
+    }
+    /// New batch with the items of `batch` that failed with a retryable error. Items the
+    /// response does not mention are retried too. Fails when the response answers another
+    /// batch, whose results would point at the wrong items.
+    pub fn retry_batch(
+        &self,
+        batch: &SyncNewAnimBatch,
+    ) -> Result<SyncNewAnimBatch, SyncNewAnimBatchError> {
+        if self.batch_id != batch.batch_id {
+            return Err(SyncNewAnimBatchError::WrongBatch {
+                expected: batch.batch_id(),
+                received: self.batch_id(),
+            });
+        }
+        let items = batch
+            .items
+            .iter()
+            .enumerate()
+            .filter(
+                |(index, _)| match self.results.iter().find(|it| it.index() == *index) {
+                    Some(result) => result.error().is_some_and(|it| it.is_retryable()),
+                    None => true,
+                },
+            )
+            .map(|(_, item)| item.clone())
+            .collect();
+        Ok(SyncNewAnimBatch::new(items))
+    }
+}
+
+impl DecodeLimited for SyncNewAnimBatch {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+            .with_max_total_bytes(MAX_ANIMATION_BYTES)
+            .with_max_collection_len(MAX_SYNC_NEW_ANIM_BATCH_LEN)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("items", self.items.len())?;
+        limits.check_bytes("items.anim", self.anim_bytes_len())
+    }
+}
+impl DecodeLimited for SyncNewAnimBatchResponse {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_collection_len(MAX_SYNC_NEW_ANIM_BATCH_LEN)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("results", self.results.len())?;
+        self.id_mapping.check_decode_limits(limits)?;
+        for result in &self.results {
+            if let Some(SyncNewAnimError::Invalid { field_paths }) = result.error() {
+                limits.check_collection("results.field_paths", field_paths.len())?;
+                for field_path in field_paths {
+                    limits.check_string("results.field_paths", field_path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for SyncNewAnimBatchItem {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        self.anim.validate_at(&field_path(path, "anim"), violations);
+        if let Some(placement) = &self.placement {
+            placement.validate_at(&field_path(path, "placement"), violations);
+        }
+    }
+}
+impl Validate for SyncNewAnimBatch {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_empty(path, "items", self.items.len());
+        violations.check_range(
+            path,
+            "items.len",
+            self.items.len() as f64,
+            0.0,
+            MAX_SYNC_NEW_ANIM_BATCH_LEN as f64,
+        );
+        violations.check_items(path, "items", &self.items);
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(placement) = &item.placement
+                && placement.anim_id() != item.anim.anim_id()
+            {
+                violations.push(
+                    field_path(&indexed_path(path, "items", index), "placement.anim_id"),
+                    ViolationKind::Invalid("placement must reference its animation"),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod animation_batch_test {
+    use uuid::Uuid;
+
+    use crate::{
+        DecodeLimited, RessourcesDescriptorsKind, StoredNewAnim, SyncNewAnim, SyncNewAnimBatch,
+        SyncNewAnimBatchError, SyncNewAnimBatchItem, SyncNewAnimBatchResponse, SyncNewAnimError,
+        SyncNewAnimItemResult, decode_with_limits,
+    };
+
+    #[test]
+    fn only_retryable_and_missing_items_are_retried() {
+        let anims: Vec<SyncNewAnim> = (0..4)
+            .map(|_| {
+                SyncNewAnim::builder(Uuid::now_v7(), Uuid::now_v7())
+                    .data(vec![1, 2, 3])
+                    .still_frame_jpeg(vec![4])
+                    .fps(24)
+                    .frame_width(64)
+                    .frame_height(64)
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        let ids: Vec<Uuid> = anims.iter().map(|it| it.anim_id()).collect();
+        let batch = SyncNewAnimBatch::new(
+            anims
+                .into_iter()
+                .map(|it| SyncNewAnimBatchItem::new(it, None))
+                .collect(),
+        );
+        let response = SyncNewAnimBatchResponse::new(
+            batch.batch_id(),
+            vec![
+                SyncNewAnimItemResult::stored(
+                    0,
+                    StoredNewAnim::new(
+                        ids[0],
+                        RessourcesDescriptorsKind::new_animation_descriptor(
+                            Uuid::now_v7(),
+                            Uuid::now_v7(),
+                            64,
+                            64,
+                        ),
+                        12,
+                        None,
+                        batch.items()[0].anim().content_hash(),
+                    ),
+                ),
+                SyncNewAnimItemResult::failed(1, ids[1], SyncNewAnimError::DuplicateAnimId),
+                SyncNewAnimItemResult::failed(2, ids[2], SyncNewAnimError::StorageFailed),
+            ],
+        );
+
+        assert!(!response.is_complete_success(&batch));
+        let partial =
+            SyncNewAnimBatchResponse::new(batch.batch_id(), response.results()[..1].to_vec());
+        assert!(!partial.is_complete_success(&batch));
+        assert!(
+            !SyncNewAnimBatchResponse::new(batch.batch_id(), Vec::new())
+                .is_complete_success(&batch)
+        );
+        assert!(response.stored().count() == 1);
+        let retry = response.retry_batch(&batch).unwrap();
+        let retried: Vec<Uuid> = retry.items().iter().map(|it| it.anim().anim_id()).collect();
+        assert!(retried == vec![ids[2], ids[3]]);
+        // Results of the first batch do not apply to the retry.
+        assert!(
+            response.retry_batch(&retry).err()
+                == Some(SyncNewAnimBatchError::WrongBatch {
+                    expected: retry.batch_id(),
+                    received: batch.batch_id()
+                })
+        );
+
+        // Same encoding as the request body.
+        let encoded = bincode::encode_to_vec(&batch, bincode::config::standard()).unwrap();
+        let limits = SyncNewAnimBatch::default_decode_limits();
+        let decoded: SyncNewAnimBatch = decode_with_limits(&encoded, &limits).unwrap();
+        assert!(decoded.batch_id() == batch.batch_id() && decoded.len() == 4);
+        let limits = limits.with_max_collection_len(2);
+        assert!(decode_with_limits::<SyncNewAnimBatch>(&encoded, &limits).is_err());
+    }
+}
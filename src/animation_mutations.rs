@@ -0,0 +1,544 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    AnimVariableContext, DecodeLimitError, DecodeLimited, DecodeLimits, FragmentTransform2DData,
+    RessourcesDescriptorsKind, Validate, Violations, field_path,
+};
+
+/// [`UpdateAnimPlacement`] moves or transforms an animation inside a composition.
+/// It only applies when the placement is still at `expected_variable_context_version`, so two
+/// clients editing the same placement can not overwrite each other silently.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct UpdateAnimPlacement {
+    anim_id: [u8; 16],
+    composition_id: [u8; 16],
+    context_id: [u8; 16],
+    expected_variable_context_version: u64,
+    transform: FragmentTransform2DData,
+}
+impl UpdateAnimPlacement {
+    pub fn new(
+        anim_id: Uuid,
+        composition_id: Uuid,
+        context_id: Uuid,
+        expected_variable_context_version: u64,
+        transform: FragmentTransform2DData,
+    ) -> Self {
+        Self {
+            anim_id: anim_id.into_bytes(),
+            composition_id: composition_id.into_bytes(),
+            context_id: context_id.into_bytes(),
+            expected_variable_context_version,
+            transform,
+        }
+    }
+    /// Update of `placement`, expecting its current version.
+    pub fn from_placement(
+        placement: &AnimVariableContext,
+        transform: FragmentTransform2DData,
+    ) -> Self {
+        Self::new(
+            placement.anim_id(),
+            placement.composition_id(),
+            placement.context_id(),
+            placement.variable_context_version(),
+            transform,
+        )
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn composition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.composition_id)
+    }
+    pub fn context_id(&self) -> Uuid {
+        Uuid::from_bytes(self.context_id)
+    }
+    pub fn expected_variable_context_version(&self) -> u64 {
+        self.expected_variable_context_version
+    }
+    pub fn transform(&self) -> &FragmentTransform2DData {
+        &self.transform
+    }
+    /// Replaces the transform of `placement` and bumps its `variable_context_version` when the
+    /// expected version matches. `placement` is left untouched otherwise.
+    pub fn apply(&self, placement: &mut AnimVariableContext) -> AnimMutationOutcome {
+        if let Err(outcome) = check_placement(
+            placement,
+            self.anim_id,
+            self.composition_id,
+            self.context_id,
+            self.expected_variable_context_version,
+        ) {
+            return outcome;
+        }
+        let variable_context_version = match next_version(placement) {
+            Ok(version) => version,
+            Err(outcome) => return outcome,
+        };
+        placement.replace_placement(self.transform.clone(), variable_context_version);
+        AnimMutationOutcome::Applied {
+            variable_context_version,
+        }
+    }
+}
+
+/// [`RemoveAnimFromComposition`] removes one placement of an animation. The animation itself
+/// and its other placements are kept.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct RemoveAnimFromComposition {
+    anim_id: [u8; 16],
+    composition_id: [u8; 16],
+    context_id: [u8; 16],
+    expected_variable_context_version: u64,
+}
+impl RemoveAnimFromComposition {
+    pub fn new(
+        anim_id: Uuid,
+        composition_id: Uuid,
+        context_id: Uuid,
+        expected_variable_context_version: u64,
+    ) -> Self {
+        Self {
+            anim_id: anim_id.into_bytes(),
+            composition_id: composition_id.into_bytes(),
+            context_id: context_id.into_bytes(),
+            expected_variable_context_version,
+        }
+    }
+    pub fn from_placement(placement: &AnimVariableContext) -> Self {
+        Self::new(
+            placement.anim_id(),
+            placement.composition_id(),
+            placement.context_id(),
+            placement.variable_context_version(),
+        )
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn composition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.composition_id)
+    }
+    pub fn context_id(&self) -> Uuid {
+        Uuid::from_bytes(self.context_id)
+    }
+    pub fn expected_variable_context_version(&self) -> u64 {
+        self.expected_variable_context_version
+    }
+    /// Whether `placement` can be removed. On success, the returned version is the one the
+    /// removal is recorded with.
+    pub fn check(&self, placement: &AnimVariableContext) -> AnimMutationOutcome {
+        if let Err(outcome) = check_placement(
+            placement,
+            self.anim_id,
+            self.composition_id,
+            self.context_id,
+            self.expected_variable_context_version,
+        ) {
+            return outcome;
+        }
+        match next_version(placement) {
+            Ok(variable_context_version) => AnimMutationOutcome::Applied {
+                variable_context_version,
+            },
+            Err(outcome) => outcome,
+        }
+    }
+}
+
+/// [`DeleteAnimation`] deletes an animation and every placement of it. Only its author may
+/// delete it, the server checks it against the user of the authenticated session.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct DeleteAnimation {
+    anim_id: [u8; 16],
+}
+impl DeleteAnimation {
+    pub fn new(anim_id: Uuid) -> Self {
+        Self {
+            anim_id: anim_id.into_bytes(),
+        }
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    /// Whether `session_user_id` may delete `animation`. `session_user_id` is the user the
+    /// server authenticated the request from, the request itself does not carry one.
+    pub fn check(
+        &self,
+        animation: &RessourcesDescriptorsKind,
+        session_user_id: Uuid,
+    ) -> Result<(), AnimMutationOutcome> {
+        if animation.get_id().into_bytes() != self.anim_id {
+            return Err(AnimMutationOutcome::NotFound);
+        }
+        if animation.get_author_id() != session_user_id {
+            return Err(AnimMutationOutcome::Forbidden);
+        }
+        Ok(())
+    }
+}
+
+fn check_placement(
+    placement: &AnimVariableContext,
+    anim_id: [u8; 16],
+    composition_id: [u8; 16],
+    context_id: [u8; 16],
+    expected_variable_context_version: u64,
+) -> Result<(), AnimMutationOutcome> {
+    if placement.anim_id().into_bytes() != anim_id
+        || placement.composition_id().into_bytes() != composition_id
+        || placement.context_id().into_bytes() != context_id
+    {
+        return Err(AnimMutationOutcome::NotFound);
+    }
+    if placement.variable_context_version() != expected_variable_context_version {
+        return Err(AnimMutationOutcome::VersionConflict {
+            expected: expected_variable_context_version,
+            current: placement.variable_context_version(),
+        });
+    }
+    Ok(())
+}
+
+/// The version a mutation of `placement` is recorded with. A placement already at `u64::MAX`
+/// cannot move past it and is reported as a conflict.
+fn next_version(placement: &AnimVariableContext) -> Result<u64, AnimMutationOutcome> {
+    let current = placement.variable_context_version();
+    current
+        .checked_add(1)
+        .ok_or(AnimMutationOutcome::VersionConflict {
+            expected: current,
+            current,
+        })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub enum AnimMutationOutcome {
+    /// `variable_context_version` is the version of the placement after the mutation.
+    Applied {
+        variable_context_version: u64,
+    },
+    /// The placement changed since the client read it. The client pulls it again and retries.
+    VersionConflict {
+        expected: u64,
+        current: u64,
+    },
+    NotFound,
+    Forbidden,
+}
+impl AnimMutationOutcome {
+    pub fn is_applied(&self) -> bool {
+        matches!(self, Self::Applied { .. })
+    }
+}
+
+/// Server answer to an [`UpdateAnimPlacement`]. `context_version` is the version of the
+/// context after the update, or its current version when the update was rejected.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct UpdateAnimPlacementResponse {
+    anim_id: [u8; 16],
+    context_id: [u8; 16],
+    context_version: u64,
+    outcome: AnimMutationOutcome,
+}
+impl UpdateAnimPlacementResponse {
+    pub fn new(
+        anim_id: Uuid,
+        context_id: Uuid,
+        context_version: u64,
+        outcome: AnimMutationOutcome,
+    ) -> Self {
+        Self {
+            anim_id: anim_id.into_bytes(),
+            context_id: context_id.into_bytes(),
+            context_version,
+            outcome,
+        }
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn context_id(&self) -> Uuid {
+        Uuid::from_bytes(self.context_id)
+    }
+    pub fn context_version(&self) -> u64 {
+        self.context_version
+    }
+    pub fn outcome(&self) -> &AnimMutationOutcome {
+        &self.outcome
+    }
+}
+
+/// Server answer to a [`RemoveAnimFromComposition`], versioned like
+/// [`UpdateAnimPlacementResponse`].
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct RemoveAnimFromCompositionResponse {
+    anim_id: [u8; 16],
+    composition_id: [u8; 16],
+    context_id: [u8; 16],
+    context_version: u64,
+    outcome: AnimMutationOutcome,
+}
+impl RemoveAnimFromCompositionResponse {
+    pub fn new(
+        anim_id: Uuid,
+        composition_id: Uuid,
+        context_id: Uuid,
+        context_version: u64,
+        outcome: AnimMutationOutcome,
+    ) -> Self {
+        Self {
+            anim_id: anim_id.into_bytes(),
+            composition_id: composition_id.into_bytes(),
+            context_id: context_id.into_bytes(),
+            context_version,
+            outcome,
+        }
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn composition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.composition_id)
+    }
+    pub fn context_id(&self) -> Uuid {
+        Uuid::from_bytes(self.context_id)
+    }
+    pub fn context_version(&self) -> u64 {
+        self.context_version
+    }
+    pub fn outcome(&self) -> &AnimMutationOutcome {
+        &self.outcome
+    }
+}
+
+/// Server answer to a [`DeleteAnimation`]. A deletion touches every context the animation was
+/// placed in, so the new version of each one is returned with the new user session version.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct DeleteAnimationResponse {
+    anim_id: [u8; 16],
+    user_session_version: u64,
+    updated_context_versions: Vec<([u8; 16], u64)>,
+    outcome: AnimMutationOutcome,
+}
+impl DeleteAnimationResponse {
+    pub fn new(
+        anim_id: Uuid,
+        user_session_version: u64,
+        updated_context_versions: Vec<(Uuid, u64)>,
+        outcome: AnimMutationOutcome,
+    ) -> Self {
+        Self {
+            anim_id: anim_id.into_bytes(),
+            user_session_version,
+            updated_context_versions: updated_context_versions
+                .into_iter()
+                .map(|it| (it.0.into_bytes(), it.1))
+                .collect(),
+            outcome,
+        }
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn user_session_version(&self) -> u64 {
+        self.user_session_version
+    }
+    pub fn updated_context_versions(&self) -> Vec<(Uuid, u64)> {
+        self.updated_context_versions
+            .iter()
+            .map(|it| (Uuid::from_bytes(it.0), it.1))
+            .collect()
+    }
+    pub fn outcome(&self) -> &AnimMutationOutcome {
+        &self.outcome
+    }
+}
+
+impl DecodeLimited for UpdateAnimPlacement {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for RemoveAnimFromComposition {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for DeleteAnimation {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for UpdateAnimPlacementResponse {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for RemoveAnimFromCompositionResponse {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for DeleteAnimationResponse {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection(
+            "updated_context_versions",
+            self.updated_context_versions.len(),
+        )
+    }
+}
+
+impl Validate for UpdateAnimPlacement {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_nil(path, "composition_id", &self.composition_id);
+        violations.check_not_nil(path, "context_id", &self.context_id);
+        self.transform
+            .validate_at(&field_path(path, "transform"), violations);
+    }
+}
+impl Validate for RemoveAnimFromComposition {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_nil(path, "composition_id", &self.composition_id);
+        violations.check_not_nil(path, "context_id", &self.context_id);
+    }
+}
+impl Validate for DeleteAnimation {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+    }
+}
+
+#[cfg(test)]
+mod animation_mutations_test {
+    use uuid::Uuid;
+
+    use crate::{
+        AnimMutationOutcome, AnimVariableContext, DeleteAnimation, FragmentTransform2DData,
+        RemoveAnimFromComposition, RessourcesDescriptorsKind, UpdateAnimPlacement,
+    };
+
+    fn placement(variable_context_version: u64) -> AnimVariableContext {
+        AnimVariableContext::new_with_transform(
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            1,
+            variable_context_version,
+            FragmentTransform2DData::from_pos_size([10.0, 20.0], 64.0, 32.0),
+        )
+    }
+
+    #[test]
+    fn placement_mutations_check_versions_and_ids() {
+        let mut current = placement(3);
+        let moved = FragmentTransform2DData::from_pos_size([40.0, -8.0], 64.0, 32.0);
+        let first = UpdateAnimPlacement::from_placement(&current, moved.clone());
+        let second = UpdateAnimPlacement::from_placement(&current, moved.clone());
+        assert!(
+            first.apply(&mut current)
+                == AnimMutationOutcome::Applied {
+                    variable_context_version: 4
+                }
+        );
+        assert!(current.world_pos() == [40.0, -8.0]);
+        // Both were read at version 3, the second one lost the race.
+        assert!(
+            second.apply(&mut current)
+                == AnimMutationOutcome::VersionConflict {
+                    expected: 3,
+                    current: 4
+                }
+        );
+        let stale = UpdateAnimPlacement::new(
+            current.anim_id(),
+            current.composition_id(),
+            current.context_id(),
+            1,
+            FragmentTransform2DData::from_pos_size([0.0, 0.0], 64.0, 32.0),
+        );
+        let before = current.clone();
+        assert!(!stale.apply(&mut current).is_applied());
+        assert!(current == before);
+        let elsewhere = UpdateAnimPlacement::from_placement(&placement(4), moved);
+        assert!(elsewhere.apply(&mut current) == AnimMutationOutcome::NotFound);
+
+        let removal = RemoveAnimFromComposition::from_placement(&current);
+        assert!(
+            removal.check(&current)
+                == AnimMutationOutcome::Applied {
+                    variable_context_version: 5
+                }
+        );
+        assert!(
+            RemoveAnimFromComposition::from_placement(&before).check(&placement(4))
+                == AnimMutationOutcome::NotFound
+        );
+        let stale = RemoveAnimFromComposition::new(
+            current.anim_id(),
+            current.composition_id(),
+            current.context_id(),
+            2,
+        );
+        assert!(
+            stale.check(&current)
+                == AnimMutationOutcome::VersionConflict {
+                    expected: 2,
+                    current: 4
+                }
+        );
+    }
+
+    #[test]
+    fn placement_mutations_at_the_last_version_conflict() {
+        let mut current = placement(u64::MAX);
+        let before = current.clone();
+        let conflict = AnimMutationOutcome::VersionConflict {
+            expected: u64::MAX,
+            current: u64::MAX,
+        };
+        let moved = FragmentTransform2DData::from_pos_size([40.0, -8.0], 64.0, 32.0);
+        assert!(
+            UpdateAnimPlacement::from_placement(&current, moved).apply(&mut current) == conflict
+        );
+        assert!(current == before);
+        assert!(RemoveAnimFromComposition::from_placement(&current).check(&current) == conflict);
+    }
+
+    #[test]
+    fn only_the_session_user_authoring_an_animation_deletes_it() {
+        let [anim_id, author_id] = [Uuid::now_v7(), Uuid::now_v7()];
+        let animation =
+            RessourcesDescriptorsKind::new_animation_descriptor(anim_id, author_id, 64, 32);
+        let delete = DeleteAnimation::new(anim_id);
+        assert!(delete.check(&animation, author_id).is_ok());
+        assert!(delete.check(&animation, Uuid::now_v7()) == Err(AnimMutationOutcome::Forbidden));
+        assert!(
+            DeleteAnimation::new(Uuid::now_v7()).check(&animation, author_id)
+                == Err(AnimMutationOutcome::NotFound)
+        );
+    }
+}
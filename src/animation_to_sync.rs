@@ -4,9 +4,19 @@ use bincode::{
     enc::Encoder,
     error::{DecodeError, EncodeError},
 };
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::{
+    CompositionData, ContentHash, DecodeLimitError, DecodeLimited, DecodeLimits, FragmentStyle,
+    IdKind, IdMapping, MAX_ANIMATION_BYTES, MAX_FPS, MAX_FRAGMENT_STYLE_BYTES, MAX_FRAME_DIMENSION,
+    MAX_KEYFRAME_BYTES, MAX_KEYFRAMES, MAX_PLAYBACK_PARAMS_BYTES, PlaybackParams,
+    QuantizationPrecision, RemapIds, RessourcesDescriptorsKind, ScreenPoint, TransformTrack,
+    Validate, Viewport, ViolationKind, Violations, borrow_decode_with_limits, content_hash,
+    field_path,
+};
+
 #[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
 pub struct ResourcesSet {
     set: Vec<[u8; 16]>,
@@ -86,6 +96,48 @@ impl FetchAnimationToSyncWithItsContexts {
         self.anim_variable_context.clone()
     }
 }
+/// [`BorrowedFetchAnimationToSyncWithItsContexts`] has the same wire format as
+/// [`FetchAnimationToSyncWithItsContexts`] but borrows the animation bytes from the received
+/// buffer instead of copying them.
+#[derive(Debug, BorrowDecode, Encode)]
+pub struct BorrowedFetchAnimationToSyncWithItsContexts<'a> {
+    anim_id: [u8; 16],
+    composition_id: [u8; 16],
+    anim_raw_bytes: &'a [u8],
+    anim_variable_context: Vec<AnimVariableContext>,
+}
+
+impl<'a> BorrowedFetchAnimationToSyncWithItsContexts<'a> {
+    pub fn decode_borrowed(buffer: &'a [u8]) -> Result<Self, DecodeLimitError> {
+        borrow_decode_with_limits(buffer, &Self::default_decode_limits())
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn composition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.composition_id)
+    }
+    pub fn animation_raw_bytes(&self) -> &'a [u8] {
+        self.anim_raw_bytes
+    }
+    /// `received` must be the buffer this was decoded from. The returned [`Bytes`] shares its
+    /// allocation.
+    pub fn shared_raw_bytes(&self, received: &Bytes) -> Bytes {
+        received.slice_ref(self.anim_raw_bytes)
+    }
+    pub fn animation_variable_context(&self) -> &[AnimVariableContext] {
+        &self.anim_variable_context
+    }
+    pub fn to_owned_fetch(&self) -> FetchAnimationToSyncWithItsContexts {
+        FetchAnimationToSyncWithItsContexts {
+            anim_id: self.anim_id,
+            composition_id: self.composition_id,
+            anim_raw_bytes: self.anim_raw_bytes.to_vec(),
+            anim_variable_context: self.anim_variable_context.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
 pub struct FetchAnimationToSyncWithoutContext {
     anim_id: [u8; 16],
@@ -110,8 +162,32 @@ impl FetchAnimationToSyncWithoutContext {
     }
 }
 
+/// Borrowed counterpart of [`FetchAnimationToSyncWithoutContext`], see
+/// [`BorrowedFetchAnimationToSyncWithItsContexts`].
+#[derive(Debug, BorrowDecode, Encode)]
+pub struct BorrowedFetchAnimationToSyncWithoutContext<'a> {
+    anim_id: [u8; 16],
+    anim_raw_bytes: &'a [u8],
+}
+
+impl<'a> BorrowedFetchAnimationToSyncWithoutContext<'a> {
+    pub fn decode_borrowed(buffer: &'a [u8]) -> Result<Self, DecodeLimitError> {
+        borrow_decode_with_limits(buffer, &Self::default_decode_limits())
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn animation_raw_bytes(&self) -> &'a [u8] {
+        self.anim_raw_bytes
+    }
+    /// `received` must be the buffer this was decoded from.
+    pub fn shared_raw_bytes(&self, received: &Bytes) -> Bytes {
+        received.slice_ref(self.anim_raw_bytes)
+    }
+}
+
 // [u8;16 are uuid]
-#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq, Eq)]
 pub struct SyncNewAnim {
     anim_id: [u8; 16],
     author_id: [u8; 16],
@@ -180,6 +256,10 @@ impl SyncNewAnim {
     pub fn fps(&self) -> u8 {
         self.fps
     }
+    /// [`ContentHash`] of the animation data, as the server stores it.
+    pub fn content_hash(&self) -> ContentHash {
+        content_hash(&self.data)
+    }
     pub fn take_data(&mut self) -> Vec<u8> {
         std::mem::take(&mut self.data)
     }
@@ -189,11 +269,191 @@ impl SyncNewAnim {
     pub fn take_mask_data(&mut self) -> Vec<u8> {
         std::mem::take(&mut self.mask_png_buffer)
     }
+    pub fn builder(anim_id: Uuid, author_id: Uuid) -> SyncNewAnimBuilder {
+        SyncNewAnimBuilder::new(anim_id, author_id)
+    }
+}
+
+/// [`SyncNewAnimBuilder`] names each field of a [`SyncNewAnim`]. `fps`, `frame_width` and
+/// `frame_height` are required, `build` reports them as missing otherwise.
+#[derive(Debug, Clone)]
+pub struct SyncNewAnimBuilder {
+    anim_id: Uuid,
+    author_id: Uuid,
+    data: Vec<u8>,
+    still_frame_jpeg: Vec<u8>,
+    fps: Option<u8>,
+    frame_width: Option<u32>,
+    frame_height: Option<u32>,
+    mask_png_buffer: Vec<u8>,
+}
+
+impl SyncNewAnimBuilder {
+    pub fn new(anim_id: Uuid, author_id: Uuid) -> Self {
+        Self {
+            anim_id,
+            author_id,
+            data: vec![],
+            still_frame_jpeg: vec![],
+            fps: None,
+            frame_width: None,
+            frame_height: None,
+            mask_png_buffer: vec![],
+        }
+    }
+    pub fn data(mut self, data: Vec<u8>) -> Self {
+        self.data = data;
+        self
+    }
+    pub fn still_frame_jpeg(mut self, still_frame_jpeg: Vec<u8>) -> Self {
+        self.still_frame_jpeg = still_frame_jpeg;
+        self
+    }
+    pub fn fps(mut self, fps: u8) -> Self {
+        self.fps = Some(fps);
+        self
+    }
+    pub fn frame_width(mut self, frame_width: u32) -> Self {
+        self.frame_width = Some(frame_width);
+        self
+    }
+    pub fn frame_height(mut self, frame_height: u32) -> Self {
+        self.frame_height = Some(frame_height);
+        self
+    }
+    /// Luma<u8> (1 chan) png of size frame_width * frame_height
+    pub fn mask_png_buffer(mut self, mask_png_buffer: Vec<u8>) -> Self {
+        self.mask_png_buffer = mask_png_buffer;
+        self
+    }
+    pub fn build(self) -> Result<SyncNewAnim, Violations> {
+        let mut violations = Violations::default();
+        for (field, is_set) in [
+            ("fps", self.fps.is_some()),
+            ("frame_width", self.frame_width.is_some()),
+            ("frame_height", self.frame_height.is_some()),
+        ] {
+            if !is_set {
+                violations.push(field.to_string(), ViolationKind::Missing);
+            }
+        }
+        let sync_new_anim = SyncNewAnim {
+            anim_id: self.anim_id.into_bytes(),
+            author_id: self.author_id.into_bytes(),
+            data: self.data,
+            still_frame_jpeg: self.still_frame_jpeg,
+            fps: self.fps.unwrap_or_default(),
+            frame_width: self.frame_width.unwrap_or_default(),
+            frame_height: self.frame_height.unwrap_or_default(),
+            mask_png_buffer: self.mask_png_buffer,
+        };
+        if violations.is_empty() {
+            sync_new_anim.validate_at("", &mut violations);
+        }
+        violations.into_result()?;
+        Ok(sync_new_anim)
+    }
+}
+/// [`SyncNewAnimBinary`] is the request of the binary upload endpoint: a [`SyncNewAnim`] sent
+/// as bincode, that the server decodes as a [`BorrowedSyncNewAnim`] without copying the
+/// buffers. [`SyncNewAnim`] keeps its JSON body for the endpoint servers already serve.
+#[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
+pub struct SyncNewAnimBinary(SyncNewAnim);
+impl SyncNewAnimBinary {
+    pub fn new(anim: SyncNewAnim) -> Self {
+        Self(anim)
+    }
+    pub fn anim(&self) -> &SyncNewAnim {
+        &self.0
+    }
+    pub fn into_anim(self) -> SyncNewAnim {
+        self.0
+    }
+}
+impl From<SyncNewAnim> for SyncNewAnimBinary {
+    fn from(value: SyncNewAnim) -> Self {
+        Self(value)
+    }
+}
+
+/// Borrowed counterpart of [`SyncNewAnim`]: the animation, still frame and mask buffers point
+/// into the received bincode buffer of a [`SyncNewAnimBinary`].
+#[derive(Debug, BorrowDecode, Encode)]
+pub struct BorrowedSyncNewAnim<'a> {
+    anim_id: [u8; 16],
+    author_id: [u8; 16],
+    data: &'a [u8],
+    still_frame_jpeg: &'a [u8],
+    fps: u8,
+    frame_width: u32,
+    frame_height: u32,
+    mask_png_buffer: &'a [u8],
+}
+
+impl<'a> BorrowedSyncNewAnim<'a> {
+    pub fn decode_borrowed(buffer: &'a [u8]) -> Result<Self, DecodeLimitError> {
+        borrow_decode_with_limits(buffer, &Self::default_decode_limits())
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn author_id(&self) -> Uuid {
+        Uuid::from_bytes(self.author_id)
+    }
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.data
+    }
+    pub fn mask_data_as_slice(&self) -> &'a [u8] {
+        self.mask_png_buffer
+    }
+    pub fn still_frame_jpeg_as_slice(&self) -> &'a [u8] {
+        self.still_frame_jpeg
+    }
+    /// `received` must be the buffer this was decoded from.
+    pub fn shared_data(&self, received: &Bytes) -> Bytes {
+        received.slice_ref(self.data)
+    }
+    pub fn shared_still_frame_jpeg(&self, received: &Bytes) -> Bytes {
+        received.slice_ref(self.still_frame_jpeg)
+    }
+    pub fn shared_mask_data(&self, received: &Bytes) -> Bytes {
+        received.slice_ref(self.mask_png_buffer)
+    }
+    pub fn frame_width(&self) -> u32 {
+        self.frame_width
+    }
+    pub fn frame_height(&self) -> u32 {
+        self.frame_height
+    }
+    pub fn fps(&self) -> u8 {
+        self.fps
+    }
+}
+impl From<BorrowedSyncNewAnim<'_>> for SyncNewAnim {
+    fn from(value: BorrowedSyncNewAnim<'_>) -> Self {
+        Self {
+            anim_id: value.anim_id,
+            author_id: value.author_id,
+            data: value.data.to_vec(),
+            still_frame_jpeg: value.still_frame_jpeg.to_vec(),
+            fps: value.fps,
+            frame_width: value.frame_width,
+            frame_height: value.frame_height,
+            mask_png_buffer: value.mask_png_buffer.to_vec(),
+        }
+    }
 }
-const QUANTIZE_V: u32 = 65535;
 
 // TODO add these {user_id, contextkind};
+/// The position of an animation is stored once, in `x_pos_world` and `y_pos_world`, world
+/// coordinates (the space of [`CompositionData`] bounds) quantized with `pos_precision`.
+/// Normalized and screen positions are derived from it and never stored. `transform` holds the
+/// size, scale, rotation and depth, its x and y are always 0.
+///
+/// JSON written before the quantized position existed decodes too, its position being read
+/// from `transform`.
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+#[serde(from = "AnimVariableContextFields")]
 pub struct AnimVariableContext {
     //user_id: [u8;16],
     anim_id: [u8; 16],
@@ -202,11 +462,17 @@ pub struct AnimVariableContext {
     context_version: u64,
     variable_context_version: u64,
     //context_kind:
-    x_pos: u32,
-    y_pos: u32,
-    x_pos_screen_coord: u32,
-    y_pos_screen_coord: u32,
+    x_pos_world: i64,
+    y_pos_world: i64,
+    pos_precision: QuantizationPrecision,
     transform: FragmentTransform2DData,
+    #[serde(default, skip_serializing_if = "FragmentStyle::is_default")]
+    style: FragmentStyle,
+    #[serde(default, skip_serializing_if = "PlaybackParams::is_default")]
+    playback: PlaybackParams,
+    /// Moves the placement over time, `transform` is used when there is none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    track: Option<TransformTrack>,
 }
 impl AnimVariableContext {
     pub fn new(
@@ -215,27 +481,16 @@ impl AnimVariableContext {
         context_id: Uuid,
         context_version: u64,
         variable_context_version: u64,
-        x_pos: f32,
-        y_pos: f32,
-        x_pos_screen_coord: f32,
-        y_pos_screen_coord: f32,
+        world_pos: [f32; 2],
     ) -> Self {
-        Self {
-            anim_id: anim_id.into_bytes(),
-            composition_id: composition_id.into_bytes(),
-            context_id: context_id.into_bytes(),
+        Self::new_with_transform(
+            anim_id,
+            composition_id,
+            context_id,
             context_version,
             variable_context_version,
-            x_pos: quantize_01(x_pos, QUANTIZE_V),
-            y_pos: quantize_01(y_pos, QUANTIZE_V),
-            x_pos_screen_coord: quantize(x_pos_screen_coord, QUANTIZE_V),
-            y_pos_screen_coord: quantize(y_pos_screen_coord, QUANTIZE_V),
-            transform: FragmentTransform2DData::from_pos_size(
-                [x_pos_screen_coord, y_pos_screen_coord],
-                0.0,
-                0.0,
-            ),
-        }
+            FragmentTransform2DData::from_pos_size(world_pos, 0.0, 0.0),
+        )
     }
     pub fn new_with_transform(
         anim_id: Uuid,
@@ -243,37 +498,96 @@ impl AnimVariableContext {
         context_id: Uuid,
         context_version: u64,
         variable_context_version: u64,
-        x_pos: f32,
-        y_pos: f32,
         transform: FragmentTransform2DData,
     ) -> Self {
-        let pos = transform.pos();
-        Self {
+        let mut anim_variable_context = Self {
             anim_id: anim_id.into_bytes(),
             composition_id: composition_id.into_bytes(),
             context_id: context_id.into_bytes(),
             context_version,
             variable_context_version,
-            x_pos: quantize_01(x_pos, QUANTIZE_V),
-            y_pos: quantize_01(y_pos, QUANTIZE_V),
-            x_pos_screen_coord: quantize(pos[0], QUANTIZE_V),
-            y_pos_screen_coord: quantize(pos[1], QUANTIZE_V),
-            transform,
-        }
+            x_pos_world: 0,
+            y_pos_world: 0,
+            pos_precision: QuantizationPrecision::default(),
+            transform: FragmentTransform2DData::from_pos_size([0.0, 0.0], 0.0, 0.0),
+            style: FragmentStyle::default(),
+            playback: PlaybackParams::default(),
+            track: None,
+        };
+        anim_variable_context.set_transform(transform);
+        anim_variable_context
+    }
+    /// Quantizes the position again with `pos_precision`.
+    pub fn with_pos_precision(mut self, pos_precision: QuantizationPrecision) -> Self {
+        let world_pos = self.world_pos();
+        self.pos_precision = pos_precision;
+        self.set_world_pos(world_pos);
+        self
     }
-    pub fn pos(&self) -> [f32; 2] {
+    pub fn pos_precision(&self) -> QuantizationPrecision {
+        self.pos_precision
+    }
+    /// Position in world coordinates, within [`QuantizationPrecision::max_error`] of the one
+    /// given.
+    pub fn world_pos(&self) -> [f32; 2] {
         [
-            dequantize_01(self.x_pos, QUANTIZE_V),
-            dequantize_01(self.y_pos, QUANTIZE_V),
+            self.pos_precision.dequantize_signed(self.x_pos_world),
+            self.pos_precision.dequantize_signed(self.y_pos_world),
         ]
     }
-    pub fn pos_screen_coordinates(&self) -> [f32; 2] {
-        let pos = self.transform.pos();
-        [pos[0], pos[1]]
+    /// Position relative to the bounds of `composition`, 0.0..=1.0 inside them.
+    pub fn normalized_pos(&self, composition: &CompositionData) -> [f32; 2] {
+        composition.normalized_from_world(self.world_pos())
+    }
+    /// Position on the screen of `viewport`, in logical pixels.
+    pub fn screen_pos(&self, viewport: &Viewport) -> ScreenPoint {
+        viewport.screen_from_world(self.world_pos().into())
+    }
+    pub fn set_world_pos(&mut self, world_pos: [f32; 2]) {
+        self.x_pos_world = self.pos_precision.quantize_signed(world_pos[0]);
+        self.y_pos_world = self.pos_precision.quantize_signed(world_pos[1]);
+    }
+    /// Splits `transform` between the quantized position and the stored transform.
+    fn set_transform(&mut self, transform: FragmentTransform2DData) {
+        let pos = transform.pos();
+        self.set_world_pos([pos[0], pos[1]]);
+        self.transform = transform.with_pos([0.0, 0.0]);
+    }
+    pub(crate) fn replace_placement(
+        &mut self,
+        transform: FragmentTransform2DData,
+        variable_context_version: u64,
+    ) {
+        self.set_transform(transform);
+        self.variable_context_version = variable_context_version;
     }
+    /// Stored transform, its position is always zero: the placement position is
+    /// [`Self::world_pos`]. See [`Self::world_transform`] for both together.
     pub fn fragment_transform(&self) -> &FragmentTransform2DData {
         &self.transform
     }
+    /// Transform in world coordinates, the stored transform moved to [`Self::world_pos`].
+    pub fn world_transform(&self) -> FragmentTransform2DData {
+        self.transform.clone().with_pos(self.world_pos())
+    }
+    pub fn style(&self) -> &FragmentStyle {
+        &self.style
+    }
+    pub fn set_style(&mut self, style: FragmentStyle) {
+        self.style = style;
+    }
+    pub fn playback(&self) -> &PlaybackParams {
+        &self.playback
+    }
+    pub fn set_playback(&mut self, playback: PlaybackParams) {
+        self.playback = playback;
+    }
+    pub fn track(&self) -> Option<&TransformTrack> {
+        self.track.as_ref()
+    }
+    pub fn set_track(&mut self, track: Option<TransformTrack>) {
+        self.track = track;
+    }
 
     pub fn composition_id(&self) -> Uuid {
         Uuid::from_bytes(self.composition_id)
@@ -290,40 +604,307 @@ impl AnimVariableContext {
     pub fn context_version(&self) -> u64 {
         self.context_version
     }
+    pub fn builder(
+        anim_id: Uuid,
+        composition_id: Uuid,
+        context_id: Uuid,
+    ) -> AnimVariableContextBuilder {
+        AnimVariableContextBuilder::new(anim_id, composition_id, context_id)
+    }
 }
-// N = 65535
-#[inline]
-fn quantize_01(x: f32, n: u32) -> u32 {
-    debug_assert!(n > 0);
-    let x = x.clamp(0.0, 1.0);
-    (x * n as f32).round() as u32
+
+/// Fields of an [`AnimVariableContext`] as found in JSON, the quantized position missing from
+/// data written before it existed.
+#[derive(Deserialize)]
+struct AnimVariableContextFields {
+    anim_id: [u8; 16],
+    composition_id: [u8; 16],
+    context_id: [u8; 16],
+    context_version: u64,
+    variable_context_version: u64,
+    #[serde(default)]
+    x_pos_world: Option<i64>,
+    #[serde(default)]
+    y_pos_world: Option<i64>,
+    #[serde(default)]
+    pos_precision: QuantizationPrecision,
+    transform: FragmentTransform2DData,
+    #[serde(default)]
+    style: FragmentStyle,
+    #[serde(default)]
+    playback: PlaybackParams,
+    #[serde(default)]
+    track: Option<TransformTrack>,
 }
 
-#[inline]
-fn quantize(x: f32, n: u32) -> u32 {
-    debug_assert!(n > 0);
-    if x == 0f32 {
-        0u32
-    } else if x < 0f32 {
-        0u32
-    } else {
-        (x * n as f32).round() as u32
+impl From<AnimVariableContextFields> for AnimVariableContext {
+    fn from(value: AnimVariableContextFields) -> Self {
+        let mut anim_variable_context = Self {
+            anim_id: value.anim_id,
+            composition_id: value.composition_id,
+            context_id: value.context_id,
+            context_version: value.context_version,
+            variable_context_version: value.variable_context_version,
+            x_pos_world: 0,
+            y_pos_world: 0,
+            pos_precision: value.pos_precision,
+            transform: FragmentTransform2DData::from_pos_size([0.0, 0.0], 0.0, 0.0),
+            style: value.style,
+            playback: value.playback,
+            track: value.track,
+        };
+        match (value.x_pos_world, value.y_pos_world) {
+            (Some(x_pos_world), Some(y_pos_world)) => {
+                anim_variable_context.x_pos_world = x_pos_world;
+                anim_variable_context.y_pos_world = y_pos_world;
+                anim_variable_context.transform = value.transform.with_pos([0.0, 0.0]);
+            }
+            _ => anim_variable_context.set_transform(value.transform),
+        }
+        anim_variable_context
     }
 }
-#[inline]
-fn dequantize_01(q: u32, n: u32) -> f32 {
-    if q <= 0 {
-        return 0.0;
-    };
-    q as f32 / n as f32
+
+/// [`AnimVariableContextBuilder`] names each field of an [`AnimVariableContext`].
+/// A position given with `world_pos` or `normalized_pos` replaces the one of `transform`.
+#[derive(Debug, Clone)]
+pub struct AnimVariableContextBuilder {
+    anim_id: Uuid,
+    composition_id: Uuid,
+    context_id: Uuid,
+    context_version: u64,
+    variable_context_version: u64,
+    world_pos: Option<[f32; 2]>,
+    transform: Option<FragmentTransform2DData>,
+    pos_precision: QuantizationPrecision,
+    style: FragmentStyle,
+    playback: PlaybackParams,
+    track: Option<TransformTrack>,
+}
+
+impl AnimVariableContextBuilder {
+    pub fn new(anim_id: Uuid, composition_id: Uuid, context_id: Uuid) -> Self {
+        Self {
+            anim_id,
+            composition_id,
+            context_id,
+            context_version: 0,
+            variable_context_version: 0,
+            world_pos: None,
+            transform: None,
+            pos_precision: QuantizationPrecision::default(),
+            style: FragmentStyle::default(),
+            playback: PlaybackParams::default(),
+            track: None,
+        }
+    }
+    pub fn context_version(mut self, context_version: u64) -> Self {
+        self.context_version = context_version;
+        self
+    }
+    pub fn variable_context_version(mut self, variable_context_version: u64) -> Self {
+        self.variable_context_version = variable_context_version;
+        self
+    }
+    pub fn world_pos(mut self, world_pos: [f32; 2]) -> Self {
+        self.world_pos = Some(world_pos);
+        self
+    }
+    /// Position normalized against the bounds of `composition`.
+    pub fn normalized_pos(
+        mut self,
+        normalized_pos: [f32; 2],
+        composition: &CompositionData,
+    ) -> Self {
+        self.world_pos = Some(composition.world_from_normalized(normalized_pos));
+        self
+    }
+    pub fn transform(mut self, transform: FragmentTransform2DData) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+    pub fn pos_precision(mut self, pos_precision: QuantizationPrecision) -> Self {
+        self.pos_precision = pos_precision;
+        self
+    }
+    pub fn style(mut self, style: FragmentStyle) -> Self {
+        self.style = style;
+        self
+    }
+    pub fn playback(mut self, playback: PlaybackParams) -> Self {
+        self.playback = playback;
+        self
+    }
+    pub fn track(mut self, track: TransformTrack) -> Self {
+        self.track = Some(track);
+        self
+    }
+    pub fn build(self) -> Result<AnimVariableContext, Violations> {
+        let mut violations = Violations::default();
+        let transform = match (self.transform, self.world_pos) {
+            (Some(transform), Some(world_pos)) => transform.with_pos(world_pos),
+            (Some(transform), None) => transform,
+            (None, Some(world_pos)) => FragmentTransform2DData::from_pos_size(world_pos, 0.0, 0.0),
+            (None, None) => {
+                violations.push("transform".to_string(), ViolationKind::Missing);
+                return Err(violations);
+            }
+        };
+        let mut anim_variable_context = AnimVariableContext::new_with_transform(
+            self.anim_id,
+            self.composition_id,
+            self.context_id,
+            self.context_version,
+            self.variable_context_version,
+            FragmentTransform2DData::from_pos_size([0.0, 0.0], 0.0, 0.0),
+        );
+        anim_variable_context.pos_precision = self.pos_precision;
+        anim_variable_context.set_transform(transform);
+        anim_variable_context.style = self.style;
+        anim_variable_context.playback = self.playback;
+        anim_variable_context.track = self.track;
+        anim_variable_context.validate_at("", &mut violations);
+        violations.into_result()?;
+        Ok(anim_variable_context)
+    }
+}
+
+/// Layout of [`AnimVariableContext`] before signed quantization: unsigned screen coordinates
+/// clamped to 0 and a fixed 65535 steps scale. Decode already stored data with it and convert
+/// with `into()`. Peers did not agree on which copy of the position was authoritative, so the
+/// migration keeps the position of `transform`, the copy every reader used, and drops the
+/// quantized copies.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct LegacyAnimVariableContext {
+    anim_id: [u8; 16],
+    composition_id: [u8; 16],
+    context_id: [u8; 16],
+    context_version: u64,
+    variable_context_version: u64,
+    x_pos: u32,
+    y_pos: u32,
+    x_pos_screen_coord: u32,
+    y_pos_screen_coord: u32,
+    transform: FragmentTransform2DData,
+}
+
+impl From<LegacyAnimVariableContext> for AnimVariableContext {
+    fn from(value: LegacyAnimVariableContext) -> Self {
+        let mut anim_variable_context = Self {
+            anim_id: value.anim_id,
+            composition_id: value.composition_id,
+            context_id: value.context_id,
+            context_version: value.context_version,
+            variable_context_version: value.variable_context_version,
+            x_pos_world: 0,
+            y_pos_world: 0,
+            pos_precision: QuantizationPrecision::LEGACY,
+            transform: FragmentTransform2DData::from_pos_size([0.0, 0.0], 0.0, 0.0),
+            style: FragmentStyle::default(),
+            playback: PlaybackParams::default(),
+            track: None,
+        };
+        anim_variable_context.set_transform(value.transform);
+        anim_variable_context
+    }
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+
+/// [`SyncNewAnimResponse`] answers a [`SyncNewAnim`]. When the animation was stored, `stored`
+/// holds what the client needs to update its cache without pulling again.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
 pub struct SyncNewAnimResponse {
     code: usize,
+    #[serde(default)]
+    stored: Option<StoredNewAnim>,
 }
 impl SyncNewAnimResponse {
     pub fn new_with_code(code: usize) -> Self {
-        Self { code }
+        Self { code, stored: None }
+    }
+    pub fn new_stored(code: usize, stored: StoredNewAnim) -> Self {
+        Self {
+            code,
+            stored: Some(stored),
+        }
+    }
+    pub fn code(&self) -> usize {
+        self.code
+    }
+    pub fn stored(&self) -> Option<&StoredNewAnim> {
+        self.stored.as_ref()
+    }
+    pub fn id_mapping(&self) -> IdMapping {
+        self.stored
+            .as_ref()
+            .map(|it| it.id_mapping())
+            .unwrap_or_default()
+    }
+}
+
+/// [`StoredNewAnim`] describes an animation as the server stored it. Clients create animations
+/// with a provisional id (see [`SyncNewAnim::set_anim_id`]), `anim_id` is the canonical one.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub struct StoredNewAnim {
+    provisional_anim_id: [u8; 16],
+    anim_id: [u8; 16],
+    descriptor: RessourcesDescriptorsKind,
+    user_session_version: u64,
+    // context the animation was placed in, with its new version
+    context_version: Option<([u8; 16], u64)>,
+    content_hash: ContentHash,
+}
+impl StoredNewAnim {
+    pub fn new(
+        provisional_anim_id: Uuid,
+        descriptor: RessourcesDescriptorsKind,
+        user_session_version: u64,
+        context_version: Option<(Uuid, u64)>,
+        content_hash: ContentHash,
+    ) -> Self {
+        Self {
+            provisional_anim_id: provisional_anim_id.into_bytes(),
+            anim_id: descriptor.get_id().into_bytes(),
+            descriptor,
+            user_session_version,
+            context_version: context_version.map(|it| (it.0.into_bytes(), it.1)),
+            content_hash,
+        }
+    }
+    pub fn provisional_anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.provisional_anim_id)
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    /// Whether the server replaced the provisional id.
+    pub fn is_reassigned(&self) -> bool {
+        self.provisional_anim_id != self.anim_id
+    }
+    pub fn descriptor(&self) -> &RessourcesDescriptorsKind {
+        &self.descriptor
+    }
+    pub fn user_session_version(&self) -> u64 {
+        self.user_session_version
+    }
+    pub fn context_version(&self) -> Option<(Uuid, u64)> {
+        self.context_version
+            .map(|it| (Uuid::from_bytes(it.0), it.1))
+    }
+    pub fn content_hash(&self) -> &ContentHash {
+        &self.content_hash
+    }
+    pub fn id_mapping(&self) -> IdMapping {
+        let mut mapping = IdMapping::new_empty();
+        mapping.insert(
+            IdKind::Animation,
+            self.provisional_anim_id(),
+            self.anim_id(),
+        );
+        mapping
+    }
+    /// Whether the server stored the same animation data `anim` holds.
+    pub fn matches(&self, anim: &SyncNewAnim) -> bool {
+        self.content_hash == anim.content_hash()
     }
 }
 
@@ -351,3 +932,443 @@ impl AnimationRefToFetch {
         Uuid::from_bytes(self.user_id)
     }
 }
+
+impl DecodeLimited for FetchAnimationToSyncWithItsContexts {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(MAX_ANIMATION_BYTES)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes("anim_raw_bytes", self.anim_raw_bytes.len())?;
+        limits.check_collection("anim_variable_context", self.anim_variable_context.len())
+    }
+}
+impl DecodeLimited for BorrowedFetchAnimationToSyncWithItsContexts<'_> {
+    fn default_decode_limits() -> DecodeLimits {
+        FetchAnimationToSyncWithItsContexts::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes("anim_raw_bytes", self.anim_raw_bytes.len())?;
+        limits.check_collection("anim_variable_context", self.anim_variable_context.len())
+    }
+}
+impl DecodeLimited for FetchAnimationToSyncWithoutContext {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(MAX_ANIMATION_BYTES)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes("anim_raw_bytes", self.anim_raw_bytes.len())
+    }
+}
+impl DecodeLimited for BorrowedFetchAnimationToSyncWithoutContext<'_> {
+    fn default_decode_limits() -> DecodeLimits {
+        FetchAnimationToSyncWithoutContext::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes("anim_raw_bytes", self.anim_raw_bytes.len())
+    }
+}
+impl DecodeLimited for SyncNewAnim {
+    /// Room is left for the still frame, the mask and the header next to an animation of
+    /// [`MAX_ANIMATION_BYTES`].
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(MAX_ANIMATION_BYTES + crate::MIB)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes(
+            "data",
+            self.data.len() + self.still_frame_jpeg.len() + self.mask_png_buffer.len(),
+        )
+    }
+}
+impl DecodeLimited for SyncNewAnimBinary {
+    fn default_decode_limits() -> DecodeLimits {
+        SyncNewAnim::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        self.0.check_decode_limits(limits)
+    }
+}
+impl DecodeLimited for BorrowedSyncNewAnim<'_> {
+    fn default_decode_limits() -> DecodeLimits {
+        SyncNewAnim::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes(
+            "data",
+            self.data.len() + self.still_frame_jpeg.len() + self.mask_png_buffer.len(),
+        )
+    }
+}
+/// Largest bincode encoding of an [`AnimVariableContext`]: ids, versions and position with
+/// their widest varints, the transform, a maximal style and playback, and a track of
+/// [`MAX_KEYFRAMES`] keyframes.
+const MAX_ANIM_VARIABLE_CONTEXT_BYTES: usize = 3 * 16
+    + 2 * 9
+    + 2 * 9
+    + 5
+    + 9 * 4
+    + MAX_FRAGMENT_STYLE_BYTES
+    + MAX_PLAYBACK_PARAMS_BYTES
+    + (1 + 3 + MAX_KEYFRAMES * MAX_KEYFRAME_BYTES);
+
+impl DecodeLimited for AnimVariableContext {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(MAX_ANIM_VARIABLE_CONTEXT_BYTES)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        self.style.check_decode_limits(limits)?;
+        match &self.track {
+            Some(track) => track.check_decode_limits(limits),
+            None => Ok(()),
+        }
+    }
+}
+impl DecodeLimited for AnimationRefToFetch {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for SyncNewAnimResponse {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for ResourcesSet {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("set", self.set.len())
+    }
+}
+
+impl RemapIds for AnimVariableContext {
+    fn remap_ids(&mut self, mapping: &IdMapping) -> usize {
+        mapping.remap_bytes(IdKind::Animation, &mut self.anim_id) as usize
+            + mapping.remap_bytes(IdKind::Composition, &mut self.composition_id) as usize
+            + mapping.remap_bytes(IdKind::Context, &mut self.context_id) as usize
+    }
+}
+
+impl Validate for SyncNewAnim {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_nil(path, "author_id", &self.author_id);
+        violations.check_not_empty(path, "data", self.data.len());
+        violations.check_range(
+            path,
+            "data",
+            self.data.len() as f64,
+            0.0,
+            MAX_ANIMATION_BYTES as f64,
+        );
+        violations.check_not_empty(path, "still_frame_jpeg", self.still_frame_jpeg.len());
+        violations.check_range(path, "fps", self.fps as f64, 1.0, MAX_FPS as f64);
+        violations.check_range(
+            path,
+            "frame_width",
+            self.frame_width as f64,
+            1.0,
+            MAX_FRAME_DIMENSION as f64,
+        );
+        violations.check_range(
+            path,
+            "frame_height",
+            self.frame_height as f64,
+            1.0,
+            MAX_FRAME_DIMENSION as f64,
+        );
+    }
+}
+impl Validate for AnimVariableContext {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_nil(path, "composition_id", &self.composition_id);
+        violations.check_not_nil(path, "context_id", &self.context_id);
+        self.transform
+            .validate_at(&field_path(path, "transform"), violations);
+        let pos = self.transform.pos();
+        if pos[0] != 0.0 || pos[1] != 0.0 {
+            violations.push(
+                field_path(path, "transform.pos"),
+                ViolationKind::Invalid("position is stored in x_pos_world and y_pos_world"),
+            );
+        }
+        self.style
+            .validate_at(&field_path(path, "style"), violations);
+        self.playback
+            .validate_at(&field_path(path, "playback"), violations);
+        if let Some(track) = &self.track {
+            track.validate_at(&field_path(path, "track"), violations);
+        }
+    }
+}
+impl Validate for FetchAnimationToSyncWithItsContexts {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_nil(path, "composition_id", &self.composition_id);
+        violations.check_not_empty(path, "anim_raw_bytes", self.anim_raw_bytes.len());
+        violations.check_items(path, "anim_variable_context", &self.anim_variable_context);
+    }
+}
+impl Validate for FetchAnimationToSyncWithoutContext {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_empty(path, "anim_raw_bytes", self.anim_raw_bytes.len());
+    }
+}
+impl Validate for AnimationRefToFetch {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_nil(path, "context_id", &self.context_id);
+        violations.check_not_nil(path, "user_id", &self.user_id);
+    }
+}
+
+#[cfg(test)]
+mod anim_variable_context_test {
+    use uuid::Uuid;
+
+    use std::time::Duration;
+
+    use crate::{
+        AnimVariableContext, BlendMode, ClipShape, CompositionData, CropRect, Easing,
+        FragmentStyle, FragmentTransform2DData, Keyframe, LegacyAnimVariableContext, LoopMode,
+        MAX_CLIP_POLYGON_POINTS, MAX_KEYFRAMES, MAX_PLAYBACK_SPEED, PlaybackParams,
+        QuantizationPrecision, TransformTrack, Validate, ViolationKind, decode_with_default_limits,
+    };
+
+    #[test]
+    fn legacy_placement_migrates_to_its_transform() {
+        let config = bincode::config::standard();
+        let transform = FragmentTransform2DData::from_pos_size([-120.5, 48.0], 64.0, 32.0);
+        // The quantized copies disagree with the transform, as written by older clients.
+        let mut encoded = vec![];
+        for id in [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()] {
+            encoded.extend_from_slice(id.as_bytes());
+        }
+        encoded.extend(bincode::encode_to_vec((3u64, 7u64), config).unwrap());
+        encoded.extend(bincode::encode_to_vec((65535u32, 0u32, 0u32, 9u32), config).unwrap());
+        encoded.extend(bincode::encode_to_vec(&transform, config).unwrap());
+
+        let (legacy, _): (LegacyAnimVariableContext, usize) =
+            bincode::decode_from_slice(&encoded, config).unwrap();
+        let migrated = AnimVariableContext::from(legacy);
+        let max_error = migrated.pos_precision().max_error();
+        let [x, y] = migrated.world_pos();
+        assert!((x + 120.5).abs() <= max_error && (y - 48.0).abs() <= max_error);
+        assert!(migrated.fragment_transform().dimensions() == transform.dimensions());
+        assert!(migrated.variable_context_version() == 7);
+        assert!(migrated.validate().is_ok());
+
+        let composition = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7())
+            .min_x(-200.0)
+            .max_x(200.0)
+            .min_y(0.0)
+            .max_y(96.0)
+            .build()
+            .unwrap();
+        let [x, y] = migrated.normalized_pos(&composition);
+        assert!((x - 0.19875).abs() < 1e-6 && (y - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn baseline_json_decodes_and_wide_positions_round_trip() {
+        let ids = format!("{:?}", [7u8; 16]);
+        // Layout of the baseline: quantized copies next to the transform, which wins.
+        let json = format!(
+            r#"{{"anim_id":{ids},"composition_id":{ids},"context_id":{ids},
+            "context_version":3,"variable_context_version":7,
+            "x_pos":65535,"y_pos":0,"x_pos_screen_coord":0,"y_pos_screen_coord":9,
+            "transform":{{"pos":[-120.5,48.0,0.0],"dimensions":[64.0,32.0],
+            "scale":[1.0,1.0],"rot":0.0,"z":0.0}}}}"#
+        );
+        let decoded: AnimVariableContext = serde_json::from_str(&json).unwrap();
+        let max_error = decoded.pos_precision().max_error();
+        let [x, y] = decoded.world_pos();
+        assert!((x + 120.5).abs() <= max_error && (y - 48.0).abs() <= max_error);
+        assert!(decoded.fragment_transform().dimensions() == [64.0, 32.0]);
+        assert!(decoded.validate().is_ok());
+
+        // Far outside the 0..=65537 of the baseline layout, on both sides.
+        let wide = AnimVariableContext::new(
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            0,
+            0,
+            [-250_000.5, 1_000_000.25],
+        );
+        let [x, y] = wide.world_pos();
+        assert!((x + 250_000.5).abs() < 0.1 && (y - 1_000_000.25).abs() < 0.1);
+        let json = serde_json::to_string(&wide).unwrap();
+        assert!(serde_json::from_str::<AnimVariableContext>(&json).unwrap() == wide);
+        let config = bincode::config::standard();
+        let encoded = bincode::encode_to_vec(&wide, config).unwrap();
+        let (decoded, _): (AnimVariableContext, usize) =
+            bincode::decode_from_slice(&encoded, config).unwrap();
+        assert!(decoded == wide);
+    }
+
+    #[test]
+    fn maximal_placement_decodes_with_default_limits() {
+        let polygon: Vec<[f32; 2]> = (0..MAX_CLIP_POLYGON_POINTS)
+            .map(|it| [0.99, it as f32 / MAX_CLIP_POLYGON_POINTS as f32])
+            .collect();
+        let style = FragmentStyle::default()
+            .with_opacity(0.5)
+            .with_blend_mode(BlendMode::Overlay)
+            .with_tint([200; 4])
+            .with_crop(CropRect::new(4096, 4096, 4096, 4096))
+            .with_clip(ClipShape::polygon(&polygon));
+        let long = Duration::from_millis(u32::MAX as u64);
+        let playback = PlaybackParams::default()
+            .with_loop_mode(LoopMode::PingPong)
+            .with_speed(MAX_PLAYBACK_SPEED)
+            .with_start_offset(long)
+            .with_trim(u32::MAX - 1, Some(u32::MAX))
+            .with_delay(long);
+        let transform =
+            FragmentTransform2DData::new([1e6, -1e6, 1.5], [4096.5, 4096.5], [2.5, 2.5], 3.5, 1.5);
+        let keyframes = (0..MAX_KEYFRAMES as u64)
+            .map(|it| {
+                Keyframe::new(
+                    long - Duration::from_millis(it),
+                    transform.clone(),
+                    Easing::cubic_bezier(0.5, -0.5, 0.5, 1.5),
+                )
+            })
+            .collect();
+        let placement =
+            AnimVariableContext::builder(Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7())
+                .context_version(u64::MAX)
+                .variable_context_version(u64::MAX)
+                .pos_precision(QuantizationPrecision::LEGACY)
+                .transform(transform)
+                .style(style)
+                .playback(playback)
+                .track(TransformTrack::new(keyframes).unwrap())
+                .build()
+                .unwrap();
+
+        let encoded = bincode::encode_to_vec(&placement, bincode::config::standard()).unwrap();
+        let decoded: AnimVariableContext = decode_with_default_limits(&encoded).unwrap();
+        assert!(decoded == placement);
+    }
+
+    #[test]
+    fn builder_requires_a_position_and_reports_invalid_fields() {
+        let builder =
+            || AnimVariableContext::builder(Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+        let violations = builder().context_version(3).build().unwrap_err();
+        let violation = violations.iter().next().unwrap();
+        assert!(violations.len() == 1);
+        assert!(
+            violation.field_path() == "transform" && violation.kind() == &ViolationKind::Missing
+        );
+
+        // `world_pos` alone places the animation, and replaces the position of `transform`.
+        let placed = builder().world_pos([12.0, -8.0]).build().unwrap();
+        let max_error = placed.pos_precision().max_error();
+        let [x, y] = placed.world_pos();
+        assert!((x - 12.0).abs() <= max_error && (y + 8.0).abs() <= max_error);
+        let transform = FragmentTransform2DData::from_pos_size([100.0, 100.0], 64.0, 32.0);
+        let placed = builder()
+            .transform(transform)
+            .world_pos([12.0, -8.0])
+            .build()
+            .unwrap();
+        assert!(placed.world_pos() == [x, y]);
+        assert!(placed.fragment_transform().dimensions() == [64.0, 32.0]);
+
+        let violations = AnimVariableContext::builder(Uuid::nil(), Uuid::now_v7(), Uuid::now_v7())
+            .world_pos([0.0, 0.0])
+            .playback(PlaybackParams::default().with_speed(0.0))
+            .build()
+            .unwrap_err();
+        let paths: Vec<_> = violations.iter().map(|it| it.field_path()).collect();
+        assert!(paths == ["anim_id", "playback.speed"]);
+    }
+}
+
+#[cfg(test)]
+mod sync_new_anim_test {
+    use uuid::Uuid;
+
+    use crate::{
+        BorrowedSyncNewAnim, MAX_ANIMATION_BYTES, MAX_FRAME_DIMENSION, SyncNewAnim, ViolationKind,
+        decode_with_default_limits,
+    };
+
+    #[test]
+    fn owned_and_borrowed_decoding_agree() {
+        let anim = SyncNewAnim::builder(Uuid::now_v7(), Uuid::now_v7())
+            .data(vec![1, 2, 3, 4])
+            .still_frame_jpeg(vec![5, 6])
+            .mask_png_buffer(vec![7])
+            .fps(24)
+            .frame_width(64)
+            .frame_height(32)
+            .build()
+            .unwrap();
+        // Same encoding as the request body.
+        let encoded = bincode::encode_to_vec(&anim, bincode::config::standard()).unwrap();
+        let owned: SyncNewAnim = decode_with_default_limits(&encoded).unwrap();
+        let borrowed = BorrowedSyncNewAnim::decode_borrowed(&encoded).unwrap();
+        assert!(borrowed.as_slice() == [1, 2, 3, 4]);
+        assert!(owned == anim && SyncNewAnim::from(borrowed) == owned);
+    }
+
+    #[test]
+    fn largest_animation_decodes_with_default_limits() {
+        let builder = |len| {
+            SyncNewAnim::builder(Uuid::now_v7(), Uuid::now_v7())
+                .data(vec![0; len])
+                .still_frame_jpeg(vec![5; 4096])
+                .fps(24)
+                .frame_width(64)
+                .frame_height(32)
+                .build()
+        };
+        let anim = builder(MAX_ANIMATION_BYTES).unwrap();
+        let encoded = bincode::encode_to_vec(&anim, bincode::config::standard()).unwrap();
+        assert!(encoded.len() > MAX_ANIMATION_BYTES);
+        assert!(decode_with_default_limits::<SyncNewAnim>(&encoded).unwrap() == anim);
+
+        let violations = builder(MAX_ANIMATION_BYTES + 1).unwrap_err();
+        let paths: Vec<_> = violations.iter().map(|it| it.field_path()).collect();
+        assert!(paths == ["data"]);
+    }
+
+    #[test]
+    fn builder_reports_missing_and_invalid_fields() {
+        let violations = SyncNewAnim::builder(Uuid::now_v7(), Uuid::now_v7())
+            .data(vec![1])
+            .still_frame_jpeg(vec![2])
+            .fps(24)
+            .build()
+            .unwrap_err();
+        let paths: Vec<_> = violations.iter().map(|it| it.field_path()).collect();
+        assert!(paths == ["frame_width", "frame_height"]);
+        assert!(
+            violations
+                .iter()
+                .all(|it| it.kind() == &ViolationKind::Missing)
+        );
+
+        let violations = SyncNewAnim::builder(Uuid::nil(), Uuid::now_v7())
+            .fps(0)
+            .frame_width(64)
+            .frame_height(MAX_FRAME_DIMENSION + 1)
+            .build()
+            .unwrap_err();
+        let paths: Vec<_> = violations.iter().map(|it| it.field_path()).collect();
+        assert!(paths == ["anim_id", "data", "still_frame_jpeg", "fps", "frame_height"]);
+    }
+}
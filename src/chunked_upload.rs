@@ -0,0 +1,537 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    DecodeLimitError, DecodeLimited, DecodeLimits, MAX_ANIMATION_BYTES, MAX_UPLOAD_CHUNK_BYTES,
+    Validate, ViolationKind, Violations, field_path, indexed_path,
+};
+
+/// sha256 of a full animation payload.
+pub type ContentHash = [u8; 32];
+
+/// Computes the [`ContentHash`] the server checks against on [`UploadFinalize`].
+pub fn content_hash(data: &[u8]) -> ContentHash {
+    Sha256::digest(data).into()
+}
+
+/// [`UploadSessionInit`] opens a resumable upload for an animation payload. The client keeps
+/// the `session_id` so that an interrupted upload can ask for an [`UploadStatus`] and only send
+/// the missing chunks.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq)]
+pub struct UploadSessionInit {
+    session_id: [u8; 16],
+    anim_id: [u8; 16],
+    total_size: u64,
+    content_hash: ContentHash,
+    chunk_size: u32,
+}
+
+impl UploadSessionInit {
+    pub fn new(anim_id: Uuid, total_size: u64, content_hash: ContentHash, chunk_size: u32) -> Self {
+        Self {
+            session_id: Uuid::now_v7().into_bytes(),
+            anim_id: anim_id.into_bytes(),
+            total_size,
+            content_hash,
+            chunk_size,
+        }
+    }
+    /// Hashes `data` and opens a session covering all of it.
+    pub fn new_for_payload(anim_id: Uuid, data: &[u8], chunk_size: u32) -> Self {
+        Self::new(anim_id, data.len() as u64, content_hash(data), chunk_size)
+    }
+    pub fn session_id(&self) -> Uuid {
+        Uuid::from_bytes(self.session_id)
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+    pub fn content_hash(&self) -> &ContentHash {
+        &self.content_hash
+    }
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+    pub fn chunk_count(&self) -> u64 {
+        if self.chunk_size == 0 {
+            return 0;
+        }
+        self.total_size.div_ceil(self.chunk_size as u64)
+    }
+    /// Splits `data` into the chunks of this session, restricted to the given byte ranges.
+    /// Pass [`UploadStatus::missing_ranges`] to resume an interrupted upload.
+    pub fn chunks_for_ranges(&self, data: &[u8], ranges: &[(u64, u64)]) -> Vec<UploadChunk> {
+        let chunk_size = self.chunk_size.max(1) as u64;
+        let mut chunks = vec![];
+        for (start, end) in ranges {
+            let end = (*end).min(data.len() as u64);
+            let mut offset = *start;
+            while offset < end {
+                let chunk_end = (offset + chunk_size).min(end);
+                chunks.push(UploadChunk::new(
+                    self.session_id(),
+                    offset,
+                    data[offset as usize..chunk_end as usize].to_vec(),
+                ));
+                offset = chunk_end;
+            }
+        }
+        chunks
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq)]
+pub struct UploadChunk {
+    session_id: [u8; 16],
+    offset: u64,
+    bytes: Vec<u8>,
+}
+
+impl UploadChunk {
+    pub fn new(session_id: Uuid, offset: u64, bytes: Vec<u8>) -> Self {
+        Self {
+            session_id: session_id.into_bytes(),
+            offset,
+            bytes,
+        }
+    }
+    pub fn session_id(&self) -> Uuid {
+        Uuid::from_bytes(self.session_id)
+    }
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// [`UploadStatus`] lists the byte ranges the server already holds for a session.
+/// Ranges are half-open (`start..end`), sorted and never overlap.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq)]
+pub struct UploadStatus {
+    session_id: [u8; 16],
+    total_size: u64,
+    received_ranges: Vec<(u64, u64)>,
+}
+
+impl UploadStatus {
+    pub fn new(session_id: Uuid, total_size: u64, received_ranges: Vec<(u64, u64)>) -> Self {
+        Self {
+            session_id: session_id.into_bytes(),
+            total_size,
+            received_ranges,
+        }
+    }
+    pub fn session_id(&self) -> Uuid {
+        Uuid::from_bytes(self.session_id)
+    }
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+    pub fn received_ranges(&self) -> &[(u64, u64)] {
+        &self.received_ranges
+    }
+    /// Inverted ranges count for nothing, see [`Validate`] to reject them.
+    pub fn received_len(&self) -> u64 {
+        self.received_ranges
+            .iter()
+            .map(|(s, e)| e.saturating_sub(*s))
+            .fold(0, u64::saturating_add)
+    }
+    pub fn is_complete(&self) -> bool {
+        self.received_len() == self.total_size
+    }
+    /// The ranges the client still has to send.
+    pub fn missing_ranges(&self) -> Vec<(u64, u64)> {
+        let mut missing = vec![];
+        let mut cursor = 0u64;
+        for (start, end) in &self.received_ranges {
+            if *start > cursor {
+                missing.push((cursor, *start));
+            }
+            cursor = cursor.max(*end);
+        }
+        if cursor < self.total_size {
+            missing.push((cursor, self.total_size));
+        }
+        missing
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode, PartialEq)]
+pub struct UploadFinalize {
+    session_id: [u8; 16],
+    content_hash: ContentHash,
+}
+
+impl UploadFinalize {
+    pub fn new(session_id: Uuid, content_hash: ContentHash) -> Self {
+        Self {
+            session_id: session_id.into_bytes(),
+            content_hash,
+        }
+    }
+    pub fn session_id(&self) -> Uuid {
+        Uuid::from_bytes(self.session_id)
+    }
+    pub fn content_hash(&self) -> &ContentHash {
+        &self.content_hash
+    }
+}
+
+/// [`UploadReassemblyBuffer`] collects the chunks of one [`UploadSessionInit`], tracks which
+/// ranges were received and checks the final hash. Chunks may arrive in any order and may be
+/// sent more than once.
+#[derive(Debug)]
+pub struct UploadReassemblyBuffer {
+    init: UploadSessionInit,
+    buffer: Vec<u8>,
+    received_ranges: Vec<(u64, u64)>,
+}
+
+impl UploadReassemblyBuffer {
+    /// Allocates the whole payload up front, so `total_size` is held to
+    /// [`MAX_ANIMATION_BYTES`].
+    pub fn new(init: UploadSessionInit) -> Result<Self, UploadError> {
+        if init.total_size > MAX_ANIMATION_BYTES as u64 {
+            return Err(UploadError::TooLarge {
+                total_size: init.total_size,
+                max: MAX_ANIMATION_BYTES,
+            });
+        }
+        Ok(Self {
+            buffer: vec![0u8; init.total_size as usize],
+            init,
+            received_ranges: vec![],
+        })
+    }
+    pub fn session_init(&self) -> &UploadSessionInit {
+        &self.init
+    }
+    pub fn write_chunk(&mut self, chunk: &UploadChunk) -> Result<(), UploadError> {
+        if chunk.session_id != self.init.session_id {
+            return Err(UploadError::SessionMismatch {
+                expected: self.init.session_id(),
+                received: chunk.session_id(),
+            });
+        }
+        if chunk.len() > self.init.chunk_size as usize {
+            return Err(UploadError::ChunkTooLarge {
+                len: chunk.len(),
+                chunk_size: self.init.chunk_size,
+            });
+        }
+        let start = chunk.offset;
+        let end = start
+            .checked_add(chunk.len() as u64)
+            .filter(|end| *end <= self.init.total_size)
+            .ok_or(UploadError::ChunkOutOfBounds {
+                offset: start,
+                len: chunk.len(),
+                total_size: self.init.total_size,
+            })?;
+        if start == end {
+            return Ok(());
+        }
+        self.buffer[start as usize..end as usize].copy_from_slice(chunk.bytes());
+        self.insert_range(start, end);
+        Ok(())
+    }
+    fn insert_range(&mut self, start: u64, end: u64) {
+        let mut merged = (start, end);
+        let mut ranges = Vec::with_capacity(self.received_ranges.len() + 1);
+        for range in self.received_ranges.drain(..) {
+            if range.1 < merged.0 || range.0 > merged.1 {
+                ranges.push(range);
+            } else {
+                merged = (merged.0.min(range.0), merged.1.max(range.1));
+            }
+        }
+        ranges.push(merged);
+        ranges.sort_unstable();
+        self.received_ranges = ranges;
+    }
+    pub fn status(&self) -> UploadStatus {
+        UploadStatus::new(
+            self.init.session_id(),
+            self.init.total_size,
+            self.received_ranges.clone(),
+        )
+    }
+    pub fn is_complete(&self) -> bool {
+        self.init.total_size == 0 || self.received_ranges == [(0, self.init.total_size)]
+    }
+    /// Consumes the buffer and returns the payload once every byte was received and the hash
+    /// matches both the one announced at init and the one sent with `finalize`.
+    pub fn finalize(self, finalize: &UploadFinalize) -> Result<Vec<u8>, UploadError> {
+        if finalize.session_id != self.init.session_id {
+            return Err(UploadError::SessionMismatch {
+                expected: self.init.session_id(),
+                received: finalize.session_id(),
+            });
+        }
+        if !self.is_complete() {
+            return Err(UploadError::Incomplete {
+                missing_ranges: self.status().missing_ranges(),
+            });
+        }
+        let hash = content_hash(&self.buffer);
+        if hash != self.init.content_hash || hash != finalize.content_hash {
+            return Err(UploadError::HashMismatch);
+        }
+        Ok(self.buffer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UploadError {
+    TooLarge {
+        total_size: u64,
+        max: usize,
+    },
+    SessionMismatch {
+        expected: Uuid,
+        received: Uuid,
+    },
+    ChunkTooLarge {
+        len: usize,
+        chunk_size: u32,
+    },
+    ChunkOutOfBounds {
+        offset: u64,
+        len: usize,
+        total_size: u64,
+    },
+    Incomplete {
+        missing_ranges: Vec<(u64, u64)>,
+    },
+    HashMismatch,
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge { total_size, max } => {
+                write!(
+                    f,
+                    "UploadError TooLarge [{}] bytes, max [{}]",
+                    total_size, max
+                )
+            }
+            Self::SessionMismatch { expected, received } => {
+                write!(
+                    f,
+                    "UploadError SessionMismatch expected [{}] received [{}]",
+                    expected, received
+                )
+            }
+            Self::ChunkTooLarge { len, chunk_size } => {
+                write!(
+                    f,
+                    "UploadError ChunkTooLarge [{}] bytes for a chunk size of [{}]",
+                    len, chunk_size
+                )
+            }
+            Self::ChunkOutOfBounds {
+                offset,
+                len,
+                total_size,
+            } => {
+                write!(
+                    f,
+                    "UploadError ChunkOutOfBounds offset [{}] len [{}] total size [{}]",
+                    offset, len, total_size
+                )
+            }
+            Self::Incomplete { missing_ranges } => {
+                write!(f, "UploadError Incomplete missing [{:?}]", missing_ranges)
+            }
+            Self::HashMismatch => write!(f, "UploadError HashMismatch"),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+impl DecodeLimited for UploadSessionInit {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    /// `total_size` and `chunk_size` drive the [`UploadReassemblyBuffer`] allocation, so they
+    /// are held to the product limits whatever `limits` says.
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        if self.total_size > MAX_ANIMATION_BYTES as u64 {
+            return Err(DecodeLimitError::TotalBytesExceeded {
+                field: "total_size",
+                len: self.total_size as usize,
+                max: MAX_ANIMATION_BYTES,
+            });
+        }
+        if self.chunk_size as usize > MAX_UPLOAD_CHUNK_BYTES {
+            return Err(DecodeLimitError::TotalBytesExceeded {
+                field: "chunk_size",
+                len: self.chunk_size as usize,
+                max: MAX_UPLOAD_CHUNK_BYTES,
+            });
+        }
+        Ok(())
+    }
+}
+impl DecodeLimited for UploadChunk {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(MAX_UPLOAD_CHUNK_BYTES + 64)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes("bytes", self.bytes.len())
+    }
+}
+impl DecodeLimited for UploadStatus {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_collection_len(4096)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("received_ranges", self.received_ranges.len())
+    }
+}
+impl DecodeLimited for UploadFinalize {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+
+impl Validate for UploadSessionInit {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "session_id", &self.session_id);
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_range(
+            path,
+            "total_size",
+            self.total_size as f64,
+            1.0,
+            MAX_ANIMATION_BYTES as f64,
+        );
+        violations.check_range(
+            path,
+            "chunk_size",
+            self.chunk_size as f64,
+            1.0,
+            MAX_UPLOAD_CHUNK_BYTES as f64,
+        );
+    }
+}
+impl Validate for UploadChunk {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "session_id", &self.session_id);
+        violations.check_not_empty(path, "bytes", self.bytes.len());
+    }
+}
+impl Validate for UploadStatus {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "session_id", &self.session_id);
+        let mut cursor = 0u64;
+        for (index, (start, end)) in self.received_ranges.iter().enumerate() {
+            if start >= end || *start < cursor || *end > self.total_size {
+                violations.push(
+                    indexed_path(path, "received_ranges", index),
+                    ViolationKind::Invalid("ranges must be sorted, disjoint and within total_size"),
+                );
+            }
+            cursor = *end;
+        }
+    }
+}
+impl Validate for UploadFinalize {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "session_id", &self.session_id);
+        if self.content_hash == [0u8; 32] {
+            violations.push(field_path(path, "content_hash"), ViolationKind::Empty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod chunked_upload_test {
+    use uuid::Uuid;
+
+    use crate::{
+        MAX_ANIMATION_BYTES, UploadError, UploadFinalize, UploadReassemblyBuffer,
+        UploadSessionInit, UploadStatus, Validate,
+    };
+
+    #[test]
+    fn reassembly_out_of_order_with_resume() {
+        let data: Vec<u8> = (0..1000u32).map(|it| (it % 251) as u8).collect();
+        let init = UploadSessionInit::new_for_payload(Uuid::now_v7(), &data, 128);
+        let chunks = init.chunks_for_ranges(&data, &[(0, data.len() as u64)]);
+        assert_eq!(chunks.len() as u64, init.chunk_count());
+
+        let mut buffer = UploadReassemblyBuffer::new(init.clone()).unwrap();
+        for chunk in chunks.iter().rev().step_by(2) {
+            assert!(buffer.write_chunk(chunk).is_ok());
+        }
+        assert!(!buffer.is_complete());
+
+        let status = buffer.status();
+        for chunk in init.chunks_for_ranges(&data, &status.missing_ranges()) {
+            assert!(buffer.write_chunk(&chunk).is_ok());
+        }
+        assert!(buffer.status().is_complete());
+
+        let finalize = UploadFinalize::new(init.session_id(), *init.content_hash());
+        assert!(buffer.finalize(&finalize) == Ok(data));
+    }
+
+    #[test]
+    fn reassembly_rejects_corrupted_payload() {
+        let data = vec![7u8; 300];
+        let init = UploadSessionInit::new_for_payload(Uuid::now_v7(), &data, 100);
+        let mut corrupted = data.clone();
+        corrupted[150] = 8;
+
+        let mut buffer = UploadReassemblyBuffer::new(init.clone()).unwrap();
+        for chunk in init.chunks_for_ranges(&corrupted, &[(0, 300)]) {
+            assert!(buffer.write_chunk(&chunk).is_ok());
+        }
+        let finalize = UploadFinalize::new(init.session_id(), *init.content_hash());
+        assert!(buffer.finalize(&finalize) == Err(UploadError::HashMismatch));
+    }
+
+    #[test]
+    fn reassembly_rejects_oversized_sessions() {
+        let total_size = MAX_ANIMATION_BYTES as u64 + 1;
+        let init = UploadSessionInit::new(Uuid::now_v7(), total_size, [1u8; 32], 1024);
+        assert!(
+            UploadReassemblyBuffer::new(init).err()
+                == Some(UploadError::TooLarge {
+                    total_size,
+                    max: MAX_ANIMATION_BYTES
+                })
+        );
+        let init = UploadSessionInit::new(Uuid::now_v7(), u64::MAX, [1u8; 32], 1024);
+        assert!(UploadReassemblyBuffer::new(init).is_err());
+    }
+
+    #[test]
+    fn inverted_server_ranges_do_not_underflow() {
+        let status = UploadStatus::new(Uuid::now_v7(), 100, vec![(0, 10), (50, 20)]);
+        assert!(status.received_len() == 10 && !status.is_complete());
+        assert!(status.validate().is_err());
+        let status = UploadStatus::new(Uuid::now_v7(), u64::MAX, vec![(0, u64::MAX), (0, 1)]);
+        assert!(status.received_len() == u64::MAX);
+    }
+}
@@ -1,10 +1,20 @@
 #[cfg(feature = "client-side")]
 use crate::UserLoginResponseErrorKind;
 #[cfg(feature = "client-side")]
-use crate::animation_to_sync::SyncNewAnim;
+use crate::animation_batch::SyncNewAnimBatch;
+#[cfg(feature = "client-side")]
+use crate::animation_mutations::{DeleteAnimation, RemoveAnimFromComposition, UpdateAnimPlacement};
+#[cfg(feature = "client-side")]
+use crate::animation_to_sync::{SyncNewAnim, SyncNewAnimBinary};
+#[cfg(feature = "client-side")]
+use crate::chunked_upload::{UploadChunk, UploadFinalize, UploadSessionInit};
+#[cfg(feature = "client-side")]
+use crate::composition_ops::CompositionOpsBatch;
 #[cfg(feature = "client-side")]
 use crate::context_sync::LastClientContextVersion;
 #[cfg(feature = "client-side")]
+use crate::media_ressources::SyncNewRessource;
+#[cfg(feature = "client-side")]
 use crate::signin::SigninResponseData;
 #[cfg(feature = "client-side")]
 use crate::signup::SignupResponseData;
@@ -35,6 +45,102 @@ impl IntoBodyReq for SyncNewAnim {
         ContentType::Json
     }
 }
+/// Sent as bincode so that the server can decode it as a
+/// [`crate::BorrowedSyncNewAnim`] without copying the buffers.
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for SyncNewAnimBinary {
+    fn into_bytes(self) -> Vec<u8> {
+        bincode::encode_to_vec(&self, bincode::config::standard()).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Binary
+    }
+}
+/// Sent as bincode, like every request carrying a payload.
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for SyncNewRessource {
+    fn into_bytes(self) -> Vec<u8> {
+        bincode::encode_to_vec(&self, bincode::config::standard()).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Binary
+    }
+}
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for CompositionOpsBatch {
+    fn into_bytes(self) -> Vec<u8> {
+        serde_json::to_vec(&self).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Json
+    }
+}
+/// Sent as bincode, for the server to decode it with the [`crate::DecodeLimited`] bounds of
+/// the batch.
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for SyncNewAnimBatch {
+    fn into_bytes(self) -> Vec<u8> {
+        bincode::encode_to_vec(&self, bincode::config::standard()).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Binary
+    }
+}
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for UpdateAnimPlacement {
+    fn into_bytes(self) -> Vec<u8> {
+        serde_json::to_vec(&self).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Json
+    }
+}
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for RemoveAnimFromComposition {
+    fn into_bytes(self) -> Vec<u8> {
+        serde_json::to_vec(&self).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Json
+    }
+}
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for DeleteAnimation {
+    fn into_bytes(self) -> Vec<u8> {
+        serde_json::to_vec(&self).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Json
+    }
+}
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for UploadSessionInit {
+    fn into_bytes(self) -> Vec<u8> {
+        serde_json::to_vec(&self).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Json
+    }
+}
+/// Sent as bincode: a JSON array of numbers would inflate the chunk several times over.
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for UploadChunk {
+    fn into_bytes(self) -> Vec<u8> {
+        bincode::encode_to_vec(&self, bincode::config::standard()).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Binary
+    }
+}
+#[cfg(feature = "client-side")]
+impl IntoBodyReq for UploadFinalize {
+    fn into_bytes(self) -> Vec<u8> {
+        serde_json::to_vec(&self).unwrap()
+    }
+    fn content_type(&self) -> ContentType {
+        ContentType::Json
+    }
+}
 #[cfg(feature = "client-side")]
 impl IntoBodyReq for LastClientContextVersion {
     fn into_bytes(self) -> Vec<u8> {
@@ -89,3 +195,58 @@ impl IntoBodyReq for UserLoginResponseErrorKind {
         ContentType::Json
     }
 }
+
+#[cfg(all(test, feature = "client-side"))]
+mod client_trait_impl_test {
+    use faces_quic_client::IntoBodyReq;
+    use uuid::Uuid;
+
+    use crate::{
+        BorrowedSyncNewAnim, ImageRessource, SyncNewAnim, SyncNewAnimBatch, SyncNewAnimBatchItem,
+        SyncNewAnimBinary, SyncNewRessource, UploadChunk, decode_with_default_limits,
+    };
+
+    #[test]
+    fn payload_bodies_decode_with_default_limits() {
+        let anim = SyncNewAnim::builder(Uuid::now_v7(), Uuid::now_v7())
+            .data(vec![1, 2, 3])
+            .still_frame_jpeg(vec![4])
+            .fps(24)
+            .frame_width(64)
+            .frame_height(32)
+            .build()
+            .unwrap();
+        // The existing endpoint keeps its JSON body.
+        let json = anim.clone().into_bytes();
+        assert!(serde_json::from_slice::<SyncNewAnim>(&json).unwrap() == anim);
+
+        let body = SyncNewAnimBinary::from(anim.clone()).into_bytes();
+        let decoded: SyncNewAnim = decode_with_default_limits(&body).unwrap();
+        assert!(decoded == anim);
+        let borrowed = BorrowedSyncNewAnim::decode_borrowed(&body).unwrap();
+        assert!(borrowed.as_slice() == [1, 2, 3]);
+
+        let batch = SyncNewAnimBatch::new(vec![SyncNewAnimBatchItem::new(anim, None)]);
+        let batch_id = batch.batch_id();
+        let decoded: SyncNewAnimBatch = decode_with_default_limits(&batch.into_bytes()).unwrap();
+        assert!(decoded.batch_id() == batch_id && decoded.len() == 1);
+
+        let data = vec![9u8; 256];
+        let image = ImageRessource::new(
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            16,
+            16,
+            &data,
+            "image/png".to_string(),
+        );
+        let upload = SyncNewRessource::new_image(image, data.clone());
+        let ressource_id = upload.ressource_id();
+        let decoded: SyncNewRessource = decode_with_default_limits(&upload.into_bytes()).unwrap();
+        assert!(decoded.ressource_id() == ressource_id && decoded.as_slice() == data);
+
+        let chunk = UploadChunk::new(Uuid::now_v7(), 128, data);
+        let decoded: UploadChunk = decode_with_default_limits(&chunk.clone().into_bytes()).unwrap();
+        assert!(decoded == chunk);
+    }
+}
@@ -0,0 +1,416 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    CompositionBounds, CompositionData, CompositionGroup, CompositionLayer,
+    CompositionResourceData, DecodeLimitError, DecodeLimited, DecodeLimits, FragmentStyle,
+    FragmentTransform2DData, Validate, ViolationKind, Violations, ZOrderKey, field_path,
+    indexed_path,
+};
+
+/// One change of a resource between two versions of a composition. Changes carry the previous
+/// value so that clients can animate the transition and detect a diff made against another
+/// version.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub enum CompositionResourceChange {
+    Added(CompositionResourceData),
+    Removed {
+        resource_id: [u8; 16],
+    },
+    /// Moved, resized, scaled or turned.
+    Transformed {
+        resource_id: [u8; 16],
+        from: FragmentTransform2DData,
+        to: FragmentTransform2DData,
+    },
+    Restacked {
+        resource_id: [u8; 16],
+        from: ZOrderKey,
+        to: ZOrderKey,
+    },
+    /// Opacity, blend mode, tint, crop or clip changed.
+    Restyled {
+        resource_id: [u8; 16],
+        from: FragmentStyle,
+        to: FragmentStyle,
+    },
+    /// The resource changed of kind, of parent or of track, it is sent whole.
+    Replaced(CompositionResourceData),
+}
+impl CompositionResourceChange {
+    pub fn resource_id(&self) -> Uuid {
+        match self {
+            Self::Added(resource) | Self::Replaced(resource) => resource.id(),
+            Self::Removed { resource_id }
+            | Self::Transformed { resource_id, .. }
+            | Self::Restacked { resource_id, .. }
+            | Self::Restyled { resource_id, .. } => Uuid::from_bytes(*resource_id),
+        }
+    }
+    /// Whether only the position of the resource changed.
+    pub fn is_move(&self) -> bool {
+        match self {
+            Self::Transformed { from, to, .. } => {
+                from.pos() != to.pos() && from.clone().with_pos([to.pos()[0], to.pos()[1]]) == *to
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompositionDiffError {
+    WrongComposition,
+    /// The composition is not the version the diff was computed from.
+    StaleBase {
+        resource_id: Uuid,
+    },
+    /// The bounds are not the ones the diff was computed from.
+    StaleBounds,
+    StaleVersion {
+        expected: u64,
+        current: u64,
+    },
+}
+
+impl std::fmt::Display for CompositionDiffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongComposition => write!(f, "CompositionDiffError WrongComposition"),
+            Self::StaleBase { resource_id } => {
+                write!(f, "CompositionDiffError StaleBase [{}]", resource_id)
+            }
+            Self::StaleBounds => write!(f, "CompositionDiffError StaleBounds"),
+            Self::StaleVersion { expected, current } => write!(
+                f,
+                "CompositionDiffError StaleVersion expected [{}] current [{}]",
+                expected, current
+            ),
+        }
+    }
+}
+impl std::error::Error for CompositionDiffError {}
+
+/// [`CompositionDiff`] lists what changed between two versions of a composition.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct CompositionDiff {
+    composition_id: [u8; 16],
+    from_version: u64,
+    to_version: u64,
+    changes: Vec<CompositionResourceChange>,
+    bounds: Option<(CompositionBounds, CompositionBounds)>,
+    /// New groups and layers, sent whole when any of them changed.
+    structure: Option<(Vec<CompositionGroup>, Vec<CompositionLayer>)>,
+}
+impl CompositionDiff {
+    /// `None` when `old` and `new` are not versions of the same composition.
+    pub fn between(old: &CompositionData, new: &CompositionData) -> Option<Self> {
+        if old.id() != new.id() {
+            return None;
+        }
+        let mut changes = vec![];
+        for before in old.resources_collection() {
+            let Some(after) = new.resource(before.id()) else {
+                changes.push(CompositionResourceChange::Removed {
+                    resource_id: before.id().into_bytes(),
+                });
+                continue;
+            };
+            if before.kind() != after.kind()
+                || before.parent() != after.parent()
+                || before.track() != after.track()
+            {
+                changes.push(CompositionResourceChange::Replaced(after.clone()));
+                continue;
+            }
+            let resource_id = before.id().into_bytes();
+            if before.transform() != after.transform() {
+                changes.push(CompositionResourceChange::Transformed {
+                    resource_id,
+                    from: before.transform().clone(),
+                    to: after.transform().clone(),
+                });
+            }
+            if before.z_order() != after.z_order() {
+                changes.push(CompositionResourceChange::Restacked {
+                    resource_id,
+                    from: before.z_order().clone(),
+                    to: after.z_order().clone(),
+                });
+            }
+            if before.style() != after.style() {
+                changes.push(CompositionResourceChange::Restyled {
+                    resource_id,
+                    from: before.style().clone(),
+                    to: after.style().clone(),
+                });
+            }
+        }
+        for after in new.resources_collection() {
+            if old.resource(after.id()).is_none() {
+                changes.push(CompositionResourceChange::Added(after.clone()));
+            }
+        }
+        let bounds = (old.bounds() != new.bounds()).then(|| (old.bounds(), new.bounds()));
+        let structure = (old.groups() != new.groups() || old.layers() != new.layers())
+            .then(|| (new.groups().to_vec(), new.layers().to_vec()));
+        Some(Self {
+            composition_id: old.id().into_bytes(),
+            from_version: old.version(),
+            to_version: new.version(),
+            changes,
+            bounds,
+            structure,
+        })
+    }
+    pub fn composition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.composition_id)
+    }
+    /// Version of the composition the diff applies to.
+    pub fn from_version(&self) -> u64 {
+        self.from_version
+    }
+    /// Version of the composition once the diff is applied.
+    pub fn to_version(&self) -> u64 {
+        self.to_version
+    }
+    pub fn changes(&self) -> &[CompositionResourceChange] {
+        &self.changes
+    }
+    /// Previous and new bounds, when they changed.
+    pub fn bounds(&self) -> Option<&(CompositionBounds, CompositionBounds)> {
+        self.bounds.as_ref()
+    }
+    pub fn structure(&self) -> Option<(&[CompositionGroup], &[CompositionLayer])> {
+        self.structure
+            .as_ref()
+            .map(|(groups, layers)| (groups.as_slice(), layers.as_slice()))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.bounds.is_none() && self.structure.is_none()
+    }
+    /// Applies every change, or none when `composition` is not the version the diff was
+    /// computed from. Added resources go at the end of the collection: its order carries no
+    /// meaning, drawing follows [`CompositionResourceData::stacking_cmp`] and equality and
+    /// [`CompositionData::content_hash`] ignore it.
+    pub fn apply(&self, composition: &mut CompositionData) -> Result<(), CompositionDiffError> {
+        if composition.id() != self.composition_id() {
+            return Err(CompositionDiffError::WrongComposition);
+        }
+        if composition.version() != self.from_version {
+            return Err(CompositionDiffError::StaleVersion {
+                expected: self.from_version,
+                current: composition.version(),
+            });
+        }
+        let mut patched = composition.clone().with_version(self.to_version);
+        for change in &self.changes {
+            let resource_id = change.resource_id();
+            let stale = CompositionDiffError::StaleBase { resource_id };
+            let resources = patched.resources_mut();
+            let index = resources.iter().position(|it| it.id() == resource_id);
+            match (change, index) {
+                (CompositionResourceChange::Added(resource), None) => {
+                    resources.push(resource.clone())
+                }
+                (CompositionResourceChange::Added(_), Some(_)) | (_, None) => return Err(stale),
+                (CompositionResourceChange::Removed { .. }, Some(index)) => {
+                    resources.remove(index);
+                }
+                (CompositionResourceChange::Transformed { from, to, .. }, Some(index)) => {
+                    if resources[index].transform() != from {
+                        return Err(stale);
+                    }
+                    resources[index].set_transform(to.clone());
+                }
+                (CompositionResourceChange::Restacked { from, to, .. }, Some(index)) => {
+                    if resources[index].z_order() != from {
+                        return Err(stale);
+                    }
+                    resources[index].set_z_order(to.clone());
+                }
+                (CompositionResourceChange::Restyled { from, to, .. }, Some(index)) => {
+                    if resources[index].style() != from {
+                        return Err(stale);
+                    }
+                    resources[index].set_style(to.clone());
+                }
+                (CompositionResourceChange::Replaced(resource), Some(index)) => {
+                    resources[index] = resource.clone()
+                }
+            }
+        }
+        if let Some((from, to)) = &self.bounds {
+            if patched.bounds() != *from {
+                return Err(CompositionDiffError::StaleBounds);
+            }
+            patched.set_bounds(to);
+        }
+        if let Some((groups, layers)) = &self.structure {
+            patched = patched
+                .with_groups(groups.clone())
+                .with_layers(layers.clone());
+        }
+        *composition = patched;
+        Ok(())
+    }
+}
+
+impl DecodeLimited for CompositionDiff {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("changes", self.changes.len())?;
+        for change in &self.changes {
+            match change {
+                CompositionResourceChange::Added(resource)
+                | CompositionResourceChange::Replaced(resource) => {
+                    resource.check_decode_limits(limits)?
+                }
+                CompositionResourceChange::Restyled { from, to, .. } => {
+                    from.check_decode_limits(limits)?;
+                    to.check_decode_limits(limits)?;
+                }
+                _ => {}
+            }
+        }
+        if let Some((groups, layers)) = &self.structure {
+            limits.check_collection("structure.groups", groups.len())?;
+            limits.check_collection("structure.layers", layers.len())?;
+            for layer in layers {
+                limits.check_string("structure.layers.name", layer.name())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for CompositionResourceChange {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        match self {
+            Self::Added(resource) | Self::Replaced(resource) => {
+                resource.validate_at(&field_path(path, "resource"), violations)
+            }
+            Self::Removed { resource_id } => {
+                violations.check_not_nil(path, "resource_id", resource_id)
+            }
+            Self::Transformed {
+                resource_id, to, ..
+            } => {
+                violations.check_not_nil(path, "resource_id", resource_id);
+                to.validate_at(&field_path(path, "to"), violations);
+            }
+            Self::Restacked {
+                resource_id, to, ..
+            } => {
+                violations.check_not_nil(path, "resource_id", resource_id);
+                to.validate_at(&field_path(path, "to"), violations);
+            }
+            Self::Restyled {
+                resource_id, to, ..
+            } => {
+                violations.check_not_nil(path, "resource_id", resource_id);
+                to.validate_at(&field_path(path, "to"), violations);
+            }
+        }
+    }
+}
+impl Validate for CompositionDiff {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "composition_id", &self.composition_id);
+        violations.check_items(path, "changes", &self.changes);
+        let mut seen = std::collections::HashSet::new();
+        for (index, change) in self.changes.iter().enumerate() {
+            // A resource can be transformed, restacked and restyled at once, nothing else.
+            let aspect = match change {
+                CompositionResourceChange::Restacked { .. } => 1,
+                CompositionResourceChange::Restyled { .. } => 2,
+                _ => 0,
+            };
+            if !seen.insert((change.resource_id(), aspect)) {
+                violations.push(
+                    indexed_path(path, "changes", index),
+                    ViolationKind::Invalid("resource changed twice"),
+                );
+            }
+        }
+        if let Some((groups, layers)) = &self.structure {
+            let structure_path = field_path(path, "structure");
+            violations.check_items(&structure_path, "groups", groups);
+            violations.check_items(&structure_path, "layers", layers);
+        }
+        if let Some((_, to)) = &self.bounds {
+            let to_path = field_path(path, "bounds.to");
+            violations.check_bounds(&to_path, "min_x..max_x", to.min_x(), to.max_x());
+            violations.check_bounds(&to_path, "min_y..max_y", to.min_y(), to.max_y());
+        }
+    }
+}
+
+#[cfg(test)]
+mod composition_diff_test {
+    use uuid::Uuid;
+
+    use crate::{
+        CompositionData, CompositionDiff, CompositionDiffError, CompositionResourceData,
+        FragmentTransform2DData, Validate,
+    };
+
+    #[test]
+    fn diff_applies_onto_its_base_only() {
+        let [kept, moved, removed, added] = [
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+        ];
+        let id = Uuid::now_v7();
+        let author_id = Uuid::now_v7();
+        let old = CompositionData::builder(id, author_id)
+            .add_resource(CompositionResourceData::new(kept, [0.0, 0.0], 4.0, 4.0))
+            .add_resource(CompositionResourceData::new(moved, [10.0, 0.0], 4.0, 4.0))
+            .add_resource(CompositionResourceData::new(removed, [20.0, 0.0], 4.0, 4.0))
+            .build()
+            .unwrap();
+        let new = CompositionData::builder(id, author_id)
+            .version(1)
+            .add_resource(CompositionResourceData::new(kept, [0.0, 0.0], 4.0, 4.0))
+            .add_resource(CompositionResourceData::new(added, [-5.0, 0.0], 2.0, 2.0))
+            .add_resource(CompositionResourceData::new_with_transform(
+                moved,
+                FragmentTransform2DData::from_pos_size([10.0, 30.0], 4.0, 4.0),
+            ))
+            .build()
+            .unwrap();
+
+        let diff = CompositionDiff::between(&old, &new).unwrap();
+        assert!(diff.validate().is_ok());
+        assert!(diff.changes().len() == 3);
+        assert!(diff.changes().iter().any(|it| it.is_move()));
+        assert!(diff.bounds().is_some());
+
+        let mut patched = old.clone();
+        diff.apply(&mut patched).unwrap();
+        assert!(CompositionDiff::between(&patched, &new).unwrap().is_empty());
+        // `added` went at the end of the collection, whose order is not part of the content.
+        assert!(patched.resources_collection().last().unwrap().id() == added);
+        assert!(patched == new);
+
+        // Applied twice, the diff finds its base gone and leaves the composition untouched.
+        assert!(matches!(
+            diff.apply(&mut patched),
+            Err(CompositionDiffError::StaleVersion {
+                expected: 0,
+                current: 1
+            })
+        ));
+        let mut rewound = patched.clone().with_version(0);
+        assert!(matches!(
+            diff.apply(&mut rewound),
+            Err(CompositionDiffError::StaleBase { .. })
+        ));
+        assert!(rewound.same_content(&patched));
+    }
+}
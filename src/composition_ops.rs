@@ -0,0 +1,871 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    CompositionData, CompositionGroup, CompositionLayer, CompositionResourceData, DecodeLimitError,
+    DecodeLimited, DecodeLimits, FragmentStyle, FragmentTransform2DData, ParentRef, Validate,
+    ViolationKind, Violations, ZOrderKey, field_path,
+};
+
+// Replicas converge by replaying the same operations in the same order: every operation
+// carries a Lamport timestamp, ties between replicas being broken by the replica id, and the
+// log keeps operations sorted by it. An operation received out of order triggers a replay
+// from the last snapshot, one received in order is applied on top of the current state.
+
+/// Highest Lamport counter accepted from a peer, far more operations than a composition ever
+/// gets. A peer sending a larger one could exhaust the clocks of every replica.
+pub const MAX_LAMPORT_COUNTER: u64 = 1 << 48;
+
+/// [`LamportTimestamp`] orders operations of every replica: `counter` first, then
+/// `replica_id`.
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct LamportTimestamp {
+    counter: u64,
+    replica_id: [u8; 16],
+}
+impl LamportTimestamp {
+    pub fn new(counter: u64, replica_id: Uuid) -> Self {
+        Self {
+            counter,
+            replica_id: replica_id.into_bytes(),
+        }
+    }
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+    pub fn replica_id(&self) -> Uuid {
+        Uuid::from_bytes(self.replica_id)
+    }
+}
+
+/// [`LamportClock`] stamps the operations of one replica.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub struct LamportClock {
+    counter: u64,
+    replica_id: [u8; 16],
+}
+impl LamportClock {
+    pub fn new(replica_id: Uuid) -> Self {
+        Self {
+            counter: 0,
+            replica_id: replica_id.into_bytes(),
+        }
+    }
+    /// Timestamp of the next local operation, `None` once the counter reached
+    /// [`MAX_LAMPORT_COUNTER`].
+    pub fn tick(&mut self) -> Option<LamportTimestamp> {
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .filter(|it| *it <= MAX_LAMPORT_COUNTER)?;
+        Some(LamportTimestamp {
+            counter: self.counter,
+            replica_id: self.replica_id,
+        })
+    }
+    /// Catches up with a timestamp received from another replica.
+    pub fn observe(&mut self, timestamp: LamportTimestamp) {
+        self.counter = self.counter.max(timestamp.counter);
+    }
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub enum CompositionOpKind {
+    /// Adds the resource, or replaces the resource with the same id.
+    AddResource(CompositionResourceData),
+    /// Moves, resizes or turns a resource.
+    Transform {
+        resource_id: [u8; 16],
+        transform: FragmentTransform2DData,
+    },
+    RemoveResource {
+        resource_id: [u8; 16],
+    },
+    SetZ {
+        resource_id: [u8; 16],
+        z: f32,
+    },
+    /// Moves a resource in the stack, see [`CompositionData::z_order_key_above`].
+    SetZOrder {
+        resource_id: [u8; 16],
+        z_order: ZOrderKey,
+    },
+    /// Changes how the resource is drawn: opacity, blend mode, tint, crop and clip.
+    SetStyle {
+        resource_id: [u8; 16],
+        style: FragmentStyle,
+    },
+    /// Adds the group, or replaces the group with the same id.
+    UpsertGroup(CompositionGroup),
+    /// Removes the group with everything nested in it.
+    RemoveGroup {
+        group_id: [u8; 16],
+    },
+    /// Adds the layer, or replaces the layer with the same id.
+    UpsertLayer(CompositionLayer),
+    /// Removes the layer with everything in it.
+    RemoveLayer {
+        layer_id: [u8; 16],
+    },
+    /// Moves a resource or a group under `parent`, at `z_order` among its new siblings, see
+    /// [`CompositionData::z_order_key_above`].
+    SetParent {
+        node_id: [u8; 16],
+        parent: Option<ParentRef>,
+        z_order: ZOrderKey,
+    },
+    SetLayerVisible {
+        layer_id: [u8; 16],
+        visible: bool,
+    },
+    SetLayerLocked {
+        layer_id: [u8; 16],
+        locked: bool,
+    },
+}
+impl CompositionOpKind {
+    pub fn add_resource(resource: CompositionResourceData) -> Self {
+        Self::AddResource(resource)
+    }
+    pub fn transform(resource_id: Uuid, transform: FragmentTransform2DData) -> Self {
+        Self::Transform {
+            resource_id: resource_id.into_bytes(),
+            transform,
+        }
+    }
+    pub fn remove_resource(resource_id: Uuid) -> Self {
+        Self::RemoveResource {
+            resource_id: resource_id.into_bytes(),
+        }
+    }
+    pub fn set_z(resource_id: Uuid, z: f32) -> Self {
+        Self::SetZ {
+            resource_id: resource_id.into_bytes(),
+            z,
+        }
+    }
+    pub fn set_z_order(resource_id: Uuid, z_order: ZOrderKey) -> Self {
+        Self::SetZOrder {
+            resource_id: resource_id.into_bytes(),
+            z_order,
+        }
+    }
+    pub fn set_style(resource_id: Uuid, style: FragmentStyle) -> Self {
+        Self::SetStyle {
+            resource_id: resource_id.into_bytes(),
+            style,
+        }
+    }
+    pub fn upsert_group(group: CompositionGroup) -> Self {
+        Self::UpsertGroup(group)
+    }
+    pub fn remove_group(group_id: Uuid) -> Self {
+        Self::RemoveGroup {
+            group_id: group_id.into_bytes(),
+        }
+    }
+    pub fn upsert_layer(layer: CompositionLayer) -> Self {
+        Self::UpsertLayer(layer)
+    }
+    pub fn remove_layer(layer_id: Uuid) -> Self {
+        Self::RemoveLayer {
+            layer_id: layer_id.into_bytes(),
+        }
+    }
+    pub fn set_parent(node_id: Uuid, parent: Option<ParentRef>, z_order: ZOrderKey) -> Self {
+        Self::SetParent {
+            node_id: node_id.into_bytes(),
+            parent,
+            z_order,
+        }
+    }
+    pub fn set_layer_visible(layer_id: Uuid, visible: bool) -> Self {
+        Self::SetLayerVisible {
+            layer_id: layer_id.into_bytes(),
+            visible,
+        }
+    }
+    pub fn set_layer_locked(layer_id: Uuid, locked: bool) -> Self {
+        Self::SetLayerLocked {
+            layer_id: layer_id.into_bytes(),
+            locked,
+        }
+    }
+    /// Id of the resource, group or layer the operation edits.
+    pub fn target_id(&self) -> Uuid {
+        match self {
+            Self::AddResource(resource) => resource.id(),
+            Self::UpsertGroup(group) => group.group_id(),
+            Self::UpsertLayer(layer) => layer.layer_id(),
+            Self::Transform { resource_id, .. }
+            | Self::RemoveResource { resource_id }
+            | Self::SetZ { resource_id, .. }
+            | Self::SetZOrder { resource_id, .. }
+            | Self::SetStyle { resource_id, .. } => Uuid::from_bytes(*resource_id),
+            Self::RemoveGroup { group_id } => Uuid::from_bytes(*group_id),
+            Self::SetParent { node_id, .. } => Uuid::from_bytes(*node_id),
+            Self::RemoveLayer { layer_id }
+            | Self::SetLayerVisible { layer_id, .. }
+            | Self::SetLayerLocked { layer_id, .. } => Uuid::from_bytes(*layer_id),
+        }
+    }
+}
+
+/// [`CompositionOp`] is one edit of a composition by one participant.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct CompositionOp {
+    op_id: [u8; 16],
+    composition_id: [u8; 16],
+    author_id: [u8; 16],
+    timestamp: LamportTimestamp,
+    kind: CompositionOpKind,
+}
+impl CompositionOp {
+    pub fn new(
+        composition_id: Uuid,
+        author_id: Uuid,
+        timestamp: LamportTimestamp,
+        kind: CompositionOpKind,
+    ) -> Self {
+        Self {
+            op_id: Uuid::now_v7().into_bytes(),
+            composition_id: composition_id.into_bytes(),
+            author_id: author_id.into_bytes(),
+            timestamp,
+            kind,
+        }
+    }
+    pub fn op_id(&self) -> Uuid {
+        Uuid::from_bytes(self.op_id)
+    }
+    pub fn composition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.composition_id)
+    }
+    pub fn author_id(&self) -> Uuid {
+        Uuid::from_bytes(self.author_id)
+    }
+    pub fn timestamp(&self) -> LamportTimestamp {
+        self.timestamp
+    }
+    pub fn kind(&self) -> &CompositionOpKind {
+        &self.kind
+    }
+    fn order_key(&self) -> (LamportTimestamp, [u8; 16]) {
+        (self.timestamp, self.op_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositionOpOutcome {
+    Applied,
+    /// The operation targets another composition.
+    WrongComposition,
+    /// The resource was removed by an earlier operation, or never added.
+    ResourceNotFound,
+    /// The group or layer was removed by an earlier operation, or never added.
+    NodeNotFound,
+    /// The parent is missing, or a group would end up nested in itself.
+    InvalidParent,
+}
+
+impl CompositionData {
+    /// Applies one operation. Client and server both go through it, so that the same
+    /// operations in the same order give the same composition. Bounds are left as they are,
+    /// see [`CompositionData::recompute_bounds`].
+    pub fn apply(&mut self, op: &CompositionOp) -> CompositionOpOutcome {
+        if op.composition_id() != self.id() {
+            return CompositionOpOutcome::WrongComposition;
+        }
+        let target_id = op.kind.target_id();
+        match &op.kind {
+            CompositionOpKind::UpsertGroup(group) => {
+                if !self.accepts_parent(target_id, group.parent()) {
+                    return CompositionOpOutcome::InvalidParent;
+                }
+                let groups = self.groups_mut();
+                match groups.iter().position(|it| it.group_id() == target_id) {
+                    Some(index) => groups[index] = group.clone(),
+                    None => groups.push(group.clone()),
+                }
+                return CompositionOpOutcome::Applied;
+            }
+            CompositionOpKind::UpsertLayer(layer) => {
+                let layers = self.layers_mut();
+                match layers.iter().position(|it| it.layer_id() == target_id) {
+                    Some(index) => layers[index] = layer.clone(),
+                    None => layers.push(layer.clone()),
+                }
+                return CompositionOpOutcome::Applied;
+            }
+            CompositionOpKind::RemoveGroup { .. } => {
+                if self.group(target_id).is_none() {
+                    return CompositionOpOutcome::NodeNotFound;
+                }
+                self.remove_descendants(ParentRef::group(target_id));
+                self.groups_mut().retain(|it| it.group_id() != target_id);
+                return CompositionOpOutcome::Applied;
+            }
+            CompositionOpKind::RemoveLayer { .. } => {
+                if self.layer(target_id).is_none() {
+                    return CompositionOpOutcome::NodeNotFound;
+                }
+                self.remove_descendants(ParentRef::layer(target_id));
+                self.layers_mut().retain(|it| it.layer_id() != target_id);
+                return CompositionOpOutcome::Applied;
+            }
+            CompositionOpKind::SetLayerVisible { visible, .. } => {
+                return match self.layer_mut(target_id) {
+                    Some(layer) => {
+                        layer.set_visible(*visible);
+                        CompositionOpOutcome::Applied
+                    }
+                    None => CompositionOpOutcome::NodeNotFound,
+                };
+            }
+            CompositionOpKind::SetLayerLocked { locked, .. } => {
+                return match self.layer_mut(target_id) {
+                    Some(layer) => {
+                        layer.set_locked(*locked);
+                        CompositionOpOutcome::Applied
+                    }
+                    None => CompositionOpOutcome::NodeNotFound,
+                };
+            }
+            CompositionOpKind::SetParent {
+                parent, z_order, ..
+            } => {
+                if !self.accepts_parent(target_id, *parent) {
+                    return CompositionOpOutcome::InvalidParent;
+                }
+                if let Some(group) = self.group_mut(target_id) {
+                    group.set_parent(*parent);
+                    group.set_z_order(z_order.clone());
+                    return CompositionOpOutcome::Applied;
+                }
+            }
+            CompositionOpKind::AddResource(resource)
+                if !self.accepts_parent(target_id, resource.parent()) =>
+            {
+                return CompositionOpOutcome::InvalidParent;
+            }
+            _ => {}
+        }
+        let resource_id = target_id.into_bytes();
+        let resources = self.resources_mut();
+        let index = resources
+            .iter()
+            .position(|it| it.id().into_bytes() == resource_id);
+        match (&op.kind, index) {
+            (CompositionOpKind::AddResource(resource), Some(index)) => {
+                resources[index] = resource.clone()
+            }
+            (CompositionOpKind::AddResource(resource), None) => resources.push(resource.clone()),
+            (_, None) => return CompositionOpOutcome::ResourceNotFound,
+            (CompositionOpKind::Transform { transform, .. }, Some(index)) => {
+                resources[index].set_transform(transform.clone())
+            }
+            (CompositionOpKind::RemoveResource { .. }, Some(index)) => {
+                resources.remove(index);
+            }
+            (CompositionOpKind::SetZ { z, .. }, Some(index)) => {
+                let transform = resources[index].transform().clone().with_z(*z);
+                resources[index].set_transform(transform)
+            }
+            (CompositionOpKind::SetZOrder { z_order, .. }, Some(index)) => {
+                resources[index].set_z_order(z_order.clone())
+            }
+            (CompositionOpKind::SetStyle { style, .. }, Some(index)) => {
+                resources[index].set_style(style.clone())
+            }
+            (
+                CompositionOpKind::SetParent {
+                    parent, z_order, ..
+                },
+                Some(index),
+            ) => {
+                resources[index].set_parent(*parent);
+                resources[index].set_z_order(z_order.clone())
+            }
+            (
+                CompositionOpKind::UpsertGroup(_)
+                | CompositionOpKind::RemoveGroup { .. }
+                | CompositionOpKind::UpsertLayer(_)
+                | CompositionOpKind::RemoveLayer { .. }
+                | CompositionOpKind::SetLayerVisible { .. }
+                | CompositionOpKind::SetLayerLocked { .. },
+                Some(_),
+            ) => unreachable!("applied above"),
+        }
+        CompositionOpOutcome::Applied
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpLogInsert {
+    /// Already in the log.
+    Duplicate,
+    /// After every operation of the log, it can be applied on top of the current state.
+    Appended,
+    /// Before some operations of the log, the state has to be replayed.
+    Reordered,
+    /// Not inserted, the operation targets another composition.
+    WrongComposition,
+    /// Not inserted, the counter of the operation is past [`MAX_LAMPORT_COUNTER`].
+    CounterOutOfRange,
+}
+
+/// [`CompositionOpLog`] holds the operations of a composition since a snapshot, sorted by
+/// timestamp.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct CompositionOpLog {
+    ops: Vec<CompositionOp>,
+}
+impl CompositionOpLog {
+    pub fn new_empty() -> Self {
+        Self { ops: vec![] }
+    }
+    pub fn insert(&mut self, op: CompositionOp) -> OpLogInsert {
+        match self
+            .ops
+            .binary_search_by(|it| it.order_key().cmp(&op.order_key()))
+        {
+            Ok(_) => OpLogInsert::Duplicate,
+            Err(index) if index == self.ops.len() => {
+                self.ops.push(op);
+                OpLogInsert::Appended
+            }
+            Err(index) => {
+                self.ops.insert(index, op);
+                OpLogInsert::Reordered
+            }
+        }
+    }
+    /// Adds the operations of `other` missing from `self`. Returns the number of added ones.
+    pub fn merge(&mut self, other: &CompositionOpLog) -> usize {
+        other
+            .ops
+            .iter()
+            .filter(|op| self.insert((*op).clone()) != OpLogInsert::Duplicate)
+            .count()
+    }
+    pub fn ops(&self) -> &[CompositionOp] {
+        &self.ops
+    }
+    /// Operations after `timestamp`, for a peer that has everything up to it.
+    pub fn ops_since(&self, timestamp: LamportTimestamp) -> &[CompositionOp] {
+        let start = self.ops.partition_point(|it| it.timestamp <= timestamp);
+        &self.ops[start..]
+    }
+    pub fn last_timestamp(&self) -> Option<LamportTimestamp> {
+        self.ops.last().map(|it| it.timestamp)
+    }
+    /// Applies every operation on a copy of `snapshot`.
+    pub fn replay(&self, snapshot: &CompositionData) -> CompositionData {
+        let mut composition = snapshot.clone();
+        for op in &self.ops {
+            composition.apply(op);
+        }
+        composition
+    }
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// [`CompositionReplica`] is the copy of a composition one participant edits: the snapshot it
+/// started from, the operations since then and the resulting state.
+#[derive(Debug, Clone)]
+pub struct CompositionReplica {
+    snapshot: CompositionData,
+    log: CompositionOpLog,
+    state: CompositionData,
+    clock: LamportClock,
+    author_id: Uuid,
+}
+impl CompositionReplica {
+    pub fn new(snapshot: CompositionData, replica_id: Uuid, author_id: Uuid) -> Self {
+        Self {
+            state: snapshot.clone(),
+            snapshot,
+            log: CompositionOpLog::new_empty(),
+            clock: LamportClock::new(replica_id),
+            author_id,
+        }
+    }
+    /// Applies a local edit, returns the operation to send to the other participants. `None`
+    /// once the clock is exhausted, see [`LamportClock::tick`].
+    pub fn edit(&mut self, kind: CompositionOpKind) -> Option<CompositionOp> {
+        let op = CompositionOp::new(self.state.id(), self.author_id, self.clock.tick()?, kind);
+        self.receive(op.clone());
+        Some(op)
+    }
+    /// Applies an operation from any replica, this one included. Operations of another
+    /// composition or with an out of range counter are dropped before touching the log or the
+    /// clock.
+    pub fn receive(&mut self, op: CompositionOp) -> OpLogInsert {
+        if op.composition_id() != self.state.id() {
+            return OpLogInsert::WrongComposition;
+        }
+        if op.timestamp.counter > MAX_LAMPORT_COUNTER {
+            return OpLogInsert::CounterOutOfRange;
+        }
+        self.clock.observe(op.timestamp);
+        let insert = self.log.insert(op);
+        match insert {
+            OpLogInsert::Duplicate
+            | OpLogInsert::WrongComposition
+            | OpLogInsert::CounterOutOfRange => {}
+            OpLogInsert::Appended => {
+                let op = self.log.ops.last().expect("just appended");
+                self.state.apply(op);
+            }
+            OpLogInsert::Reordered => self.state = self.log.replay(&self.snapshot),
+        }
+        insert
+    }
+    pub fn state(&self) -> &CompositionData {
+        &self.state
+    }
+    pub fn log(&self) -> &CompositionOpLog {
+        &self.log
+    }
+    /// Takes the current state as the new snapshot, once every participant acknowledged the
+    /// operations.
+    pub fn compact(&mut self) {
+        self.snapshot = self.state.clone();
+        self.log = CompositionOpLog::new_empty();
+    }
+}
+
+/// [`CompositionOpsBatch`] carries operations of one composition between client and server.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct CompositionOpsBatch {
+    composition_id: [u8; 16],
+    ops: Vec<CompositionOp>,
+}
+impl CompositionOpsBatch {
+    pub fn new(composition_id: Uuid, ops: Vec<CompositionOp>) -> Self {
+        Self {
+            composition_id: composition_id.into_bytes(),
+            ops,
+        }
+    }
+    pub fn composition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.composition_id)
+    }
+    pub fn ops(&self) -> &[CompositionOp] {
+        &self.ops
+    }
+    pub fn into_ops(self) -> Vec<CompositionOp> {
+        self.ops
+    }
+}
+
+impl DecodeLimited for CompositionOpsBatch {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("ops", self.ops.len())?;
+        for op in &self.ops {
+            match &op.kind {
+                CompositionOpKind::AddResource(resource) => resource.check_decode_limits(limits)?,
+                CompositionOpKind::SetStyle { style, .. } => style.check_decode_limits(limits)?,
+                CompositionOpKind::UpsertLayer(layer) => {
+                    limits.check_string("layers.name", layer.name())?
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for CompositionOpKind {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        match self {
+            Self::AddResource(resource) => {
+                resource.validate_at(&field_path(path, "resource"), violations)
+            }
+            Self::Transform {
+                resource_id,
+                transform,
+            } => {
+                violations.check_not_nil(path, "resource_id", resource_id);
+                transform.validate_at(&field_path(path, "transform"), violations);
+            }
+            Self::RemoveResource { resource_id } => {
+                violations.check_not_nil(path, "resource_id", resource_id)
+            }
+            Self::SetZ { resource_id, z } => {
+                violations.check_not_nil(path, "resource_id", resource_id);
+                violations.check_finite(path, "z", *z);
+            }
+            Self::SetZOrder {
+                resource_id,
+                z_order,
+            } => {
+                violations.check_not_nil(path, "resource_id", resource_id);
+                z_order.validate_at(&field_path(path, "z_order"), violations);
+            }
+            Self::SetStyle { resource_id, style } => {
+                violations.check_not_nil(path, "resource_id", resource_id);
+                style.validate_at(&field_path(path, "style"), violations);
+            }
+            Self::UpsertGroup(group) => group.validate_at(&field_path(path, "group"), violations),
+            Self::UpsertLayer(layer) => layer.validate_at(&field_path(path, "layer"), violations),
+            Self::RemoveGroup { group_id } => violations.check_not_nil(path, "group_id", group_id),
+            Self::SetParent {
+                node_id, z_order, ..
+            } => {
+                violations.check_not_nil(path, "node_id", node_id);
+                z_order.validate_at(&field_path(path, "z_order"), violations);
+            }
+            Self::RemoveLayer { layer_id }
+            | Self::SetLayerVisible { layer_id, .. }
+            | Self::SetLayerLocked { layer_id, .. } => {
+                violations.check_not_nil(path, "layer_id", layer_id)
+            }
+        }
+    }
+}
+impl Validate for CompositionOp {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "op_id", &self.op_id);
+        violations.check_not_nil(path, "composition_id", &self.composition_id);
+        violations.check_not_nil(path, "author_id", &self.author_id);
+        violations.check_not_nil(path, "timestamp.replica_id", &self.timestamp.replica_id);
+        violations.check_range(
+            path,
+            "timestamp.counter",
+            self.timestamp.counter as f64,
+            1.0,
+            MAX_LAMPORT_COUNTER as f64,
+        );
+        self.kind.validate_at(&field_path(path, "kind"), violations);
+    }
+}
+impl Validate for CompositionOpsBatch {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "composition_id", &self.composition_id);
+        violations.check_items(path, "ops", &self.ops);
+        for (index, op) in self.ops.iter().enumerate() {
+            if op.composition_id != self.composition_id {
+                violations.push(
+                    field_path(path, &format!("ops[{index}].composition_id")),
+                    ViolationKind::Invalid("operation of another composition"),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod composition_ops_test {
+    use uuid::Uuid;
+
+    use crate::{
+        CompositionData, CompositionGroup, CompositionLayer, CompositionOp, CompositionOpKind,
+        CompositionOpOutcome, CompositionReplica, CompositionResourceData, FragmentTransform2DData,
+        LamportClock, LamportTimestamp, OpLogInsert, ParentRef, Validate, ZOrderKey,
+    };
+
+    #[test]
+    fn concurrent_edits_converge() {
+        let composition = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7())
+            .build()
+            .unwrap();
+        let [alice, bob] = [Uuid::now_v7(), Uuid::now_v7()];
+        let mut on_alice = CompositionReplica::new(composition.clone(), Uuid::now_v7(), alice);
+        let mut on_bob = CompositionReplica::new(composition, Uuid::now_v7(), bob);
+
+        let resource_id = Uuid::now_v7();
+        let added = on_alice
+            .edit(CompositionOpKind::add_resource(
+                CompositionResourceData::new(resource_id, [0.0, 0.0], 10.0, 10.0),
+            ))
+            .unwrap();
+        assert!(on_bob.receive(added.clone()) == OpLogInsert::Appended);
+        assert!(on_bob.receive(added) == OpLogInsert::Duplicate);
+
+        // Both move the resource at the same time, then Alice removes another one that does
+        // not exist.
+        let moved_by_alice = on_alice
+            .edit(CompositionOpKind::transform(
+                resource_id,
+                FragmentTransform2DData::from_pos_size([5.0, 5.0], 10.0, 10.0),
+            ))
+            .unwrap();
+        let moved_by_bob = on_bob
+            .edit(CompositionOpKind::transform(
+                resource_id,
+                FragmentTransform2DData::from_pos_size([-5.0, 0.0], 10.0, 10.0),
+            ))
+            .unwrap();
+        let raised_by_bob = on_bob
+            .edit(CompositionOpKind::set_z(resource_id, 3.0))
+            .unwrap();
+        let removed_by_alice = on_alice
+            .edit(CompositionOpKind::remove_resource(Uuid::now_v7()))
+            .unwrap();
+
+        on_alice.receive(moved_by_bob);
+        on_alice.receive(raised_by_bob);
+        on_bob.receive(removed_by_alice);
+        on_bob.receive(moved_by_alice);
+
+        let [a, b] = [on_alice.state(), on_bob.state()];
+        assert!(a.resources_collection().len() == 1);
+        assert!(
+            a.resource(resource_id).unwrap().transform()
+                == b.resource(resource_id).unwrap().transform()
+        );
+        assert!(a.resource(resource_id).unwrap().transform().z() == 3.0);
+        assert!(on_alice.log().ops() == on_bob.log().ops());
+    }
+
+    #[test]
+    fn concurrent_tree_edits_converge() {
+        let composition = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7())
+            .build()
+            .unwrap();
+        let mut on_alice =
+            CompositionReplica::new(composition.clone(), Uuid::now_v7(), Uuid::now_v7());
+        let mut on_bob = CompositionReplica::new(composition, Uuid::now_v7(), Uuid::now_v7());
+        let [layer_id, group_id] = [Uuid::now_v7(), Uuid::now_v7()];
+        let [resource_id, grouped_id, late_id] = [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+        let resource = |id: Uuid| CompositionResourceData::new(id, [0.0, 0.0], 10.0, 10.0);
+        let in_group = Some(ParentRef::group(group_id));
+
+        let setup = [
+            CompositionOpKind::upsert_layer(CompositionLayer::new(layer_id, "ink".to_string())),
+            CompositionOpKind::upsert_group(CompositionGroup::new(
+                group_id,
+                FragmentTransform2DData::from_pos_size([100.0, 0.0], 0.0, 0.0),
+            )),
+            CompositionOpKind::add_resource(resource(resource_id)),
+        ];
+        for kind in setup {
+            let op = on_alice.edit(kind).unwrap();
+            on_bob.receive(op);
+        }
+
+        // Alice groups the resource and hides the layer while Bob moves the group into the
+        // layer, locks it and adds a resource to the group.
+        let by_alice = [
+            CompositionOpKind::set_parent(resource_id, in_group, ZOrderKey::first()),
+            CompositionOpKind::set_layer_visible(layer_id, false),
+        ]
+        .map(|kind| on_alice.edit(kind).unwrap());
+        let by_bob = [
+            CompositionOpKind::set_parent(
+                group_id,
+                Some(ParentRef::layer(layer_id)),
+                ZOrderKey::first(),
+            ),
+            CompositionOpKind::set_layer_locked(layer_id, true),
+            CompositionOpKind::add_resource(
+                resource(grouped_id).with_parent(ParentRef::group(group_id)),
+            ),
+        ]
+        .map(|kind| on_bob.edit(kind).unwrap());
+        for op in by_bob {
+            on_alice.receive(op);
+        }
+        for op in by_alice {
+            on_bob.receive(op);
+        }
+
+        let [a, b] = [on_alice.state(), on_bob.state()];
+        assert!(a == b && on_alice.log().ops() == on_bob.log().ops());
+        assert!(a.resource(resource_id).unwrap().parent() == in_group);
+        assert!(a.group(group_id).unwrap().parent() == Some(ParentRef::layer(layer_id)));
+        let layer = a.layer(layer_id).unwrap();
+        assert!(!layer.visible() && layer.locked());
+        let flattened: Vec<_> = a.flatten().iter().map(|it| it.resource().id()).collect();
+        assert!(flattened.len() == 2 && flattened.contains(&resource_id));
+
+        // Alice removes the group while Bob adds to it: the group goes with its content.
+        let removed = on_alice
+            .edit(CompositionOpKind::remove_group(group_id))
+            .unwrap();
+        let added = on_bob
+            .edit(CompositionOpKind::add_resource(
+                resource(late_id).with_parent(ParentRef::group(group_id)),
+            ))
+            .unwrap();
+        on_alice.receive(added);
+        on_bob.receive(removed);
+        let [a, b] = [on_alice.state(), on_bob.state()];
+        assert!(a == b && a.group(group_id).is_none());
+        assert!(a.resources_collection().is_empty() && a.validate().is_ok());
+
+        // A group can not be moved into itself.
+        let inner = on_alice
+            .edit(CompositionOpKind::upsert_group(
+                CompositionGroup::new(
+                    Uuid::now_v7(),
+                    FragmentTransform2DData::from_pos_size([0.0; 2], 0.0, 0.0),
+                )
+                .with_parent(ParentRef::layer(layer_id)),
+            ))
+            .unwrap();
+        let cycle = CompositionOp::new(
+            inner.composition_id(),
+            Uuid::now_v7(),
+            LamportTimestamp::new(100, Uuid::now_v7()),
+            CompositionOpKind::set_parent(
+                inner.kind().target_id(),
+                Some(ParentRef::group(inner.kind().target_id())),
+                ZOrderKey::first(),
+            ),
+        );
+        assert!(on_alice.state().clone().apply(&cycle) == CompositionOpOutcome::InvalidParent);
+    }
+
+    #[test]
+    fn foreign_and_out_of_range_ops_are_dropped() {
+        let composition = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7())
+            .build()
+            .unwrap();
+        let author_id = Uuid::now_v7();
+        let mut replica = CompositionReplica::new(composition.clone(), Uuid::now_v7(), author_id);
+        let add = CompositionOpKind::add_resource(CompositionResourceData::new(
+            Uuid::now_v7(),
+            [0.0, 0.0],
+            10.0,
+            10.0,
+        ));
+
+        let foreign = CompositionOp::new(
+            Uuid::now_v7(),
+            author_id,
+            LamportTimestamp::new(5, Uuid::now_v7()),
+            add.clone(),
+        );
+        assert!(replica.receive(foreign) == OpLogInsert::WrongComposition);
+        let absurd = CompositionOp::new(
+            composition.id(),
+            author_id,
+            LamportTimestamp::new(u64::MAX, Uuid::now_v7()),
+            add.clone(),
+        );
+        assert!(absurd.validate().is_err());
+        assert!(replica.receive(absurd) == OpLogInsert::CounterOutOfRange);
+        assert!(replica.log().is_empty() && replica.state().resources_collection().is_empty());
+        assert!(replica.edit(add).unwrap().timestamp().counter() == 1);
+
+        let mut clock = LamportClock::new(Uuid::now_v7());
+        clock.observe(LamportTimestamp::new(u64::MAX, Uuid::now_v7()));
+        assert!(clock.tick().is_none());
+    }
+}
@@ -0,0 +1,578 @@
+use std::collections::{HashMap, HashSet};
+
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    Aabb, Affine2, CompositionData, CompositionResourceData, FragmentTransform2DData, IdKind,
+    IdMapping, RemapIds, Validate, ViolationKind, Violations, ZOrderKey, field_path, indexed_path,
+};
+
+/// Deepest nesting of groups walked, deeper nodes are not rendered.
+pub const MAX_GROUP_DEPTH: usize = 32;
+
+/// Node a resource or a group belongs to. Nodes without a parent belong to the base layer,
+/// drawn under every named layer.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Hash)]
+pub enum ParentRef {
+    Group([u8; 16]),
+    Layer([u8; 16]),
+}
+impl ParentRef {
+    pub fn group(group_id: Uuid) -> Self {
+        Self::Group(group_id.into_bytes())
+    }
+    pub fn layer(layer_id: Uuid) -> Self {
+        Self::Layer(layer_id.into_bytes())
+    }
+    pub fn id(&self) -> Uuid {
+        match self {
+            Self::Group(id) | Self::Layer(id) => Uuid::from_bytes(*id),
+        }
+    }
+}
+
+/// [`CompositionGroup`] moves its children together: their transforms are relative to the
+/// group's one. The `dimensions` of a group transform are not used.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct CompositionGroup {
+    group_id: [u8; 16],
+    parent: Option<ParentRef>,
+    transform: FragmentTransform2DData,
+    z_order: ZOrderKey,
+}
+impl CompositionGroup {
+    pub fn new(group_id: Uuid, transform: FragmentTransform2DData) -> Self {
+        Self {
+            group_id: group_id.into_bytes(),
+            parent: None,
+            transform,
+            z_order: ZOrderKey::default(),
+        }
+    }
+    pub fn with_parent(mut self, parent: ParentRef) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+    pub fn with_z_order(mut self, z_order: ZOrderKey) -> Self {
+        self.z_order = z_order;
+        self
+    }
+    pub fn group_id(&self) -> Uuid {
+        Uuid::from_bytes(self.group_id)
+    }
+    pub fn parent(&self) -> Option<ParentRef> {
+        self.parent
+    }
+    pub(crate) fn set_parent(&mut self, parent: Option<ParentRef>) {
+        self.parent = parent;
+    }
+    pub fn transform(&self) -> &FragmentTransform2DData {
+        &self.transform
+    }
+    pub fn z_order(&self) -> &ZOrderKey {
+        &self.z_order
+    }
+    pub(crate) fn set_z_order(&mut self, z_order: ZOrderKey) {
+        self.z_order = z_order;
+    }
+}
+
+/// [`CompositionLayer`] is a named stack of nodes. Hidden layers are not drawn and locked
+/// layers do not answer hit tests.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub struct CompositionLayer {
+    layer_id: [u8; 16],
+    name: String,
+    visible: bool,
+    locked: bool,
+    z_order: ZOrderKey,
+}
+impl CompositionLayer {
+    pub fn new(layer_id: Uuid, name: String) -> Self {
+        Self {
+            layer_id: layer_id.into_bytes(),
+            name,
+            visible: true,
+            locked: false,
+            z_order: ZOrderKey::default(),
+        }
+    }
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+    pub fn with_locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+    pub fn with_z_order(mut self, z_order: ZOrderKey) -> Self {
+        self.z_order = z_order;
+        self
+    }
+    pub fn layer_id(&self) -> Uuid {
+        Uuid::from_bytes(self.layer_id)
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+    pub(crate) fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+    pub(crate) fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+    pub fn z_order(&self) -> &ZOrderKey {
+        &self.z_order
+    }
+}
+
+impl RemapIds for ParentRef {
+    fn remap_ids(&mut self, mapping: &IdMapping) -> usize {
+        match self {
+            Self::Group(id) => mapping.remap_bytes(IdKind::Group, id) as usize,
+            Self::Layer(id) => mapping.remap_bytes(IdKind::Layer, id) as usize,
+        }
+    }
+}
+impl RemapIds for CompositionGroup {
+    fn remap_ids(&mut self, mapping: &IdMapping) -> usize {
+        mapping.remap_bytes(IdKind::Group, &mut self.group_id) as usize
+            + self.parent.as_mut().map_or(0, |it| it.remap_ids(mapping))
+    }
+}
+impl RemapIds for CompositionLayer {
+    fn remap_ids(&mut self, mapping: &IdMapping) -> usize {
+        mapping.remap_bytes(IdKind::Layer, &mut self.layer_id) as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompositionNode<'a> {
+    Group(&'a CompositionGroup),
+    Resource(&'a CompositionResourceData),
+}
+impl CompositionNode<'_> {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Self::Group(group) => group.group_id(),
+            Self::Resource(resource) => resource.id(),
+        }
+    }
+    pub fn transform(&self) -> &FragmentTransform2DData {
+        match self {
+            Self::Group(group) => &group.transform,
+            Self::Resource(resource) => resource.transform(),
+        }
+    }
+    pub fn z_order(&self) -> &ZOrderKey {
+        match self {
+            Self::Group(group) => &group.z_order,
+            Self::Resource(resource) => resource.z_order(),
+        }
+    }
+    /// Same order as [`CompositionResourceData::stacking_cmp`], groups included.
+    fn stacking_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.z_order()
+            .cmp(other.z_order())
+            .then(self.transform().z().total_cmp(&other.transform().z()))
+            .then(self.id().cmp(&other.id()))
+    }
+}
+
+/// [`FlattenedResource`] is a resource with everything needed to draw it.
+#[derive(Debug, Clone, Copy)]
+pub struct FlattenedResource<'a> {
+    resource: &'a CompositionResourceData,
+    /// Local to world, groups included.
+    world_transform: Affine2,
+    layer: Option<&'a CompositionLayer>,
+    depth: usize,
+}
+impl<'a> FlattenedResource<'a> {
+    pub fn resource(&self) -> &'a CompositionResourceData {
+        self.resource
+    }
+    pub fn world_transform(&self) -> &Affine2 {
+        &self.world_transform
+    }
+    /// `None` for the base layer.
+    pub fn layer(&self) -> Option<&'a CompositionLayer> {
+        self.layer
+    }
+    /// Number of groups the resource is nested in.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+    pub fn visible(&self) -> bool {
+        self.layer.is_none_or(|it| it.visible)
+    }
+    pub fn locked(&self) -> bool {
+        self.layer.is_some_and(|it| it.locked)
+    }
+    pub fn world_corners(&self) -> [[f32; 2]; 4] {
+        let [half_w, half_h] = self.resource.transform().dimensions().map(|it| it / 2.0);
+        [
+            [-half_w, -half_h],
+            [half_w, -half_h],
+            [half_w, half_h],
+            [-half_w, half_h],
+        ]
+        .map(|corner| self.world_transform.transform_point(corner))
+    }
+    pub fn world_aabb(&self) -> Aabb {
+        Aabb::from_points(self.world_corners()).expect("four corners")
+    }
+    pub fn contains_point(&self, world_pos: [f32; 2]) -> bool {
+        let Some(inverse) = self.world_transform.inverse() else {
+            return false;
+        };
+        let [x, y] = inverse.transform_point(world_pos);
+        let [width, height] = self.resource.transform().dimensions();
+        if x.abs() > width / 2.0 || y.abs() > height / 2.0 {
+            return false;
+        }
+        // Clipped out parts do not catch pointers.
+        match self.resource.style().clip() {
+            Some(clip) if width > 0.0 && height > 0.0 => {
+                clip.contains([x / width + 0.5, y / height + 0.5], width, height)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Children of every parent, bottom to top, built in one pass over the composition.
+type ChildrenIndex<'a> = HashMap<Option<ParentRef>, Vec<CompositionNode<'a>>>;
+
+impl CompositionData {
+    /// Nodes directly under `parent`, `None` standing for the base layer, bottom to top.
+    pub fn children(&self, parent: Option<ParentRef>) -> Vec<CompositionNode<'_>> {
+        let mut children: Vec<_> = self
+            .groups()
+            .iter()
+            .filter(|it| it.parent == parent)
+            .map(CompositionNode::Group)
+            .chain(
+                self.resources_collection()
+                    .iter()
+                    .filter(|it| it.parent() == parent)
+                    .map(CompositionNode::Resource),
+            )
+            .collect();
+        children.sort_by(|a, b| a.stacking_cmp(b));
+        children
+    }
+    fn children_index(&self) -> ChildrenIndex<'_> {
+        let mut index = ChildrenIndex::new();
+        for group in self.groups() {
+            let children = index.entry(group.parent).or_default();
+            children.push(CompositionNode::Group(group));
+        }
+        for resource in self.resources_collection() {
+            let children = index.entry(resource.parent()).or_default();
+            children.push(CompositionNode::Resource(resource));
+        }
+        for children in index.values_mut() {
+            children.sort_by(|a, b| a.stacking_cmp(b));
+        }
+        index
+    }
+    /// Named layers, bottom to top.
+    pub fn layers_by_z_order(&self) -> Vec<&CompositionLayer> {
+        let mut layers: Vec<_> = self.layers().iter().collect();
+        layers.sort_by(|a, b| a.z_order.cmp(&b.z_order).then(a.layer_id.cmp(&b.layer_id)));
+        layers
+    }
+    /// Visits every node depth first in drawing order, the base layer then the named layers,
+    /// with its local to world transform and its depth. Nodes whose parent is missing, or
+    /// nested deeper than [`MAX_GROUP_DEPTH`], are not visited.
+    pub fn walk<'a>(
+        &'a self,
+        mut visit: impl FnMut(CompositionNode<'a>, Option<&'a CompositionLayer>, &Affine2, usize),
+    ) {
+        let index = self.children_index();
+        walk_from(&index, None, None, &Affine2::IDENTITY, 0, &mut visit);
+        for layer in self.layers_by_z_order() {
+            let parent = Some(ParentRef::Layer(layer.layer_id));
+            walk_from(
+                &index,
+                parent,
+                Some(layer),
+                &Affine2::IDENTITY,
+                0,
+                &mut visit,
+            );
+        }
+    }
+    /// Resources in drawing order with their world transforms.
+    pub fn flatten(&self) -> Vec<FlattenedResource<'_>> {
+        let mut flattened = vec![];
+        self.walk(|node, layer, world_transform, depth| {
+            if let CompositionNode::Resource(resource) = node {
+                flattened.push(FlattenedResource {
+                    resource,
+                    world_transform: *world_transform,
+                    layer,
+                    depth,
+                });
+            }
+        });
+        flattened
+    }
+    pub fn group(&self, group_id: Uuid) -> Option<&CompositionGroup> {
+        let group_id = group_id.into_bytes();
+        self.groups().iter().find(|it| it.group_id == group_id)
+    }
+    pub fn layer(&self, layer_id: Uuid) -> Option<&CompositionLayer> {
+        let layer_id = layer_id.into_bytes();
+        self.layers().iter().find(|it| it.layer_id == layer_id)
+    }
+    pub(crate) fn group_mut(&mut self, group_id: Uuid) -> Option<&mut CompositionGroup> {
+        let group_id = group_id.into_bytes();
+        self.groups_mut()
+            .iter_mut()
+            .find(|it| it.group_id == group_id)
+    }
+    pub(crate) fn layer_mut(&mut self, layer_id: Uuid) -> Option<&mut CompositionLayer> {
+        let layer_id = layer_id.into_bytes();
+        self.layers_mut()
+            .iter_mut()
+            .find(|it| it.layer_id == layer_id)
+    }
+
+    /// Whether `node_id` can be put under `parent`: the parent exists and, for a group, is
+    /// not nested in `node_id` nor deeper than [`MAX_GROUP_DEPTH`].
+    pub(crate) fn accepts_parent(&self, node_id: Uuid, parent: Option<ParentRef>) -> bool {
+        let mut ancestor = match parent {
+            None => return true,
+            Some(ParentRef::Layer(id)) => return self.layer(Uuid::from_bytes(id)).is_some(),
+            Some(ParentRef::Group(id)) => id,
+        };
+        for _ in 0..MAX_GROUP_DEPTH {
+            if ancestor == node_id.into_bytes() {
+                return false;
+            }
+            match self.group(Uuid::from_bytes(ancestor)).map(|it| it.parent) {
+                None => return false,
+                Some(Some(ParentRef::Group(id))) => ancestor = id,
+                Some(_) => return true,
+            }
+        }
+        false
+    }
+    /// Removes the groups and resources nested in `root`, at any depth.
+    pub(crate) fn remove_descendants(&mut self, root: ParentRef) {
+        let mut removed = HashSet::from([root]);
+        loop {
+            let before = removed.len();
+            for group in self.groups() {
+                if group.parent.is_some_and(|it| removed.contains(&it)) {
+                    removed.insert(ParentRef::Group(group.group_id));
+                }
+            }
+            if removed.len() == before {
+                break;
+            }
+        }
+        let is_removed = |parent: Option<ParentRef>| parent.is_some_and(|it| removed.contains(&it));
+        self.groups_mut().retain(|it| !is_removed(it.parent));
+        self.resources_mut().retain(|it| !is_removed(it.parent()));
+    }
+    /// Checks that parents exist and that groups do not contain themselves.
+    pub(crate) fn validate_tree(&self, path: &str, violations: &mut Violations) {
+        let group_parents: HashMap<[u8; 16], Option<ParentRef>> = self
+            .groups()
+            .iter()
+            .map(|it| (it.group_id, it.parent))
+            .collect();
+        let layer_ids: HashSet<[u8; 16]> = self.layers().iter().map(|it| it.layer_id).collect();
+        let parent_exists = |parent: &Option<ParentRef>| match parent {
+            None => true,
+            Some(ParentRef::Group(id)) => group_parents.contains_key(id),
+            Some(ParentRef::Layer(id)) => layer_ids.contains(id),
+        };
+        for (index, resource) in self.resources_collection().iter().enumerate() {
+            if !parent_exists(&resource.parent()) {
+                violations.push(
+                    field_path(&indexed_path(path, "resource_collection", index), "parent"),
+                    ViolationKind::Invalid("parent not found"),
+                );
+            }
+        }
+        for (index, group) in self.groups().iter().enumerate() {
+            let group_path = indexed_path(path, "groups", index);
+            if !parent_exists(&group.parent) {
+                violations.push(
+                    field_path(&group_path, "parent"),
+                    ViolationKind::Invalid("parent not found"),
+                );
+                continue;
+            }
+            let mut ancestor = group.parent;
+            let mut depth = 0;
+            while let Some(ParentRef::Group(id)) = ancestor {
+                depth += 1;
+                if id == group.group_id || depth > MAX_GROUP_DEPTH {
+                    violations.push(
+                        field_path(&group_path, "parent"),
+                        ViolationKind::Invalid("group nested in itself or too deep"),
+                    );
+                    break;
+                }
+                ancestor = group_parents.get(&id).copied().flatten();
+            }
+        }
+        let mut ids = HashSet::new();
+        for (index, group) in self.groups().iter().enumerate() {
+            if !ids.insert(group.group_id) {
+                violations.push(
+                    field_path(&indexed_path(path, "groups", index), "group_id"),
+                    ViolationKind::Invalid("duplicate id"),
+                );
+            }
+        }
+    }
+}
+
+fn walk_from<'a>(
+    index: &ChildrenIndex<'a>,
+    parent: Option<ParentRef>,
+    layer: Option<&'a CompositionLayer>,
+    parent_transform: &Affine2,
+    depth: usize,
+    visit: &mut impl FnMut(CompositionNode<'a>, Option<&'a CompositionLayer>, &Affine2, usize),
+) {
+    if depth > MAX_GROUP_DEPTH {
+        return;
+    }
+    for node in index.get(&parent).into_iter().flatten() {
+        let world = parent_transform.mul(&node.transform().to_affine());
+        visit(*node, layer, &world, depth);
+        if let CompositionNode::Group(group) = node {
+            let parent = Some(ParentRef::Group(group.group_id));
+            walk_from(index, parent, layer, &world, depth + 1, visit);
+        }
+    }
+}
+
+impl Validate for CompositionGroup {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "group_id", &self.group_id);
+        self.transform
+            .validate_at(&field_path(path, "transform"), violations);
+        self.z_order
+            .validate_at(&field_path(path, "z_order"), violations);
+    }
+}
+impl Validate for CompositionLayer {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "layer_id", &self.layer_id);
+        self.z_order
+            .validate_at(&field_path(path, "z_order"), violations);
+    }
+}
+
+#[cfg(test)]
+mod composition_tree_test {
+    use std::f32::consts::FRAC_PI_2;
+
+    use uuid::Uuid;
+
+    use crate::{
+        CompositionData, CompositionGroup, CompositionLayer, CompositionResourceData,
+        FragmentTransform2DData, ParentRef, Validate, ZOrderKey,
+    };
+
+    #[test]
+    fn groups_move_their_children_together() {
+        let [group_id, nested_id, layer_id] = [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+        let [loose, grouped, nested, hidden] = [
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+        ];
+        let group = CompositionGroup::new(
+            group_id,
+            FragmentTransform2DData::new([100.0, 0.0, 0.0], [0.0, 0.0], [2.0, 2.0], 0.0, 0.0),
+        )
+        .with_z_order(ZOrderKey::first());
+        let nested_group = CompositionGroup::new(
+            nested_id,
+            FragmentTransform2DData::new([10.0, 0.0, 0.0], [0.0, 0.0], [1.0, 1.0], FRAC_PI_2, 0.0),
+        )
+        .with_parent(ParentRef::group(group_id));
+        let composition = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7())
+            .add_group(group)
+            .add_group(nested_group)
+            .add_layer(CompositionLayer::new(layer_id, "sketch".to_string()).with_visible(false))
+            .add_resource(CompositionResourceData::new(loose, [0.0, 0.0], 4.0, 4.0))
+            .add_resource(
+                CompositionResourceData::new(grouped, [5.0, 0.0], 4.0, 4.0)
+                    .with_parent(ParentRef::group(group_id))
+                    .with_z_order(ZOrderKey::first()),
+            )
+            .add_resource(
+                CompositionResourceData::new(nested, [3.0, 0.0], 2.0, 2.0)
+                    .with_parent(ParentRef::group(nested_id)),
+            )
+            .add_resource(
+                CompositionResourceData::new(hidden, [0.0, 0.0], 4.0, 4.0)
+                    .with_parent(ParentRef::layer(layer_id)),
+            )
+            .build()
+            .unwrap();
+
+        let flattened = composition.flatten();
+        let order: Vec<_> = flattened.iter().map(|it| it.resource().id()).collect();
+        assert!(order == [loose, nested, grouped, hidden]);
+        let origin = |index: usize| {
+            flattened[index]
+                .world_transform()
+                .transform_point([0.0, 0.0])
+        };
+        assert!(origin(2) == [110.0, 0.0]);
+        // 100 + 2 * (10 + rotated (3, 0))
+        let nested_origin = origin(1);
+        assert!((nested_origin[0] - 120.0).abs() < 1e-4 && (nested_origin[1] - 6.0).abs() < 1e-4);
+        assert!(flattened[1].depth() == 2 && !flattened[3].visible());
+
+        // The hidden layer does not answer, the group resource does.
+        assert!(composition.hit_test([0.0, 0.0]).map(|it| it.id()) == Some(loose));
+        assert!(composition.hit_test([113.0, 3.0]).map(|it| it.id()) == Some(grouped));
+
+        // Keys are computed among the siblings of the group only.
+        let in_group = Some(ParentRef::group(group_id));
+        let key = composition
+            .z_order_key_above(in_group, Some(nested_id))
+            .unwrap();
+        assert!(key > ZOrderKey::default() && key < ZOrderKey::first());
+        assert!(
+            composition
+                .z_order_key_above(in_group, Some(loose))
+                .is_none()
+        );
+
+        let cyclic = composition.clone().with_groups(vec![
+            CompositionGroup::new(
+                group_id,
+                FragmentTransform2DData::from_pos_size([0.0; 2], 0.0, 0.0),
+            )
+            .with_parent(ParentRef::group(nested_id)),
+            CompositionGroup::new(
+                nested_id,
+                FragmentTransform2DData::from_pos_size([0.0; 2], 0.0, 0.0),
+            )
+            .with_parent(ParentRef::group(group_id)),
+        ]);
+        assert!(cyclic.validate().is_err());
+    }
+}
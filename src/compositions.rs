@@ -1,7 +1,15 @@
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+use crate::{
+    Aabb, Affine2, ClipShape, CompositionGroup, CompositionLayer, ContentHash, DecodeLimitError,
+    DecodeLimited, DecodeLimits, Easing, FragmentStyle, IdKind, IdMapping, ParentRef, RemapIds,
+    RessourceKind, ScreenPoint, TransformTrack, Validate, Viewport, ViolationKind, Violations,
+    ZOrderKey, field_path,
+};
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Decode, Encode)]
 pub struct FragmentTransform2DData {
     pos: [f32; 3],
@@ -30,6 +38,15 @@ impl FragmentTransform2DData {
             z: 0.0,
         }
     }
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+    /// Moves the transform to `pos`, keeping its depth.
+    pub fn with_pos(mut self, pos: [f32; 2]) -> Self {
+        self.pos = [pos[0], pos[1], self.pos[2]];
+        self
+    }
     pub fn pos(&self) -> [f32; 3] {
         self.pos
     }
@@ -45,29 +62,144 @@ impl FragmentTransform2DData {
     pub fn z(&self) -> f32 {
         self.z
     }
+    /// Local to world transform. Local space is the unscaled fragment, centered on the origin;
+    /// `scale` applies first, then `rot` (counterclockwise, in radians), then the translation
+    /// to `pos`.
+    pub fn to_affine(&self) -> Affine2 {
+        Affine2::from_scale_rotation_translation(self.scale, self.rot, [self.pos[0], self.pos[1]])
+    }
+    /// World to local transform, `None` when a scale is zero.
+    pub fn inverse_affine(&self) -> Option<Affine2> {
+        self.to_affine().inverse()
+    }
+    /// Corners of the oriented bounding box in world coordinates, counterclockwise from the
+    /// local bottom left.
+    pub fn corners(&self) -> [[f32; 2]; 4] {
+        let affine = self.to_affine();
+        let [half_w, half_h] = [self.dimensions[0] / 2.0, self.dimensions[1] / 2.0];
+        [
+            [-half_w, -half_h],
+            [half_w, -half_h],
+            [half_w, half_h],
+            [-half_w, half_h],
+        ]
+        .map(|corner| affine.transform_point(corner))
+    }
+    /// Axis-aligned bounds of the rotated and scaled fragment.
+    pub fn aabb(&self) -> Aabb {
+        let [first, rest @ ..] = self.corners();
+        rest.into_iter()
+            .fold(Aabb::new(first, first), |aabb, corner| {
+                aabb.union(&Aabb::new(corner, corner))
+            })
+    }
+    /// Whether `world_pos` falls inside the oriented fragment, edges included.
+    pub fn contains_point(&self, world_pos: [f32; 2]) -> bool {
+        let Some(inverse) = self.inverse_affine() else {
+            return false;
+        };
+        let [x, y] = inverse.transform_point(world_pos);
+        x.abs() <= self.dimensions[0] / 2.0 && y.abs() <= self.dimensions[1] / 2.0
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Decode, Encode)]
 pub struct CompositionResourceData {
     id: [u8; 16],
     transform: FragmentTransform2DData,
+    #[serde(default)]
+    kind: RessourceKind,
+    #[serde(default)]
+    z_order: ZOrderKey,
+    /// Group or layer holding the resource, `transform` is relative to a parent group.
+    #[serde(default)]
+    parent: Option<ParentRef>,
+    #[serde(default, skip_serializing_if = "FragmentStyle::is_default")]
+    style: FragmentStyle,
+    /// Moves the resource over time, `transform` is used when there is none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    track: Option<TransformTrack>,
 }
 impl CompositionResourceData {
+    /// Places an animation, use [`Self::with_kind`] for other media.
     pub fn new(id: Uuid, pos_world_coord: [f32; 2], width: f32, height: f32) -> Self {
         Self {
             id: id.into_bytes(),
             transform: FragmentTransform2DData::from_pos_size(pos_world_coord, width, height),
+            kind: RessourceKind::Animation,
+            z_order: ZOrderKey::default(),
+            parent: None,
+            style: FragmentStyle::default(),
+            track: None,
         }
     }
     pub fn new_with_transform(id: Uuid, transform: FragmentTransform2DData) -> Self {
         Self {
             id: id.into_bytes(),
             transform,
+            kind: RessourceKind::Animation,
+            z_order: ZOrderKey::default(),
+            parent: None,
+            style: FragmentStyle::default(),
+            track: None,
         }
     }
+    pub fn with_parent(mut self, parent: ParentRef) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+    pub fn with_z_order(mut self, z_order: ZOrderKey) -> Self {
+        self.z_order = z_order;
+        self
+    }
+    pub fn with_style(mut self, style: FragmentStyle) -> Self {
+        self.style = style;
+        self
+    }
+    pub fn with_track(mut self, track: TransformTrack) -> Self {
+        self.track = Some(track);
+        self
+    }
+    pub fn with_kind(mut self, kind: RessourceKind) -> Self {
+        self.kind = kind;
+        self
+    }
     pub fn id(&self) -> Uuid {
         Uuid::from_bytes(self.id)
     }
+    pub fn kind(&self) -> RessourceKind {
+        self.kind
+    }
+    pub fn parent(&self) -> Option<ParentRef> {
+        self.parent
+    }
+    pub(crate) fn set_parent(&mut self, parent: Option<ParentRef>) {
+        self.parent = parent;
+    }
+    /// Stacking key, see [`CompositionData::resources_by_z_order`].
+    pub fn z_order(&self) -> &ZOrderKey {
+        &self.z_order
+    }
+    pub(crate) fn set_z_order(&mut self, z_order: ZOrderKey) {
+        self.z_order = z_order;
+    }
+    pub fn style(&self) -> &FragmentStyle {
+        &self.style
+    }
+    pub(crate) fn set_style(&mut self, style: FragmentStyle) {
+        self.style = style;
+    }
+    pub fn track(&self) -> Option<&TransformTrack> {
+        self.track.as_ref()
+    }
+    /// Bottom to top order: `z_order`, then `transform.z` for resources sharing a key (the
+    /// empty key of older data), then the id so that every replica agrees.
+    pub fn stacking_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.z_order
+            .cmp(&other.z_order)
+            .then(self.transform.z.total_cmp(&other.transform.z))
+            .then(self.id.cmp(&other.id))
+    }
     pub fn width(&self) -> f32 {
         self.transform.dimensions[0]
     }
@@ -77,11 +209,15 @@ impl CompositionResourceData {
     pub fn transform(&self) -> &FragmentTransform2DData {
         &self.transform
     }
+    pub(crate) fn set_transform(&mut self, transform: FragmentTransform2DData) {
+        self.transform = transform;
+    }
     pub fn pos_world_coord(&self) -> [f32; 2] {
         [self.transform.pos[0], self.transform.pos[1]]
     }
-    pub fn pos_screen_coord(&self) -> [f32; 2] {
-        self.pos_world_coord()
+    /// Position on the screen of `viewport`, in logical pixels.
+    pub fn pos_screen_coord(&self, viewport: &Viewport) -> ScreenPoint {
+        viewport.screen_from_world(self.pos_world_coord().into())
     }
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
@@ -95,11 +231,20 @@ pub struct CompositionData {
     max_x: f32,
     min_y: f32,
     max_y: f32,
+    /// Bumped by the server on every stored change.
+    #[serde(default)]
+    version: u64,
+    #[serde(default)]
+    groups: Vec<CompositionGroup>,
+    #[serde(default)]
+    layers: Vec<CompositionLayer>,
 }
 
+/// Same composition, same version and same content. See [`CompositionData::same_identity`]
+/// to compare ids only.
 impl PartialEq for CompositionData {
     fn eq(&self, other: &Self) -> bool {
-        if self.id == other.id { true } else { false }
+        self.same_identity(other) && self.version == other.version && self.same_content(other)
     }
 }
 impl Eq for CompositionData {}
@@ -126,6 +271,9 @@ impl CompositionData {
             max_x,
             min_y,
             max_y,
+            version: 0,
+            groups: vec![],
+            layers: vec![],
         }
     }
 
@@ -135,9 +283,296 @@ impl CompositionData {
     pub fn author_id(&self) -> Uuid {
         Uuid::from_bytes(self.author_id)
     }
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+    pub fn groups(&self) -> &[CompositionGroup] {
+        &self.groups
+    }
+    pub fn layers(&self) -> &[CompositionLayer] {
+        &self.layers
+    }
+    pub fn with_groups(mut self, groups: Vec<CompositionGroup>) -> Self {
+        self.groups = groups;
+        self
+    }
+    pub fn with_layers(mut self, layers: Vec<CompositionLayer>) -> Self {
+        self.layers = layers;
+        self
+    }
+    pub fn with_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+    /// Bumps the version after a change, returns the new one.
+    pub fn next_version(&mut self) -> u64 {
+        self.version += 1;
+        self.version
+    }
+    /// Whether both are versions of the same composition, whatever their content.
+    pub fn same_identity(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+    /// Whether both hold the same layout, field by field with the canonicalization of
+    /// [`Self::content_hash`] so that both always agree. Versions are ignored.
+    pub fn same_content(&self, other: &Self) -> bool {
+        if self.id != other.id
+            || self.author_id != other.author_id
+            || !same_floats(&self.bounds_floats(), &other.bounds_floats())
+            || self.resource_collection.len() != other.resource_collection.len()
+            || self.groups.len() != other.groups.len()
+            || self.layers.len() != other.layers.len()
+        {
+            return false;
+        }
+        let mut resources = sorted_by_key(&self.resource_collection, |it| it.id)
+            .into_iter()
+            .zip(sorted_by_key(&other.resource_collection, |it| it.id));
+        let mut groups = sorted_by_key(&self.groups, |it| it.group_id())
+            .into_iter()
+            .zip(sorted_by_key(&other.groups, |it| it.group_id()));
+        let mut layers = sorted_by_key(&self.layers, |it| it.layer_id())
+            .into_iter()
+            .zip(sorted_by_key(&other.layers, |it| it.layer_id()));
+        resources.all(|(a, b)| {
+            a.id == b.id
+                && a.kind == b.kind
+                && same_transform(&a.transform, &b.transform)
+                && a.z_order == b.z_order
+                && a.parent == b.parent
+                && a.style == b.style
+                && same_track(a.track.as_ref(), b.track.as_ref())
+        }) && groups.all(|(a, b)| {
+            a.group_id() == b.group_id()
+                && a.parent() == b.parent()
+                && a.z_order() == b.z_order()
+                && same_transform(a.transform(), b.transform())
+        }) && layers.all(|(a, b)| a == b)
+    }
+    /// Whether `self` is an older version of `other`, for caches to drop it.
+    pub fn is_stale_against(&self, other: &Self) -> bool {
+        self.same_identity(other) && self.version < other.version
+    }
+    /// Hash of the id, author, bounds and resources, the version excluded. Resources are
+    /// hashed in id order since their order in the collection carries no meaning, and floats
+    /// by their bits once `-0.0` and NaN are canonicalized, so that every peer computes the
+    /// same hash for the same layout.
+    pub fn content_hash(&self) -> ContentHash {
+        let mut hasher = Sha256::new();
+        hasher.update(self.id);
+        hasher.update(self.author_id);
+        hash_floats(&mut hasher, &self.bounds_floats());
+        let resources = sorted_by_key(&self.resource_collection, |it| it.id);
+        hasher.update((resources.len() as u64).to_le_bytes());
+        for resource in resources {
+            hasher.update(resource.id);
+            hasher.update([resource.kind as u8]);
+            hash_transform(&mut hasher, &resource.transform);
+            let z_order = resource.z_order.as_bytes();
+            hasher.update((z_order.len() as u64).to_le_bytes());
+            hasher.update(z_order);
+            hash_parent(&mut hasher, resource.parent);
+            hash_style(&mut hasher, &resource.style);
+            hash_track(&mut hasher, resource.track.as_ref());
+        }
+        let groups = sorted_by_key(&self.groups, |it| it.group_id());
+        hasher.update((groups.len() as u64).to_le_bytes());
+        for group in groups {
+            hasher.update(group.group_id().as_bytes());
+            hash_transform(&mut hasher, group.transform());
+            let z_order = group.z_order().as_bytes();
+            hasher.update((z_order.len() as u64).to_le_bytes());
+            hasher.update(z_order);
+            hash_parent(&mut hasher, group.parent());
+        }
+        let layers = sorted_by_key(&self.layers, |it| it.layer_id());
+        hasher.update((layers.len() as u64).to_le_bytes());
+        for layer in layers {
+            hasher.update(layer.layer_id().as_bytes());
+            hasher.update((layer.name().len() as u64).to_le_bytes());
+            hasher.update(layer.name());
+            hasher.update([layer.visible() as u8, layer.locked() as u8]);
+            let z_order = layer.z_order().as_bytes();
+            hasher.update((z_order.len() as u64).to_le_bytes());
+            hasher.update(z_order);
+        }
+        hasher.finalize().into()
+    }
     pub fn resources_collection(&self) -> &[CompositionResourceData] {
         self.resource_collection.as_slice()
     }
+    pub fn resource(&self, resource_id: Uuid) -> Option<&CompositionResourceData> {
+        let resource_id = resource_id.into_bytes();
+        self.resource_collection
+            .iter()
+            .find(|it| it.id == resource_id)
+    }
+    pub(crate) fn resources_mut(&mut self) -> &mut Vec<CompositionResourceData> {
+        &mut self.resource_collection
+    }
+    pub(crate) fn groups_mut(&mut self) -> &mut Vec<CompositionGroup> {
+        &mut self.groups
+    }
+    pub(crate) fn layers_mut(&mut self) -> &mut Vec<CompositionLayer> {
+        &mut self.layers
+    }
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+    pub fn min_x(&self) -> f32 {
+        self.min_x
+    }
+    pub fn max_x(&self) -> f32 {
+        self.max_x
+    }
+    pub fn min_y(&self) -> f32 {
+        self.min_y
+    }
+    pub fn max_y(&self) -> f32 {
+        self.max_y
+    }
+    pub fn bounds(&self) -> CompositionBounds {
+        CompositionBounds {
+            width: self.width,
+            height: self.height,
+            min_x: self.min_x,
+            max_x: self.max_x,
+            min_y: self.min_y,
+            max_y: self.max_y,
+        }
+    }
+    fn bounds_floats(&self) -> [f32; 6] {
+        [
+            self.width,
+            self.height,
+            self.min_x,
+            self.max_x,
+            self.min_y,
+            self.max_y,
+        ]
+    }
+    pub(crate) fn set_bounds(&mut self, bounds: &CompositionBounds) {
+        self.width = bounds.width;
+        self.height = bounds.height;
+        self.min_x = bounds.min_x;
+        self.max_x = bounds.max_x;
+        self.min_y = bounds.min_y;
+        self.max_y = bounds.max_y;
+    }
+    /// Maps a world position to 0.0..=1.0 inside the bounds. An empty axis maps to 0.0.
+    pub fn normalized_from_world(&self, world_pos: [f32; 2]) -> [f32; 2] {
+        [
+            normalize_on_axis(world_pos[0], self.min_x, self.max_x),
+            normalize_on_axis(world_pos[1], self.min_y, self.max_y),
+        ]
+    }
+    pub fn world_from_normalized(&self, normalized_pos: [f32; 2]) -> [f32; 2] {
+        [
+            self.min_x + normalized_pos[0] * (self.max_x - self.min_x),
+            self.min_y + normalized_pos[1] * (self.max_y - self.min_y),
+        ]
+    }
+    pub fn builder(id: Uuid, author_id: Uuid) -> CompositionDataBuilder {
+        CompositionDataBuilder::new(id, author_id)
+    }
+    /// Union of the world bounds of the drawn resources, see [`Self::flatten`]. `None`
+    /// without resources.
+    pub fn resources_aabb(&self) -> Option<Aabb> {
+        self.flatten()
+            .iter()
+            .map(|it| it.world_aabb())
+            .reduce(|a, b| a.union(&b))
+    }
+    /// Resets the bounds, width and height to the union of the resources bounds, as
+    /// [`CompositionDataBuilder::build`] does when they are left unset. A composition without
+    /// resources is left untouched. Returns whether anything changed.
+    pub fn recompute_bounds(&mut self) -> bool {
+        let Some(aabb) = self.resources_aabb() else {
+            return false;
+        };
+        let before = [
+            self.min_x,
+            self.max_x,
+            self.min_y,
+            self.max_y,
+            self.width,
+            self.height,
+        ];
+        [self.min_x, self.min_y] = aabb.min();
+        [self.max_x, self.max_y] = aabb.max();
+        self.width = aabb.width();
+        self.height = aabb.height();
+        before
+            != [
+                self.min_x,
+                self.max_x,
+                self.min_y,
+                self.max_y,
+                self.width,
+                self.height,
+            ]
+    }
+    /// Topmost visible resource under `world_pos`, locked layers excluded, in the drawing
+    /// order of [`Self::flatten`].
+    pub fn hit_test(&self, world_pos: [f32; 2]) -> Option<&CompositionResourceData> {
+        self.flatten()
+            .into_iter()
+            .rev()
+            .find(|it| it.visible() && !it.locked() && it.contains_point(world_pos))
+            .map(|it| it.resource())
+    }
+    /// Resources from bottom to top, see [`CompositionResourceData::stacking_cmp`]. Groups and
+    /// layers are ignored, see [`Self::flatten`] for the drawing order.
+    pub fn resources_by_z_order(&self) -> Vec<&CompositionResourceData> {
+        let mut resources: Vec<_> = self.resource_collection.iter().collect();
+        resources.sort_by(|a, b| a.stacking_cmp(b));
+        resources
+    }
+    /// Key placing a resource above every other one.
+    pub fn z_order_key_on_top(&self) -> ZOrderKey {
+        self.resource_collection
+            .iter()
+            .map(|it| &it.z_order)
+            .max()
+            .map_or_else(ZOrderKey::first, ZOrderKey::after)
+    }
+    /// Key placing a node under `parent` right above its sibling `below_id`, under the
+    /// sibling that currently follows it, or at the bottom of `parent` for `None`. Siblings
+    /// are the [`Self::children`] of `parent`, groups included. Only the moved node needs the
+    /// new key. Returns `None` when `below_id` is not a child of `parent`, or when no key fits
+    /// because siblings share the key of `below_id`.
+    pub fn z_order_key_above(
+        &self,
+        parent: Option<ParentRef>,
+        below_id: Option<Uuid>,
+    ) -> Option<ZOrderKey> {
+        let siblings = self.children(parent);
+        let position = match below_id {
+            Some(below_id) => Some(siblings.iter().position(|it| it.id() == below_id)?),
+            None => None,
+        };
+        let below = position.map(|it| siblings[it].z_order());
+        let above = siblings
+            .get(position.map_or(0, |it| it + 1))
+            .map(|it| it.z_order());
+        ZOrderKey::between(below, above)
+    }
+}
+
+/// Size and bounds of a [`CompositionData`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Decode, Encode)]
+pub struct CompositionBounds {
+    width: f32,
+    height: f32,
+    min_x: f32,
+    max_x: f32,
+    min_y: f32,
+    max_y: f32,
+}
+impl CompositionBounds {
     pub fn width(&self) -> f32 {
         self.width
     }
@@ -157,3 +592,529 @@ impl CompositionData {
         self.max_y
     }
 }
+
+/// Bits of `value` once `-0.0` and NaN are canonicalized.
+fn canonical_bits(value: f32) -> u32 {
+    let canonical = if value.is_nan() {
+        f32::NAN
+    } else if value == 0.0 {
+        0.0
+    } else {
+        value
+    };
+    canonical.to_bits()
+}
+
+fn hash_floats(hasher: &mut Sha256, values: &[f32]) {
+    for value in values {
+        hasher.update(canonical_bits(*value).to_le_bytes());
+    }
+}
+
+fn same_floats(a: &[f32], b: &[f32]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| canonical_bits(*a) == canonical_bits(*b))
+}
+
+fn transform_floats(transform: &FragmentTransform2DData) -> [f32; 9] {
+    let [x, y, z_pos] = transform.pos;
+    let [width, height] = transform.dimensions;
+    let [scale_x, scale_y] = transform.scale;
+    [
+        x,
+        y,
+        z_pos,
+        width,
+        height,
+        scale_x,
+        scale_y,
+        transform.rot,
+        transform.z,
+    ]
+}
+
+fn hash_transform(hasher: &mut Sha256, transform: &FragmentTransform2DData) {
+    hash_floats(hasher, &transform_floats(transform));
+}
+
+fn same_transform(a: &FragmentTransform2DData, b: &FragmentTransform2DData) -> bool {
+    same_floats(&transform_floats(a), &transform_floats(b))
+}
+
+fn same_track(a: Option<&TransformTrack>, b: Option<&TransformTrack>) -> bool {
+    let [a, b] = [a, b].map(|it| it.map_or(&[][..], |it| it.keyframes()));
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.time() == b.time()
+                && same_transform(a.transform(), b.transform())
+                && match (a.easing(), b.easing()) {
+                    (
+                        Easing::CubicBezier { x1, y1, x2, y2 },
+                        Easing::CubicBezier {
+                            x1: other_x1,
+                            y1: other_y1,
+                            x2: other_x2,
+                            y2: other_y2,
+                        },
+                    ) => same_floats(&[x1, y1, x2, y2], &[other_x1, other_y1, other_x2, other_y2]),
+                    (a, b) => a == b,
+                }
+        })
+}
+
+fn sorted_by_key<T, K: Ord>(items: &[T], key: impl Fn(&T) -> K) -> Vec<&T> {
+    let mut sorted: Vec<_> = items.iter().collect();
+    sorted.sort_by_key(|it| key(it));
+    sorted
+}
+
+fn hash_parent(hasher: &mut Sha256, parent: Option<ParentRef>) {
+    match parent {
+        None => hasher.update([0]),
+        Some(ParentRef::Group(id)) => {
+            hasher.update([1]);
+            hasher.update(id);
+        }
+        Some(ParentRef::Layer(id)) => {
+            hasher.update([2]);
+            hasher.update(id);
+        }
+    }
+}
+
+fn hash_style(hasher: &mut Sha256, style: &FragmentStyle) {
+    hasher.update([(style.opacity() * u8::MAX as f32).round() as u8]);
+    hasher.update([style.blend_mode() as u8]);
+    hasher.update(style.tint());
+    match style.crop() {
+        None => hasher.update([0]),
+        Some(crop) => {
+            hasher.update([1]);
+            for value in [crop.x(), crop.y(), crop.width(), crop.height()] {
+                hasher.update(value.to_le_bytes());
+            }
+        }
+    }
+    match style.clip() {
+        None => hasher.update([0]),
+        Some(ClipShape::RoundedRect { corner_radius }) => {
+            hasher.update([1]);
+            hasher.update(corner_radius.to_le_bytes());
+        }
+        Some(ClipShape::Ellipse) => hasher.update([2]),
+        Some(ClipShape::Polygon { points }) => {
+            hasher.update([3]);
+            hasher.update((points.len() as u64).to_le_bytes());
+            for point in points {
+                hasher.update(point[0].to_le_bytes());
+                hasher.update(point[1].to_le_bytes());
+            }
+        }
+    }
+}
+
+fn hash_track(hasher: &mut Sha256, track: Option<&TransformTrack>) {
+    let keyframes = track.map_or(&[][..], |it| it.keyframes());
+    hasher.update((keyframes.len() as u64).to_le_bytes());
+    for keyframe in keyframes {
+        hasher.update((keyframe.time().as_millis() as u64).to_le_bytes());
+        hash_transform(hasher, keyframe.transform());
+        match keyframe.easing() {
+            Easing::Linear => hasher.update([0]),
+            Easing::Step => hasher.update([1]),
+            Easing::EaseIn => hasher.update([2]),
+            Easing::EaseOut => hasher.update([3]),
+            Easing::EaseInOut => hasher.update([4]),
+            Easing::CubicBezier { x1, y1, x2, y2 } => {
+                hasher.update([5]);
+                hash_floats(hasher, &[x1, y1, x2, y2]);
+            }
+        }
+    }
+}
+
+fn normalize_on_axis(value: f32, min: f32, max: f32) -> f32 {
+    let extent = max - min;
+    if extent == 0.0 {
+        0.0
+    } else {
+        (value - min) / extent
+    }
+}
+
+/// [`CompositionDataBuilder`] names every field of a [`CompositionData`]. Bounds left unset are
+/// computed from the resource collection, and width/height default to the bounds extent.
+#[derive(Debug, Clone)]
+pub struct CompositionDataBuilder {
+    id: Uuid,
+    author_id: Uuid,
+    resource_collection: Vec<CompositionResourceData>,
+    width: Option<f32>,
+    height: Option<f32>,
+    min_x: Option<f32>,
+    max_x: Option<f32>,
+    min_y: Option<f32>,
+    max_y: Option<f32>,
+    version: u64,
+    groups: Vec<CompositionGroup>,
+    layers: Vec<CompositionLayer>,
+}
+
+impl CompositionDataBuilder {
+    pub fn new(id: Uuid, author_id: Uuid) -> Self {
+        Self {
+            id,
+            author_id,
+            resource_collection: vec![],
+            width: None,
+            height: None,
+            min_x: None,
+            max_x: None,
+            min_y: None,
+            max_y: None,
+            version: 0,
+            groups: vec![],
+            layers: vec![],
+        }
+    }
+    pub fn resources(mut self, resource_collection: Vec<CompositionResourceData>) -> Self {
+        self.resource_collection = resource_collection;
+        self
+    }
+    pub fn add_resource(mut self, resource: CompositionResourceData) -> Self {
+        self.resource_collection.push(resource);
+        self
+    }
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = Some(width);
+        self
+    }
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = Some(height);
+        self
+    }
+    pub fn min_x(mut self, min_x: f32) -> Self {
+        self.min_x = Some(min_x);
+        self
+    }
+    pub fn max_x(mut self, max_x: f32) -> Self {
+        self.max_x = Some(max_x);
+        self
+    }
+    pub fn min_y(mut self, min_y: f32) -> Self {
+        self.min_y = Some(min_y);
+        self
+    }
+    pub fn max_y(mut self, max_y: f32) -> Self {
+        self.max_y = Some(max_y);
+        self
+    }
+    pub fn version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+    pub fn add_group(mut self, group: CompositionGroup) -> Self {
+        self.groups.push(group);
+        self
+    }
+    pub fn add_layer(mut self, layer: CompositionLayer) -> Self {
+        self.layers.push(layer);
+        self
+    }
+    pub fn build(self) -> Result<CompositionData, Violations> {
+        let mut composition = CompositionData {
+            id: self.id.into_bytes(),
+            author_id: self.author_id.into_bytes(),
+            resource_collection: self.resource_collection,
+            width: 0.0,
+            height: 0.0,
+            min_x: 0.0,
+            max_x: 0.0,
+            min_y: 0.0,
+            max_y: 0.0,
+            version: self.version,
+            groups: self.groups,
+            layers: self.layers,
+        };
+        let computed = composition
+            .resources_aabb()
+            .unwrap_or(Aabb::new([0.0, 0.0], [0.0, 0.0]));
+        composition.min_x = self.min_x.unwrap_or(computed.min()[0]);
+        composition.max_x = self.max_x.unwrap_or(computed.max()[0]);
+        composition.min_y = self.min_y.unwrap_or(computed.min()[1]);
+        composition.max_y = self.max_y.unwrap_or(computed.max()[1]);
+        composition.width = self.width.unwrap_or(composition.max_x - composition.min_x);
+        composition.height = self.height.unwrap_or(composition.max_y - composition.min_y);
+        composition.validate()?;
+        Ok(composition)
+    }
+}
+
+impl DecodeLimited for CompositionResourceData {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        self.style.check_decode_limits(limits)?;
+        match &self.track {
+            Some(track) => track.check_decode_limits(limits),
+            None => Ok(()),
+        }
+    }
+}
+impl DecodeLimited for CompositionData {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("resource_collection", self.resource_collection.len())?;
+        limits.check_collection("groups", self.groups.len())?;
+        limits.check_collection("layers", self.layers.len())?;
+        for layer in &self.layers {
+            limits.check_string("layers.name", layer.name())?;
+        }
+        for resource in &self.resource_collection {
+            resource.check_decode_limits(limits)?;
+        }
+        Ok(())
+    }
+}
+
+impl RemapIds for CompositionResourceData {
+    fn remap_ids(&mut self, mapping: &IdMapping) -> usize {
+        let kind = match self.kind {
+            RessourceKind::Animation => IdKind::Animation,
+            _ => IdKind::Ressource,
+        };
+        mapping.remap_bytes(kind, &mut self.id) as usize
+            + self.parent.as_mut().map_or(0, |it| it.remap_ids(mapping))
+    }
+}
+impl RemapIds for CompositionData {
+    fn remap_ids(&mut self, mapping: &IdMapping) -> usize {
+        mapping.remap_bytes(IdKind::Composition, &mut self.id) as usize
+            + mapping.remap_bytes(IdKind::User, &mut self.author_id) as usize
+            + self.resource_collection.remap_ids(mapping)
+            + self.groups.remap_ids(mapping)
+            + self.layers.remap_ids(mapping)
+    }
+}
+
+/// Layout of [`CompositionResourceData`] before it carried a [`RessourceKind`], every resource
+/// was an animation.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
+pub struct LegacyCompositionResourceData {
+    id: [u8; 16],
+    transform: FragmentTransform2DData,
+}
+impl From<LegacyCompositionResourceData> for CompositionResourceData {
+    fn from(value: LegacyCompositionResourceData) -> Self {
+        Self {
+            id: value.id,
+            transform: value.transform,
+            kind: RessourceKind::Animation,
+            z_order: ZOrderKey::default(),
+            parent: None,
+            style: FragmentStyle::default(),
+            track: None,
+        }
+    }
+}
+
+/// Layout of [`CompositionData`] holding [`LegacyCompositionResourceData`]. Decode already
+/// stored data with it and convert with `into()`.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
+pub struct LegacyCompositionData {
+    id: [u8; 16],
+    author_id: [u8; 16],
+    resource_collection: Vec<LegacyCompositionResourceData>,
+    width: f32,
+    height: f32,
+    min_x: f32,
+    max_x: f32,
+    min_y: f32,
+    max_y: f32,
+}
+impl DecodeLimited for LegacyCompositionData {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("resource_collection", self.resource_collection.len())
+    }
+}
+impl From<LegacyCompositionData> for CompositionData {
+    fn from(value: LegacyCompositionData) -> Self {
+        Self {
+            id: value.id,
+            author_id: value.author_id,
+            resource_collection: value
+                .resource_collection
+                .into_iter()
+                .map(CompositionResourceData::from)
+                .collect(),
+            width: value.width,
+            height: value.height,
+            min_x: value.min_x,
+            max_x: value.max_x,
+            min_y: value.min_y,
+            max_y: value.max_y,
+            version: 0,
+            groups: vec![],
+            layers: vec![],
+        }
+    }
+}
+
+impl Validate for FragmentTransform2DData {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        for (field, values) in [
+            ("pos", &self.pos[..]),
+            ("dimensions", &self.dimensions[..]),
+            ("scale", &self.scale[..]),
+            ("rot", &[self.rot][..]),
+            ("z", &[self.z][..]),
+        ] {
+            if values.iter().any(|it| !it.is_finite()) {
+                violations.push(field_path(path, field), ViolationKind::NotFinite);
+            }
+        }
+        if self.dimensions.iter().any(|it| *it < 0.0) {
+            violations.push(
+                field_path(path, "dimensions"),
+                ViolationKind::Invalid("dimensions must not be negative"),
+            );
+        }
+        if self.scale.contains(&0.0) {
+            violations.push(
+                field_path(path, "scale"),
+                ViolationKind::Invalid("scale must not be zero"),
+            );
+        }
+    }
+}
+impl Validate for CompositionResourceData {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "id", &self.id);
+        self.transform
+            .validate_at(&field_path(path, "transform"), violations);
+        self.z_order
+            .validate_at(&field_path(path, "z_order"), violations);
+        self.style
+            .validate_at(&field_path(path, "style"), violations);
+        if let Some(track) = &self.track {
+            track.validate_at(&field_path(path, "track"), violations);
+        }
+    }
+}
+impl Validate for CompositionData {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "id", &self.id);
+        violations.check_not_nil(path, "author_id", &self.author_id);
+        violations.check_range(path, "width", self.width as f64, 0.0, f32::MAX as f64);
+        violations.check_range(path, "height", self.height as f64, 0.0, f32::MAX as f64);
+        for (field, value) in [
+            ("min_x", self.min_x),
+            ("max_x", self.max_x),
+            ("min_y", self.min_y),
+            ("max_y", self.max_y),
+        ] {
+            violations.check_finite(path, field, value);
+        }
+        violations.check_bounds(path, "min_x..max_x", self.min_x, self.max_x);
+        violations.check_bounds(path, "min_y..max_y", self.min_y, self.max_y);
+        violations.check_items(path, "resource_collection", &self.resource_collection);
+        violations.check_items(path, "groups", &self.groups);
+        violations.check_items(path, "layers", &self.layers);
+        self.validate_tree(path, violations);
+    }
+}
+
+#[cfg(test)]
+mod compositions_test {
+    use uuid::Uuid;
+
+    use crate::{CompositionData, CompositionResourceData};
+
+    #[test]
+    fn builder_computes_bounds_and_reports_invalid_fields() {
+        let [id, author_id] = [Uuid::now_v7(), Uuid::now_v7()];
+        let composition = CompositionData::builder(id, author_id)
+            .add_resource(CompositionResourceData::new(
+                Uuid::now_v7(),
+                [0.0, 0.0],
+                4.0,
+                4.0,
+            ))
+            .add_resource(CompositionResourceData::new(
+                Uuid::now_v7(),
+                [10.0, -6.0],
+                4.0,
+                2.0,
+            ))
+            .build()
+            .unwrap();
+        let aabb = composition.resources_aabb().unwrap();
+        assert!(composition.min_x() == aabb.min()[0] && composition.max_x() == aabb.max()[0]);
+        assert!(composition.min_y() == aabb.min()[1] && composition.max_y() == aabb.max()[1]);
+        assert!(composition.width() == composition.max_x() - composition.min_x());
+        assert!(composition.height() == composition.max_y() - composition.min_y());
+
+        // Swapped x bounds, a negative width and a resource with a negative size.
+        let violations = CompositionData::builder(Uuid::nil(), author_id)
+            .add_resource(CompositionResourceData::new(
+                Uuid::now_v7(),
+                [0.0, 0.0],
+                -4.0,
+                4.0,
+            ))
+            .min_x(10.0)
+            .max_x(0.0)
+            .min_y(0.0)
+            .max_y(10.0)
+            .build()
+            .unwrap_err();
+        let paths: Vec<_> = violations.iter().map(|it| it.field_path()).collect();
+        assert!(
+            paths
+                == [
+                    "id",
+                    "width",
+                    "min_x..max_x",
+                    "resource_collection[0].transform.dimensions"
+                ]
+        );
+    }
+
+    #[test]
+    fn content_hash_ignores_collection_order_and_signed_zeros() {
+        let [id, author_id] = [Uuid::now_v7(), Uuid::now_v7()];
+        let a = CompositionResourceData::new(Uuid::now_v7(), [0.0, 1.0], 2.0, 2.0);
+        let b = CompositionResourceData::new(Uuid::now_v7(), [-0.0, 3.0], 2.0, 2.0);
+        let b_positive_zero = CompositionResourceData::new(b.id(), [0.0, 3.0], 2.0, 2.0);
+        let first = CompositionData::builder(id, author_id)
+            .resources(vec![a.clone(), b])
+            .build()
+            .unwrap();
+        let second = CompositionData::builder(id, author_id)
+            .resources(vec![b_positive_zero, a.clone()])
+            .version(4)
+            .build()
+            .unwrap();
+        assert!(first.same_content(&second));
+        assert!(first.content_hash() == second.content_hash());
+        assert!(first != second && first == second.clone().with_version(0));
+        assert!(first.is_stale_against(&second));
+
+        let moved = CompositionData::builder(id, author_id)
+            .resources(vec![
+                a,
+                CompositionResourceData::new(Uuid::now_v7(), [0.5, 3.0], 2.0, 2.0),
+            ])
+            .build()
+            .unwrap();
+        assert!(first.same_identity(&moved) && !first.same_content(&moved));
+    }
+}
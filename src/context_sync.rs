@@ -2,6 +2,8 @@ use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::{DecodeLimitError, DecodeLimited, DecodeLimits, Validate, Violations};
+
 #[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
 pub struct LastClientContextVersion {
     context_id: [u8; 16], // UUid
@@ -65,3 +67,31 @@ pub enum AnimationDeltaCollection {
     UptoDate,
     AnimationDelta { ids_collection: Vec<[u8; 16]> },
 }
+
+impl DecodeLimited for LastClientContextVersion {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for PulledContextVersionWithAnimationDelta {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        match &self.animation_delta {
+            AnimationDeltaCollection::UptoDate => Ok(()),
+            AnimationDeltaCollection::AnimationDelta { ids_collection } => {
+                limits.check_collection("animation_delta.ids_collection", ids_collection.len())
+            }
+        }
+    }
+}
+
+impl Validate for LastClientContextVersion {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "context_id", &self.context_id);
+    }
+}
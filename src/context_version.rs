@@ -2,7 +2,11 @@ use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{CompositionData, RessourcesDescriptors};
+use crate::{
+    CompositionData, CompositionDiff, DecodeLimitError, DecodeLimited, DecodeLimits,
+    LegacyCompositionData, LegacyRessourcesDescriptors, RessourcesDescriptors, Validate,
+    Violations, field_path, indexed_path,
+};
 
 #[derive(Debug, Serialize, Deserialize, Hash, PartialEq, Clone, Eq)]
 /// [`ServerContextVersion`] represents two version counter states from the server.
@@ -45,6 +49,11 @@ pub struct PushedUserSessionDeltasWithRessourceDescriptors {
     // contextId -> CompositionData
     compositions_delta: Vec<(ContextId, CompositionData)>,
     has_more_ressources: bool,
+    // contextId -> CompositionDiff, for compositions the client already holds. Bincode
+    // messages sent before this field existed decode with
+    // LegacyPushedUserSessionDeltasWithRessourceDescriptors, `serde(default)` only covers JSON.
+    #[serde(default)]
+    compositions_diffs: Vec<(ContextId, CompositionDiff)>,
 }
 
 impl PushedUserSessionDeltasWithRessourceDescriptors {
@@ -66,14 +75,27 @@ impl PushedUserSessionDeltasWithRessourceDescriptors {
             ressources_descriptors,
             compositions_delta,
             has_more_ressources,
+            compositions_diffs: vec![],
         }
     }
+    /// Sends these compositions as diffs against the version the client holds, instead of
+    /// whole snapshots in `compositions_delta`.
+    pub fn with_compositions_diffs(
+        mut self,
+        compositions_diffs: Vec<(ContextId, CompositionDiff)>,
+    ) -> Self {
+        self.compositions_diffs = compositions_diffs;
+        self
+    }
     pub fn user_id(&self) -> Uuid {
         Uuid::from_bytes(self.user_id)
     }
     pub fn compositions_delta(&self) -> &[(ContextId, CompositionData)] {
         &self.compositions_delta
     }
+    pub fn compositions_diffs(&self) -> &[(ContextId, CompositionDiff)] {
+        &self.compositions_diffs
+    }
     pub fn current_user_session_version(&self) -> u64 {
         self.current_user_session_version
     }
@@ -88,6 +110,38 @@ impl PushedUserSessionDeltasWithRessourceDescriptors {
     }
 }
 
+/// Layout of [`PushedUserSessionDeltasWithRessourceDescriptors`] before placements were
+/// quantized, resources carried a kind and compositions could be sent as diffs. Decode
+/// messages from older servers with it and convert with `into()`.
+#[derive(Debug, Serialize, Encode, Decode, Deserialize, Clone)]
+pub struct LegacyPushedUserSessionDeltasWithRessourceDescriptors {
+    user_id: [u8; 16],
+    current_user_session_version: u64,
+    current_user_context_versions: Vec<([u8; 16], u64)>,
+    ressources_descriptors: LegacyRessourcesDescriptors,
+    compositions_delta: Vec<(ContextId, LegacyCompositionData)>,
+    has_more_ressources: bool,
+}
+impl From<LegacyPushedUserSessionDeltasWithRessourceDescriptors>
+    for PushedUserSessionDeltasWithRessourceDescriptors
+{
+    fn from(value: LegacyPushedUserSessionDeltasWithRessourceDescriptors) -> Self {
+        Self {
+            user_id: value.user_id,
+            current_user_session_version: value.current_user_session_version,
+            current_user_context_versions: value.current_user_context_versions,
+            ressources_descriptors: value.ressources_descriptors.into(),
+            compositions_delta: value
+                .compositions_delta
+                .into_iter()
+                .map(|(context_id, composition)| (context_id, composition.into()))
+                .collect(),
+            has_more_ressources: value.has_more_ressources,
+            compositions_diffs: vec![],
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Encode, Decode, Deserialize, Hash, PartialEq, Clone, Eq)]
 pub struct LastPulledUserSessionVersionAndContextVersions {
     user_id: [u8; 16],
@@ -129,3 +183,171 @@ impl LastPulledUserSessionVersionAndContextVersions {
             .collect()
     }
 }
+
+impl DecodeLimited for PushedUserSessionDeltasWithRessourceDescriptors {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(16 * crate::MIB)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection(
+            "current_user_context_versions",
+            self.current_user_context_versions.len(),
+        )?;
+        limits.check_collection("compositions_delta", self.compositions_delta.len())?;
+        for (_, composition) in &self.compositions_delta {
+            composition.check_decode_limits(limits)?;
+        }
+        limits.check_collection("compositions_diffs", self.compositions_diffs.len())?;
+        for (_, diff) in &self.compositions_diffs {
+            diff.check_decode_limits(limits)?;
+        }
+        self.ressources_descriptors.check_decode_limits(limits)
+    }
+}
+impl DecodeLimited for LegacyPushedUserSessionDeltasWithRessourceDescriptors {
+    fn default_decode_limits() -> DecodeLimits {
+        PushedUserSessionDeltasWithRessourceDescriptors::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection(
+            "current_user_context_versions",
+            self.current_user_context_versions.len(),
+        )?;
+        limits.check_collection("compositions_delta", self.compositions_delta.len())?;
+        for (_, composition) in &self.compositions_delta {
+            composition.check_decode_limits(limits)?;
+        }
+        self.ressources_descriptors.check_decode_limits(limits)
+    }
+}
+impl DecodeLimited for LastPulledUserSessionVersionAndContextVersions {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("contexts", self.contexts.len())
+    }
+}
+
+impl Validate for PushedUserSessionDeltasWithRessourceDescriptors {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "user_id", &self.user_id);
+        for (index, (context_id, _)) in self.current_user_context_versions.iter().enumerate() {
+            violations.check_not_nil(
+                &indexed_path(path, "current_user_context_versions", index),
+                "context_id",
+                context_id,
+            );
+        }
+        for (index, (context_id, composition)) in self.compositions_delta.iter().enumerate() {
+            let item_path = indexed_path(path, "compositions_delta", index);
+            violations.check_not_nil(&item_path, "context_id", context_id);
+            composition.validate_at(&field_path(&item_path, "composition"), violations);
+        }
+        for (index, (context_id, diff)) in self.compositions_diffs.iter().enumerate() {
+            let item_path = indexed_path(path, "compositions_diffs", index);
+            violations.check_not_nil(&item_path, "context_id", context_id);
+            diff.validate_at(&field_path(&item_path, "diff"), violations);
+        }
+        self.ressources_descriptors
+            .validate_at(&field_path(path, "ressources_descriptors"), violations);
+    }
+}
+impl Validate for LastPulledUserSessionVersionAndContextVersions {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "user_id", &self.user_id);
+        for (index, (context_id, _)) in self.contexts.iter().enumerate() {
+            violations.check_not_nil(
+                &indexed_path(path, "contexts", index),
+                "context_id",
+                context_id,
+            );
+        }
+        violations.check_range(
+            path,
+            "max_descriptor_amount",
+            self.max_descriptor_amount as f64,
+            1.0,
+            usize::MAX as f64,
+        );
+    }
+}
+
+#[cfg(test)]
+mod context_version_test {
+    use uuid::Uuid;
+
+    use super::{
+        LegacyPushedUserSessionDeltasWithRessourceDescriptors,
+        PushedUserSessionDeltasWithRessourceDescriptors,
+    };
+    use crate::{FragmentTransform2DData, RessourcesDescriptorsKind, decode_with_default_limits};
+
+    #[test]
+    fn baseline_pushed_deltas_decode_with_the_legacy_layout() {
+        let config = bincode::config::standard();
+        let [user_id, context_id, anim_id, composition_id] = [
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+        ];
+        let transform = FragmentTransform2DData::from_pos_size([12.0, 8.0], 64.0, 32.0);
+        let descriptor =
+            RessourcesDescriptorsKind::new_animation_descriptor(anim_id, user_id, 64, 32);
+
+        // Baseline message: one descriptor with one placement and one composition snapshot.
+        let mut encoded = bincode::encode_to_vec(
+            (
+                user_id.into_bytes(),
+                5u64,
+                vec![(context_id.into_bytes(), 3u64)],
+                1u8,
+            ),
+            config,
+        )
+        .unwrap();
+        encoded.extend(bincode::encode_to_vec((&descriptor, 1u8), config).unwrap());
+        encoded.extend_from_slice(anim_id.as_bytes());
+        encoded.extend_from_slice(composition_id.as_bytes());
+        encoded.extend_from_slice(context_id.as_bytes());
+        encoded
+            .extend(bincode::encode_to_vec((3u64, 1u64, 0u32, 0u32, 0u32, 0u32), config).unwrap());
+        encoded.extend(bincode::encode_to_vec(&transform, config).unwrap());
+        encoded.extend(bincode::encode_to_vec((1u8, context_id.into_bytes()), config).unwrap());
+        encoded.extend(
+            bincode::encode_to_vec(
+                (composition_id.into_bytes(), user_id.into_bytes(), 1u8),
+                config,
+            )
+            .unwrap(),
+        );
+        encoded.extend_from_slice(anim_id.as_bytes());
+        encoded.extend(bincode::encode_to_vec(&transform, config).unwrap());
+        encoded.extend(
+            bincode::encode_to_vec(
+                (128.0f32, 64.0f32, 0.0f32, 128.0f32, 0.0f32, 64.0f32, true),
+                config,
+            )
+            .unwrap(),
+        );
+
+        assert!(
+            decode_with_default_limits::<PushedUserSessionDeltasWithRessourceDescriptors>(&encoded)
+                .is_err()
+        );
+        let legacy: LegacyPushedUserSessionDeltasWithRessourceDescriptors =
+            decode_with_default_limits(&encoded).unwrap();
+        let pushed = PushedUserSessionDeltasWithRessourceDescriptors::from(legacy);
+        assert!(pushed.user_id() == user_id && pushed.current_user_session_version() == 5);
+        assert!(pushed.current_user_context_versions() == [(context_id, 3)]);
+        assert!(pushed.compositions_diffs().is_empty());
+        let (_, composition) = &pushed.compositions_delta()[0];
+        assert!(composition.id() == composition_id);
+        assert!(composition.resource(anim_id).unwrap().transform() == &transform);
+        let item = pushed.ressources_descriptors().iter().next().unwrap();
+        let (decoded_descriptor, contexts) = &*item;
+        assert!(decoded_descriptor == &descriptor && decoded_descriptor.width() == 64);
+        assert!(contexts[0].fragment_transform().dimensions() == transform.dimensions());
+    }
+}
@@ -0,0 +1,314 @@
+use bincode::{BorrowDecode, Decode, config, error::DecodeError};
+
+pub const KIB: usize = 1024;
+pub const MIB: usize = 1024 * KIB;
+
+/// Largest animation payload (data, still frame and mask together) a client may send.
+pub const MAX_ANIMATION_BYTES: usize = 64 * MIB;
+/// Largest chunk accepted by an upload session.
+pub const MAX_UPLOAD_CHUNK_BYTES: usize = 4 * MIB;
+pub const MAX_CONTEXT_PARTICIPANTS: usize = 256;
+pub const MAX_USERNAME_LEN: usize = 64;
+
+/// In memory, a decoded collection can take more room than its encoded form (a `Vec` of
+/// structs claims `len * size_of::<T>()`). Allocations are capped at
+/// `max_total_bytes * ALLOCATION_FACTOR`, rounded up to a power of two.
+const ALLOCATION_FACTOR: usize = 4;
+
+/// [`DecodeLimits`] bounds what a received buffer can make us allocate.
+/// `max_total_bytes` is checked against the buffer before decoding and caps every allocation
+/// made while decoding. Collection and string lengths are checked by [`DecodeLimited`] once the
+/// value is decoded. Byte buffers (`Vec<u8>`) only count against `max_total_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    max_total_bytes: usize,
+    max_collection_len: usize,
+    max_string_len: usize,
+}
+
+impl DecodeLimits {
+    pub const DEFAULT: DecodeLimits = DecodeLimits::new(MIB, 1024, 256);
+
+    pub const fn new(
+        max_total_bytes: usize,
+        max_collection_len: usize,
+        max_string_len: usize,
+    ) -> Self {
+        Self {
+            max_total_bytes,
+            max_collection_len,
+            max_string_len,
+        }
+    }
+    pub const fn with_max_total_bytes(mut self, max_total_bytes: usize) -> Self {
+        self.max_total_bytes = max_total_bytes;
+        self
+    }
+    pub const fn with_max_collection_len(mut self, max_collection_len: usize) -> Self {
+        self.max_collection_len = max_collection_len;
+        self
+    }
+    pub const fn with_max_string_len(mut self, max_string_len: usize) -> Self {
+        self.max_string_len = max_string_len;
+        self
+    }
+    pub fn max_total_bytes(&self) -> usize {
+        self.max_total_bytes
+    }
+    pub fn max_collection_len(&self) -> usize {
+        self.max_collection_len
+    }
+    pub fn max_string_len(&self) -> usize {
+        self.max_string_len
+    }
+    pub fn check_collection(
+        &self,
+        field: &'static str,
+        len: usize,
+    ) -> Result<(), DecodeLimitError> {
+        if len > self.max_collection_len {
+            return Err(DecodeLimitError::CollectionTooLong {
+                field,
+                len,
+                max: self.max_collection_len,
+            });
+        }
+        Ok(())
+    }
+    pub fn check_string(&self, field: &'static str, value: &str) -> Result<(), DecodeLimitError> {
+        if value.len() > self.max_string_len {
+            return Err(DecodeLimitError::StringTooLong {
+                field,
+                len: value.len(),
+                max: self.max_string_len,
+            });
+        }
+        Ok(())
+    }
+    pub fn check_bytes(&self, field: &'static str, len: usize) -> Result<(), DecodeLimitError> {
+        if len > self.max_total_bytes {
+            return Err(DecodeLimitError::TotalBytesExceeded {
+                field,
+                len,
+                max: self.max_total_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Implemented by every type the server or the client receives as bincode.
+pub trait DecodeLimited {
+    /// Limits matching our product constraints for this type.
+    fn default_decode_limits() -> DecodeLimits;
+    /// Checks collection and string lengths of an already decoded value, nested types included.
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError>;
+}
+
+pub fn decode_with_limits<T: Decode<()> + DecodeLimited>(
+    buffer: &[u8],
+    limits: &DecodeLimits,
+) -> Result<T, DecodeLimitError> {
+    limits.check_bytes("buffer", buffer.len())?;
+    let (decoded, _) = decode_with_allocation_limit::<T>(buffer, limits.max_total_bytes)?;
+    decoded.check_decode_limits(limits)?;
+    Ok(decoded)
+}
+
+pub fn decode_with_default_limits<T: Decode<()> + DecodeLimited>(
+    buffer: &[u8],
+) -> Result<T, DecodeLimitError> {
+    decode_with_limits(buffer, &T::default_decode_limits())
+}
+
+pub fn borrow_decode_with_limits<'a, T: BorrowDecode<'a, ()> + DecodeLimited>(
+    buffer: &'a [u8],
+    limits: &DecodeLimits,
+) -> Result<T, DecodeLimitError> {
+    limits.check_bytes("buffer", buffer.len())?;
+    let (decoded, _) = borrow_decode_with_allocation_limit::<T>(buffer, limits.max_total_bytes)?;
+    decoded.check_decode_limits(limits)?;
+    Ok(decoded)
+}
+
+// bincode only takes its allocation limit as a const generic, so the runtime limit picks the
+// smallest power of two bucket that fits.
+macro_rules! dispatch_allocation_limit {
+    ($decode_fn:ident, $buffer:expr, $max_total_bytes:expr, $($shift:literal),*) => {{
+        let wanted = $max_total_bytes
+            .saturating_mul(ALLOCATION_FACTOR)
+            .checked_next_power_of_two()
+            .unwrap_or(usize::MAX);
+        match wanted.trailing_zeros() {
+            $(shift if shift <= $shift => bincode::$decode_fn(
+                $buffer,
+                config::standard().with_limit::<{ 1usize << $shift }>(),
+            ),)*
+            _ => bincode::$decode_fn(
+                $buffer,
+                config::standard().with_limit::<{ 1usize << 31 }>(),
+            ),
+        }
+    }};
+}
+
+fn decode_with_allocation_limit<T: Decode<()>>(
+    buffer: &[u8],
+    max_total_bytes: usize,
+) -> Result<(T, usize), DecodeError> {
+    dispatch_allocation_limit!(
+        decode_from_slice,
+        buffer,
+        max_total_bytes,
+        12,
+        13,
+        14,
+        15,
+        16,
+        17,
+        18,
+        19,
+        20,
+        21,
+        22,
+        23,
+        24,
+        25,
+        26,
+        27,
+        28,
+        29,
+        30
+    )
+}
+
+fn borrow_decode_with_allocation_limit<'a, T: BorrowDecode<'a, ()>>(
+    buffer: &'a [u8],
+    max_total_bytes: usize,
+) -> Result<(T, usize), DecodeError> {
+    dispatch_allocation_limit!(
+        borrow_decode_from_slice,
+        buffer,
+        max_total_bytes,
+        12,
+        13,
+        14,
+        15,
+        16,
+        17,
+        18,
+        19,
+        20,
+        21,
+        22,
+        23,
+        24,
+        25,
+        26,
+        27,
+        28,
+        29,
+        30
+    )
+}
+
+#[derive(Debug)]
+pub enum DecodeLimitError {
+    Decode(DecodeError),
+    TotalBytesExceeded {
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+    CollectionTooLong {
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+    StringTooLong {
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+}
+
+impl From<DecodeError> for DecodeLimitError {
+    fn from(value: DecodeError) -> Self {
+        Self::Decode(value)
+    }
+}
+
+impl std::fmt::Display for DecodeLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Decode(error) => write!(f, "DecodeLimitError Decode [{}]", error),
+            Self::TotalBytesExceeded { field, len, max } => {
+                write!(
+                    f,
+                    "DecodeLimitError TotalBytesExceeded [{}] : [{}] bytes, max [{}]",
+                    field, len, max
+                )
+            }
+            Self::CollectionTooLong { field, len, max } => {
+                write!(
+                    f,
+                    "DecodeLimitError CollectionTooLong [{}] : [{}] items, max [{}]",
+                    field, len, max
+                )
+            }
+            Self::StringTooLong { field, len, max } => {
+                write!(
+                    f,
+                    "DecodeLimitError StringTooLong [{}] : [{}] bytes, max [{}]",
+                    field, len, max
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeLimitError {}
+
+#[cfg(test)]
+mod decode_limits_test {
+    use uuid::Uuid;
+
+    use crate::{
+        DecodeLimitError, DecodeLimits, FriendContact, FriendRegisterDelta, SyncNewAnim,
+        decode_with_default_limits, decode_with_limits,
+    };
+
+    #[test]
+    fn oversized_length_prefix_is_rejected_without_allocating() {
+        // anim_id, author_id, then a varint length prefix announcing u64::MAX bytes of data.
+        let mut forged = vec![0u8; 32];
+        forged.push(253);
+        forged.extend_from_slice(&u64::MAX.to_le_bytes());
+
+        let decoded = decode_with_default_limits::<SyncNewAnim>(&forged);
+        assert!(matches!(decoded, Err(DecodeLimitError::Decode(_))));
+    }
+
+    #[test]
+    fn collection_limit_is_enforced() {
+        let contacts = (0..20)
+            .map(|it| FriendContact::new(format!("user_{}", it), Uuid::now_v7()))
+            .collect();
+        let delta = FriendRegisterDelta::new(contacts, 3);
+        let encoded = bincode::encode_to_vec(&delta, bincode::config::standard()).unwrap();
+
+        let limits = DecodeLimits::DEFAULT.with_max_collection_len(10);
+        let decoded = decode_with_limits::<FriendRegisterDelta>(&encoded, &limits);
+        assert!(matches!(
+            decoded,
+            Err(DecodeLimitError::CollectionTooLong { len: 20, .. })
+        ));
+        assert!(decode_with_default_limits::<FriendRegisterDelta>(&encoded).ok() == Some(delta));
+    }
+}
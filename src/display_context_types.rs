@@ -4,7 +4,11 @@ use postgres_types::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{CompositionData, context_ressources::ContextRessourcesMetaDelta};
+use crate::{
+    CompositionData, DecodeLimitError, DecodeLimited, DecodeLimits, MAX_CONTEXT_PARTICIPANTS,
+    Validate, ViolationKind, Violations, context_ressources::ContextRessourcesMetaDelta,
+    field_path, indexed_path,
+};
 
 /// [`DisplayContext`] represents a context for the client. It reflects the state of the db.
 /// [`participants`] field represents who is authorized to access the content of this context.
@@ -105,6 +109,140 @@ impl DisplayContext {
     pub fn kind(&self) -> DisplayContextKind {
         self.kind
     }
+    pub fn builder(id: Uuid, kind: DisplayContextKind) -> DisplayContextBuilder {
+        DisplayContextBuilder::new(id, kind)
+    }
+}
+
+/// [`DisplayContextBuilder`] names each field of a [`DisplayContext`]. `updated_at` defaults to
+/// `created_at`.
+#[derive(Debug, Clone)]
+pub struct DisplayContextBuilder {
+    id: Uuid,
+    kind: DisplayContextKind,
+    participants: Vec<Uuid>,
+    compositions: Vec<CompositionData>,
+    ressources_delta: Option<ContextRessourcesMetaDelta>,
+    user_session_version: u64,
+    context_version: u64,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl DisplayContextBuilder {
+    pub fn new(id: Uuid, kind: DisplayContextKind) -> Self {
+        Self {
+            id,
+            kind,
+            participants: vec![],
+            compositions: vec![],
+            ressources_delta: None,
+            user_session_version: 0,
+            context_version: 0,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+    pub fn participants(mut self, participants: Vec<Uuid>) -> Self {
+        self.participants = participants;
+        self
+    }
+    pub fn compositions(mut self, compositions: Vec<CompositionData>) -> Self {
+        self.compositions = compositions;
+        self
+    }
+    pub fn ressources_delta(mut self, ressources_delta: ContextRessourcesMetaDelta) -> Self {
+        self.ressources_delta = Some(ressources_delta);
+        self
+    }
+    pub fn user_session_version(mut self, user_session_version: u64) -> Self {
+        self.user_session_version = user_session_version;
+        self
+    }
+    pub fn context_version(mut self, context_version: u64) -> Self {
+        self.context_version = context_version;
+        self
+    }
+    pub fn created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+    pub fn updated_at(mut self, updated_at: DateTime<Utc>) -> Self {
+        self.updated_at = Some(updated_at);
+        self
+    }
+    pub fn build(self) -> Result<DisplayContext, Violations> {
+        let mut violations = Violations::default();
+        let Some(created_at) = self.created_at else {
+            violations.push("created_at".to_string(), ViolationKind::Missing);
+            return Err(violations);
+        };
+        let display_context = DisplayContext {
+            id: self.id.into_bytes(),
+            participants: self
+                .participants
+                .into_iter()
+                .map(|it| it.into_bytes())
+                .collect(),
+            compositions: self.compositions,
+            ressources_delta: self.ressources_delta,
+            user_session_version: self.user_session_version,
+            context_version: self.context_version,
+            created_at: created_at.timestamp(),
+            updated_at: self.updated_at.unwrap_or(created_at).timestamp(),
+            kind: self.kind,
+        };
+        display_context.validate_at("", &mut violations);
+        violations.into_result()?;
+        Ok(display_context)
+    }
+}
+
+impl DecodeLimited for DisplayContext {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(16 * crate::MIB)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits
+            .with_max_collection_len(limits.max_collection_len().min(MAX_CONTEXT_PARTICIPANTS))
+            .check_collection("participants", self.participants.len())?;
+        limits.check_collection("compositions", self.compositions.len())?;
+        for composition in &self.compositions {
+            composition.check_decode_limits(limits)?;
+        }
+        match &self.ressources_delta {
+            Some(delta) => delta.check_decode_limits(limits),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Validate for DisplayContext {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "id", &self.id);
+        violations.check_not_empty(path, "participants", self.participants.len());
+        for (index, participant) in self.participants.iter().enumerate() {
+            if participant == &[0u8; 16] {
+                violations.push(
+                    indexed_path(path, "participants", index),
+                    ViolationKind::NilId,
+                );
+            }
+        }
+        if self.kind == DisplayContextKind::Solo && self.participants.len() > 1 {
+            violations.push(
+                field_path(path, "participants"),
+                ViolationKind::Invalid("a solo context has a single participant"),
+            );
+        }
+        if self.updated_at < self.created_at {
+            violations.push(
+                field_path(path, "updated_at"),
+                ViolationKind::Invalid("updated_at is before created_at"),
+            );
+        }
+        violations.check_items(path, "compositions", &self.compositions);
+    }
 }
 
 #[derive(
@@ -124,6 +262,8 @@ pub mod context_ressources {
     use serde::{Deserialize, Serialize};
     use uuid::Uuid;
 
+    use crate::{DecodeLimitError, DecodeLimited, DecodeLimits, RessourceKind};
+
     type CompositionId = [u8; 16];
     /// [`ContextRessourcesMeta`] represents the collection of ressources attached within the
     /// current version of a context. It does not contain the ressources's data.
@@ -174,6 +314,21 @@ pub mod context_ressources {
             }
         }
     }
+    impl DecodeLimited for ContextRessourcesMetaDelta {
+        fn default_decode_limits() -> DecodeLimits {
+            DecodeLimits::DEFAULT
+        }
+        fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+            limits.check_collection(
+                "ressources_delta.ressources_by_composition",
+                self.ressources_by_composition.len(),
+            )?;
+            for ressources in self.ressources_by_composition.values() {
+                limits.check_collection("ressources_delta.ressources", ressources.len())?;
+            }
+            Ok(())
+        }
+    }
     impl Default for ContextRessourcesMetaDelta {
         fn default() -> Self {
             Self {
@@ -185,6 +340,10 @@ pub mod context_ressources {
     #[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
     pub enum RessourceItem {
         Animation { id: [u8; 16] },
+        TextBlock { id: [u8; 16] },
+        Image { id: [u8; 16] },
+        Sticker { id: [u8; 16] },
+        Audio { id: [u8; 16] },
     }
 
     impl RessourceItem {
@@ -193,10 +352,72 @@ pub mod context_ressources {
                 id: animation_id.into_bytes(),
             }
         }
+        pub fn new_ressource(kind: RessourceKind, ressource_id: Uuid) -> Self {
+            let id = ressource_id.into_bytes();
+            match kind {
+                RessourceKind::Animation => Self::Animation { id },
+                RessourceKind::TextBlock => Self::TextBlock { id },
+                RessourceKind::Image => Self::Image { id },
+                RessourceKind::Sticker => Self::Sticker { id },
+                RessourceKind::Audio => Self::Audio { id },
+            }
+        }
         pub fn get_ressource_id(&self) -> Uuid {
             match self {
-                Self::Animation { id, .. } => Uuid::from_bytes(*id),
+                Self::Animation { id, .. }
+                | Self::TextBlock { id, .. }
+                | Self::Image { id, .. }
+                | Self::Sticker { id, .. }
+                | Self::Audio { id, .. } => Uuid::from_bytes(*id),
             }
         }
+        pub fn kind(&self) -> RessourceKind {
+            match self {
+                Self::Animation { .. } => RessourceKind::Animation,
+                Self::TextBlock { .. } => RessourceKind::TextBlock,
+                Self::Image { .. } => RessourceKind::Image,
+                Self::Sticker { .. } => RessourceKind::Sticker,
+                Self::Audio { .. } => RessourceKind::Audio,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod display_context_types_test {
+    use chrono::{DateTime, TimeDelta};
+    use uuid::Uuid;
+
+    use crate::{DisplayContext, DisplayContextKind, ViolationKind};
+
+    #[test]
+    fn builder_requires_created_at_and_reports_invalid_fields() {
+        let [id, user_id] = [Uuid::now_v7(), Uuid::now_v7()];
+        let created_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let context = DisplayContext::builder(id, DisplayContextKind::Solo)
+            .participants(vec![user_id])
+            .created_at(created_at)
+            .build()
+            .unwrap();
+        assert!(context.updated_at() == Some(created_at) && context.participants() == [user_id]);
+
+        let violations = DisplayContext::builder(id, DisplayContextKind::Solo)
+            .participants(vec![user_id])
+            .build()
+            .unwrap_err();
+        let violation = violations.iter().next().unwrap();
+        assert!(violations.len() == 1);
+        assert!(
+            violation.field_path() == "created_at" && violation.kind() == &ViolationKind::Missing
+        );
+
+        let violations = DisplayContext::builder(Uuid::nil(), DisplayContextKind::Solo)
+            .participants(vec![user_id, Uuid::nil()])
+            .created_at(created_at)
+            .updated_at(created_at - TimeDelta::seconds(1))
+            .build()
+            .unwrap_err();
+        let paths: Vec<_> = violations.iter().map(|it| it.field_path()).collect();
+        assert!(paths == ["id", "participants[1]", "participants", "updated_at"]);
     }
 }
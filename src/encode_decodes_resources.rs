@@ -59,6 +59,13 @@ impl DecodeConfig {
 
         (Header(header), Payload(data))
     }
+    /// Same as [`Self::split_header_and_data_into_bytes_buffer`] for a buffer that is already
+    /// shared, e.g. the result of `shared_raw_bytes` on a borrowed fetch. Nothing is copied.
+    pub fn split_header_and_data_from_bytes(mut bytes: Bytes) -> (Header, Payload) {
+        let data = bytes.split_off(HEADER_LEN.min(bytes.len()));
+
+        (Header(bytes), Payload(data))
+    }
 }
 
 pub struct Payload(pub Bytes);
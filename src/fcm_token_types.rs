@@ -1,6 +1,8 @@
 use bincode::{Decode, Encode};
 use uuid::Uuid;
 
+use crate::{DecodeLimitError, DecodeLimited, DecodeLimits, Validate, Violations};
+
 #[derive(Encode, Decode, Debug)]
 pub struct FcmToken {
     user_id: [u8; 16],
@@ -48,3 +50,30 @@ impl FcmTokToDevicePair {
         self.fcm_token.as_str()
     }
 }
+
+impl DecodeLimited for FcmToken {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+            .with_max_total_bytes(8 * crate::KIB)
+            .with_max_string_len(4096)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_string("fcm_token", &self.fcm_token)
+    }
+}
+impl DecodeLimited for FcmTokToDevicePair {
+    fn default_decode_limits() -> DecodeLimits {
+        FcmToken::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_string("fcm_token", &self.fcm_token)
+    }
+}
+
+impl Validate for FcmToken {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "user_id", &self.user_id);
+        violations.check_not_nil(path, "device_id", &self.device_id);
+        violations.check_not_empty(path, "fcm_token", self.fcm_token.len());
+    }
+}
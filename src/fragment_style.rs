@@ -0,0 +1,296 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DecodeLimitError, DecodeLimited, DecodeLimits, MAX_FRAME_DIMENSION, Validate, Violations,
+    field_path,
+};
+
+/// Most points a [`ClipShape::Polygon`] may have.
+pub const MAX_CLIP_POLYGON_POINTS: usize = 64;
+/// Largest bincode encoding of a [`FragmentStyle`]: its fixed fields, a crop and a clip polygon
+/// of [`MAX_CLIP_POLYGON_POINTS`] points, `u16` varints taking up to 3 bytes.
+pub(crate) const MAX_FRAGMENT_STYLE_BYTES: usize =
+    1 + 1 + 4 + (1 + 4 * 5) + (1 + 1 + 3 + MAX_CLIP_POLYGON_POINTS * 2 * 3);
+
+/// Fractions of the fragment box are quantized on this many steps.
+const UNIT_STEPS: f32 = u16::MAX as f32;
+
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Hash,
+)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    Add,
+}
+
+/// Part of the source frame drawn, in source pixels.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Hash)]
+pub struct CropRect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+impl CropRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Shape the fragment is clipped to. Lengths and points are fractions of the fragment box,
+/// `[0, 0]` being its top left corner and `[1, 1]` its bottom right one, quantized on `u16`.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Hash)]
+pub enum ClipShape {
+    /// Corner radius as a fraction of the shortest side.
+    RoundedRect {
+        corner_radius: u16,
+    },
+    /// Ellipse inscribed in the fragment box.
+    Ellipse,
+    Polygon {
+        points: Vec<[u16; 2]>,
+    },
+}
+impl ClipShape {
+    pub fn rounded_rect(corner_radius: f32) -> Self {
+        Self::RoundedRect {
+            corner_radius: quantize_unit(corner_radius),
+        }
+    }
+    pub fn polygon(points: &[[f32; 2]]) -> Self {
+        Self::Polygon {
+            points: points.iter().map(|it| it.map(quantize_unit)).collect(),
+        }
+    }
+    pub fn polygon_points(&self) -> Option<Vec<[f32; 2]>> {
+        match self {
+            Self::Polygon { points } => Some(points.iter().map(|it| it.map(unit)).collect()),
+            _ => None,
+        }
+    }
+    /// Whether `point`, a fraction of a `width` x `height` box, is inside the shape.
+    pub fn contains(&self, point: [f32; 2], width: f32, height: f32) -> bool {
+        if !(0.0..=1.0).contains(&point[0]) || !(0.0..=1.0).contains(&point[1]) {
+            return false;
+        }
+        match self {
+            Self::RoundedRect { corner_radius } => {
+                let radius = unit(*corner_radius) * width.min(height);
+                let [x, y] = [point[0] * width, point[1] * height];
+                let dx = (radius - x).max(x - (width - radius)).max(0.0);
+                let dy = (radius - y).max(y - (height - radius)).max(0.0);
+                dx * dx + dy * dy <= radius * radius
+            }
+            Self::Ellipse => {
+                let [x, y] = [point[0] * 2.0 - 1.0, point[1] * 2.0 - 1.0];
+                x * x + y * y <= 1.0
+            }
+            Self::Polygon { points } => {
+                // Even-odd rule.
+                let mut inside = false;
+                for (index, current) in points.iter().enumerate() {
+                    let [x1, y1] = current.map(unit);
+                    let [x2, y2] = points[(index + 1) % points.len()].map(unit);
+                    if (y1 > point[1]) != (y2 > point[1])
+                        && point[0] < (x2 - x1) * (point[1] - y1) / (y2 - y1) + x1
+                    {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+}
+
+fn quantize_unit(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * UNIT_STEPS).round() as u16
+}
+fn unit(value: u16) -> f32 {
+    value as f32 / UNIT_STEPS
+}
+
+/// [`FragmentStyle`] is how a placed fragment is drawn, besides its geometry. The default
+/// style draws the source untouched, and it is left out of JSON payloads.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Hash)]
+pub struct FragmentStyle {
+    /// 0 is transparent, 255 opaque.
+    opacity: u8,
+    blend_mode: BlendMode,
+    /// rgba multiplied with the source, white leaves it untouched.
+    tint: [u8; 4],
+    crop: Option<CropRect>,
+    clip: Option<ClipShape>,
+}
+impl Default for FragmentStyle {
+    fn default() -> Self {
+        Self {
+            opacity: u8::MAX,
+            blend_mode: BlendMode::Normal,
+            tint: [u8::MAX; 4],
+            crop: None,
+            clip: None,
+        }
+    }
+}
+impl FragmentStyle {
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = (opacity.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8;
+        self
+    }
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+    pub fn with_tint(mut self, tint: [u8; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+    pub fn with_crop(mut self, crop: CropRect) -> Self {
+        self.crop = Some(crop);
+        self
+    }
+    pub fn with_clip(mut self, clip: ClipShape) -> Self {
+        self.clip = Some(clip);
+        self
+    }
+    /// 0.0 is transparent, 1.0 opaque.
+    pub fn opacity(&self) -> f32 {
+        self.opacity as f32 / u8::MAX as f32
+    }
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+    pub fn tint(&self) -> [u8; 4] {
+        self.tint
+    }
+    pub fn crop(&self) -> Option<&CropRect> {
+        self.crop.as_ref()
+    }
+    pub fn clip(&self) -> Option<&ClipShape> {
+        self.clip.as_ref()
+    }
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl DecodeLimited for FragmentStyle {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_collection_len(MAX_CLIP_POLYGON_POINTS)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        match &self.clip {
+            Some(ClipShape::Polygon { points }) => {
+                limits.check_collection("style.clip.points", points.len())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Validate for FragmentStyle {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        if let Some(crop) = &self.crop {
+            let crop_path = field_path(path, "crop");
+            let max = MAX_FRAME_DIMENSION as f64;
+            violations.check_range(&crop_path, "width", crop.width as f64, 1.0, max);
+            violations.check_range(&crop_path, "height", crop.height as f64, 1.0, max);
+            violations.check_range(&crop_path, "x", crop.x as f64, 0.0, max);
+            violations.check_range(&crop_path, "y", crop.y as f64, 0.0, max);
+        }
+        if let Some(ClipShape::Polygon { points }) = &self.clip {
+            violations.check_range(
+                &field_path(path, "clip"),
+                "points.len",
+                points.len() as f64,
+                3.0,
+                MAX_CLIP_POLYGON_POINTS as f64,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod fragment_style_test {
+    use uuid::Uuid;
+
+    use crate::{
+        BlendMode, ClipShape, CompositionData, CompositionDiff, CompositionResourceData,
+        FragmentStyle, Validate,
+    };
+
+    #[test]
+    fn clip_shapes_and_default_style() {
+        let style = FragmentStyle::default();
+        assert!(style.is_default() && style.opacity() == 1.0);
+        let json = serde_json::to_string(&style).unwrap();
+        let decoded: FragmentStyle = serde_json::from_str(&json).unwrap();
+        assert!(decoded == style);
+        assert!(FragmentStyle::default().with_opacity(0.5).opacity() > 0.49);
+
+        let rounded = ClipShape::rounded_rect(0.25);
+        // 200 x 100 box, 25 units radius: the very corner is out, the edge middle is in.
+        assert!(!rounded.contains([0.0, 0.0], 200.0, 100.0));
+        assert!(rounded.contains([0.5, 0.0], 200.0, 100.0));
+        assert!(ClipShape::Ellipse.contains([0.5, 0.5], 1.0, 1.0));
+        assert!(!ClipShape::Ellipse.contains([0.05, 0.05], 1.0, 1.0));
+        let triangle = ClipShape::polygon(&[[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+        assert!(triangle.contains([0.2, 0.2], 1.0, 1.0));
+        assert!(!triangle.contains([0.8, 0.8], 1.0, 1.0));
+    }
+
+    #[test]
+    fn styles_travel_in_diffs_and_clip_hit_tests() {
+        let [resource_id, author_id] = [Uuid::now_v7(), Uuid::now_v7()];
+        let resource = CompositionResourceData::new(resource_id, [0.0, 0.0], 10.0, 10.0);
+        let old = CompositionData::builder(Uuid::now_v7(), author_id)
+            .add_resource(resource.clone())
+            .build()
+            .unwrap();
+        let style = FragmentStyle::default()
+            .with_opacity(0.5)
+            .with_blend_mode(BlendMode::Multiply)
+            .with_clip(ClipShape::Ellipse);
+        let new = CompositionData::builder(old.id(), author_id)
+            .add_resource(resource.with_style(style.clone()))
+            .version(1)
+            .build()
+            .unwrap();
+        assert!(old.content_hash() != new.content_hash());
+
+        let diff = CompositionDiff::between(&old, &new).unwrap();
+        assert!(diff.validate().is_ok());
+        let mut patched = old.clone();
+        diff.apply(&mut patched).unwrap();
+        assert!(patched.resource(resource_id).unwrap().style() == &style);
+        // The corner of the box is outside the ellipse.
+        assert!(old.hit_test([4.5, 4.5]).is_some());
+        assert!(patched.hit_test([4.5, 4.5]).is_none());
+        assert!(patched.hit_test([0.0, 0.0]).is_some());
+    }
+}
@@ -2,6 +2,10 @@ use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::{
+    DecodeLimitError, DecodeLimited, DecodeLimits, MAX_USERNAME_LEN, Validate, Violations,
+};
+
 #[derive(Serialize, Debug, Deserialize, Encode, Decode, Clone, PartialEq, Eq, Hash)]
 pub struct FriendRegisterDelta {
     pulled_version: u64,
@@ -51,3 +55,38 @@ impl FriendContact {
         Uuid::from_bytes(self.user_id)
     }
 }
+
+impl DecodeLimited for FriendRegisterDelta {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+            .with_max_collection_len(10_000)
+            .with_max_string_len(MAX_USERNAME_LEN)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("delta_collection", self.delta_collection.len())?;
+        for contact in &self.delta_collection {
+            contact.check_decode_limits(limits)?;
+        }
+        Ok(())
+    }
+}
+impl DecodeLimited for FriendContact {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_string_len(MAX_USERNAME_LEN)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_string("username", &self.username)
+    }
+}
+
+impl Validate for FriendRegisterDelta {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_items(path, "delta_collection", &self.delta_collection);
+    }
+}
+impl Validate for FriendContact {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_empty(path, "username", self.username.len());
+        violations.check_not_nil(path, "user_id", &self.user_id);
+    }
+}
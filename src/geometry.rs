@@ -0,0 +1,221 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// [`Affine2`] is a 2D affine transform, stored column-major as `[a, b, c, d, tx, ty]`:
+/// `x' = a * x + c * y + tx` and `y' = b * x + d * y + ty`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct Affine2 {
+    m: [f32; 6],
+}
+impl Affine2 {
+    pub const IDENTITY: Self = Self {
+        m: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+    };
+
+    pub fn from_cols_array(m: [f32; 6]) -> Self {
+        Self { m }
+    }
+    pub fn from_translation(translation: [f32; 2]) -> Self {
+        Self {
+            m: [1.0, 0.0, 0.0, 1.0, translation[0], translation[1]],
+        }
+    }
+    /// Counterclockwise rotation of `angle` radians around the origin.
+    pub fn from_rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            m: [cos, sin, -sin, cos, 0.0, 0.0],
+        }
+    }
+    pub fn from_scale(scale: [f32; 2]) -> Self {
+        Self {
+            m: [scale[0], 0.0, 0.0, scale[1], 0.0, 0.0],
+        }
+    }
+    /// Scale, then rotation, then translation.
+    pub fn from_scale_rotation_translation(
+        scale: [f32; 2],
+        angle: f32,
+        translation: [f32; 2],
+    ) -> Self {
+        Self::from_translation(translation)
+            .mul(&Self::from_rotation(angle))
+            .mul(&Self::from_scale(scale))
+    }
+    pub fn to_cols_array(&self) -> [f32; 6] {
+        self.m
+    }
+    /// `self * rhs`: the returned transform applies `rhs` first, then `self`.
+    pub fn mul(&self, rhs: &Affine2) -> Affine2 {
+        let [a, b, c, d, tx, ty] = self.m;
+        let [ra, rb, rc, rd, rtx, rty] = rhs.m;
+        Affine2 {
+            m: [
+                a * ra + c * rb,
+                b * ra + d * rb,
+                a * rc + c * rd,
+                b * rc + d * rd,
+                a * rtx + c * rty + tx,
+                b * rtx + d * rty + ty,
+            ],
+        }
+    }
+    pub fn determinant(&self) -> f32 {
+        self.m[0] * self.m[3] - self.m[2] * self.m[1]
+    }
+    /// `None` when the transform collapses the plane (zero scale on an axis).
+    pub fn inverse(&self) -> Option<Affine2> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let [a, b, c, d, tx, ty] = self.m;
+        let inv_det = 1.0 / det;
+        let [ia, ib, ic, id] = [d * inv_det, -b * inv_det, -c * inv_det, a * inv_det];
+        Some(Affine2 {
+            m: [ia, ib, ic, id, -(ia * tx + ic * ty), -(ib * tx + id * ty)],
+        })
+    }
+    pub fn transform_point(&self, point: [f32; 2]) -> [f32; 2] {
+        let [a, b, c, d, tx, ty] = self.m;
+        [
+            a * point[0] + c * point[1] + tx,
+            b * point[0] + d * point[1] + ty,
+        ]
+    }
+    /// Applies the linear part only, for directions and sizes.
+    pub fn transform_vector(&self, vector: [f32; 2]) -> [f32; 2] {
+        let [a, b, c, d, _, _] = self.m;
+        [a * vector[0] + c * vector[1], b * vector[0] + d * vector[1]]
+    }
+}
+impl Default for Affine2 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// [`Aabb`] is an axis-aligned bounding box, `min` and `max` included.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct Aabb {
+    min: [f32; 2],
+    max: [f32; 2],
+}
+impl Aabb {
+    /// Corners are reordered, so any two opposite corners can be given.
+    pub fn new(a: [f32; 2], b: [f32; 2]) -> Self {
+        Self {
+            min: [a[0].min(b[0]), a[1].min(b[1])],
+            max: [a[0].max(b[0]), a[1].max(b[1])],
+        }
+    }
+    /// `None` for an empty iterator.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 2]>) -> Option<Self> {
+        points.into_iter().fold(None, |aabb: Option<Aabb>, point| {
+            Some(match aabb {
+                Some(aabb) => aabb.union(&Aabb::new(point, point)),
+                None => Aabb::new(point, point),
+            })
+        })
+    }
+    pub fn min(&self) -> [f32; 2] {
+        self.min
+    }
+    pub fn max(&self) -> [f32; 2] {
+        self.max
+    }
+    pub fn width(&self) -> f32 {
+        self.max[0] - self.min[0]
+    }
+    pub fn height(&self) -> f32 {
+        self.max[1] - self.min[1]
+    }
+    pub fn center(&self) -> [f32; 2] {
+        [
+            (self.min[0] + self.max[0]) / 2.0,
+            (self.min[1] + self.max[1]) / 2.0,
+        ]
+    }
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: [self.min[0].min(other.min[0]), self.min[1].min(other.min[1])],
+            max: [self.max[0].max(other.max[0]), self.max[1].max(other.max[1])],
+        }
+    }
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        (self.min[0]..=self.max[0]).contains(&point[0])
+            && (self.min[1]..=self.max[1]).contains(&point[1])
+    }
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min[0] <= other.max[0]
+            && other.min[0] <= self.max[0]
+            && self.min[1] <= other.max[1]
+            && other.min[1] <= self.max[1]
+    }
+}
+
+#[cfg(test)]
+mod geometry_test {
+    use std::f32::consts::FRAC_PI_2;
+
+    use uuid::Uuid;
+
+    use crate::{Affine2, CompositionData, CompositionResourceData, FragmentTransform2DData};
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4
+    }
+
+    #[test]
+    fn rotated_fragment_layout() {
+        let affine = Affine2::from_scale_rotation_translation([2.0, 1.0], FRAC_PI_2, [10.0, 5.0]);
+        let point = affine.transform_point([1.0, 0.0]);
+        assert!(close(point, [10.0, 7.0]));
+        assert!(close(
+            affine.inverse().unwrap().transform_point(point),
+            [1.0, 0.0]
+        ));
+        assert!(Affine2::from_scale([0.0, 1.0]).inverse().is_none());
+
+        // 40 x 10 fragment turned a quarter: it stands 10 wide and 40 high.
+        let transform = FragmentTransform2DData::new(
+            [100.0, 0.0, 0.0],
+            [40.0, 10.0],
+            [1.0, 1.0],
+            FRAC_PI_2,
+            0.0,
+        );
+        let aabb = transform.aabb();
+        assert!(close(aabb.min(), [95.0, -20.0]));
+        assert!(close(aabb.max(), [105.0, 20.0]));
+        assert!(transform.contains_point([100.0, 19.0]));
+        assert!(!transform.contains_point([115.0, 0.0]));
+
+        let under = Uuid::now_v7();
+        let above = Uuid::now_v7();
+        let mut composition = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7())
+            .add_resource(CompositionResourceData::new(
+                under,
+                [100.0, 0.0],
+                20.0,
+                20.0,
+            ))
+            .add_resource(CompositionResourceData::new_with_transform(
+                above, transform,
+            ))
+            .min_x(0.0)
+            .max_x(1.0)
+            .min_y(0.0)
+            .max_y(1.0)
+            .build()
+            .unwrap();
+        assert!(composition.hit_test([100.0, 5.0]).map(|it| it.id()) == Some(above));
+        assert!(composition.hit_test([91.0, 0.0]).map(|it| it.id()) == Some(under));
+        assert!(composition.hit_test([0.0, 0.0]).is_none());
+
+        composition.recompute_bounds();
+        assert!(composition.min_x() == 90.0 && composition.max_x() == 110.0);
+        assert!((composition.min_y() + 20.0).abs() < 1e-4);
+        assert!((composition.height() - 40.0).abs() < 1e-4);
+    }
+}
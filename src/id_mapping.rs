@@ -0,0 +1,167 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{DecodeLimitError, DecodeLimited, DecodeLimits};
+
+/// Kind of the object an id designates. The same local id can be reused across kinds.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Hash)]
+pub enum IdKind {
+    Animation,
+    Composition,
+    Context,
+    /// Any ressource other than an animation, see [`crate::RessourceKind`].
+    Ressource,
+    Group,
+    Layer,
+    /// Author of objects created before the signin of its user completed.
+    User,
+}
+
+/// [`IdMapping`] lists, per object kind, the local ids a client created offline and the ids the
+/// server assigned to them. Apply it with [`RemapIds`] once the mutation is acknowledged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub struct IdMapping {
+    pairs: Vec<(IdKind, [u8; 16], [u8; 16])>,
+}
+impl IdMapping {
+    pub fn new_empty() -> Self {
+        Self { pairs: vec![] }
+    }
+    /// Pairs mapping an id to itself are skipped, a new pair for an already mapped local id
+    /// replaces the previous one.
+    pub fn insert(&mut self, kind: IdKind, local_id: Uuid, server_id: Uuid) {
+        if local_id == server_id {
+            return;
+        }
+        let local_id = local_id.into_bytes();
+        match self
+            .pairs
+            .iter_mut()
+            .find(|it| it.0 == kind && it.1 == local_id)
+        {
+            Some(pair) => pair.2 = server_id.into_bytes(),
+            None => self.pairs.push((kind, local_id, server_id.into_bytes())),
+        }
+    }
+    pub fn extend(&mut self, other: &IdMapping) {
+        for (kind, local_id, server_id) in other.iter() {
+            self.insert(kind, local_id, server_id);
+        }
+    }
+    pub fn get(&self, kind: IdKind, local_id: Uuid) -> Option<Uuid> {
+        let local_id = local_id.into_bytes();
+        self.pairs
+            .iter()
+            .find(|it| it.0 == kind && it.1 == local_id)
+            .map(|it| Uuid::from_bytes(it.2))
+    }
+    /// Server id of `id`, or `id` itself when it is not mapped.
+    pub fn resolve(&self, kind: IdKind, id: Uuid) -> Uuid {
+        self.get(kind, id).unwrap_or(id)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (IdKind, Uuid, Uuid)> + '_ {
+        self.pairs
+            .iter()
+            .map(|it| (it.0, Uuid::from_bytes(it.1), Uuid::from_bytes(it.2)))
+    }
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+    /// Rewrites `id` in place, returns whether it changed.
+    pub fn remap_bytes(&self, kind: IdKind, id: &mut [u8; 16]) -> bool {
+        match self.pairs.iter().find(|it| it.0 == kind && it.1 == *id) {
+            Some(pair) => {
+                *id = pair.2;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Implemented by the types holding references to objects a client can create offline.
+pub trait RemapIds {
+    /// Rewrites every mapped reference of `self`, nested values included. Returns the number
+    /// of rewritten references.
+    fn remap_ids(&mut self, mapping: &IdMapping) -> usize;
+}
+
+impl<T: RemapIds> RemapIds for [T] {
+    fn remap_ids(&mut self, mapping: &IdMapping) -> usize {
+        self.iter_mut().map(|it| it.remap_ids(mapping)).sum()
+    }
+}
+
+impl DecodeLimited for IdMapping {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("pairs", self.pairs.len())
+    }
+}
+
+#[cfg(test)]
+mod id_mapping_test {
+    use uuid::Uuid;
+
+    use crate::{
+        AnimVariableContext, CompositionData, CompositionGroup, CompositionLayer,
+        CompositionResourceData, FragmentTransform2DData, IdKind, IdMapping, ParentRef, RemapIds,
+    };
+
+    #[test]
+    fn offline_references_are_rewritten() {
+        let [local_anim, local_comp, context] = [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+        let [server_anim, server_comp] = [Uuid::now_v7(), Uuid::now_v7()];
+        let [local_group, local_layer, local_author] =
+            [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+        let [server_group, server_layer, server_author] =
+            [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+        let mut mapping = IdMapping::new_empty();
+        mapping.insert(IdKind::Animation, local_anim, server_anim);
+        mapping.insert(IdKind::Composition, local_comp, server_comp);
+        mapping.insert(IdKind::Group, local_group, server_group);
+        mapping.insert(IdKind::Layer, local_layer, server_layer);
+        mapping.insert(IdKind::User, local_author, server_author);
+        // A composition id must not be rewritten as an animation id.
+        mapping.insert(IdKind::Animation, local_comp, Uuid::now_v7());
+
+        let mut composition = CompositionData::builder(local_comp, local_author)
+            .add_layer(CompositionLayer::new(local_layer, "front".to_string()))
+            .add_group(
+                CompositionGroup::new(
+                    local_group,
+                    FragmentTransform2DData::from_pos_size([0.0, 0.0], 0.0, 0.0),
+                )
+                .with_parent(ParentRef::layer(local_layer)),
+            )
+            .add_resource(
+                CompositionResourceData::new(local_anim, [0.0, 0.0], 8.0, 8.0)
+                    .with_parent(ParentRef::group(local_group)),
+            )
+            .build()
+            .unwrap();
+        let mut placement =
+            AnimVariableContext::new(local_anim, local_comp, context, 1, 1, [0.0; 2]);
+
+        assert!(composition.remap_ids(&mapping) == 7);
+        assert!(placement.remap_ids(&mapping) == 2);
+        assert!(composition.id() == server_comp);
+        assert!(composition.author_id() == server_author);
+        let resource = &composition.resources_collection()[0];
+        assert!(resource.id() == server_anim);
+        assert!(resource.parent() == Some(ParentRef::group(server_group)));
+        let group = &composition.groups()[0];
+        assert!(group.group_id() == server_group);
+        assert!(group.parent() == Some(ParentRef::layer(server_layer)));
+        assert!(composition.layers()[0].layer_id() == server_layer);
+        assert!(placement.anim_id() == server_anim);
+        assert!(placement.composition_id() == server_comp);
+        assert!(placement.context_id() == context);
+    }
+}
@@ -3,7 +3,10 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::StreamMessage;
+use crate::{
+    DecodeLimitError, DecodeLimited, DecodeLimits, MAX_USERNAME_LEN, StreamMessage, Validate,
+    Violations,
+};
 
 #[derive(Decode, Encode, Debug)]
 pub struct InvitationResponse {
@@ -114,6 +117,42 @@ impl From<InvitationMessage> for StreamMessage {
     }
 }
 
+impl DecodeLimited for InvitationResponse {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for PeerInvitationByTextHandle {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+            .with_max_total_bytes(1024)
+            .with_max_string_len(MAX_USERNAME_LEN)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_string("peer_username_handle", &self.peer_username_handle)
+    }
+}
+
+impl Validate for InvitationResponse {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "invitation_id", &self.invitation_id);
+        violations.check_not_nil(path, "responder_user_id", &self.responder_user_id);
+    }
+}
+impl Validate for PeerInvitationByTextHandle {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "emitting_user_id", &self.emitting_user_id);
+        violations.check_not_empty(
+            path,
+            "peer_username_handle",
+            self.peer_username_handle.trim().len(),
+        );
+    }
+}
+
 #[cfg(test)]
 mod invitation_test {
     use bincode::{Decode, config::standard};
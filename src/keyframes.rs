@@ -0,0 +1,276 @@
+use std::time::Duration;
+
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AnimVariableContext, CompositionResourceData, DecodeLimitError, DecodeLimited, DecodeLimits,
+    FragmentTransform2DData, Validate, ViolationKind, Violations, field_path, indexed_path,
+};
+
+/// Most keyframes a [`TransformTrack`] may have.
+pub const MAX_KEYFRAMES: usize = 256;
+/// Largest bincode encoding of a [`Keyframe`]: a 5 bytes varint time, the 9 floats of the
+/// transform and a cubic bezier easing.
+pub(crate) const MAX_KEYFRAME_BYTES: usize = 5 + 9 * 4 + 1 + 4 * 4;
+
+/// Progress curve between two keyframes: maps the elapsed fraction of the segment to the
+/// fraction of the change applied, both in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Keeps the transform of the keyframe until the next one.
+    Step,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// CSS `cubic-bezier(x1, y1, x2, y2)`, `x1` and `x2` in `0.0..=1.0`.
+    CubicBezier {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    },
+}
+impl Easing {
+    pub fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Self::CubicBezier { x1, y1, x2, y2 }
+    }
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Self::Linear => t,
+            Self::Step => 0.0,
+            // Same curves as CSS `ease-in`, `ease-out` and `ease-in-out`.
+            Self::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+            Self::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+            Self::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+            Self::CubicBezier { x1, y1, x2, y2 } => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+/// One coordinate of the bezier curve from 0.0 to 1.0 with control points `p1` and `p2`.
+fn bezier_axis(p1: f32, p2: f32, s: f32) -> f32 {
+    let inverse = 1.0 - s;
+    3.0 * inverse * inverse * s * p1 + 3.0 * inverse * s * s * p2 + s * s * s
+}
+
+/// `y` of the curve point whose `x` is `t`. `x` grows with the curve parameter when `x1` and
+/// `x2` are in `0.0..=1.0`, so a bisection always finds it.
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, t: f32) -> f32 {
+    let [mut low, mut high] = [0.0f32, 1.0f32];
+    let mut s = t;
+    for _ in 0..32 {
+        let x = bezier_axis(x1, x2, s);
+        if (x - t).abs() < 1e-6 {
+            break;
+        }
+        if x < t {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    bezier_axis(y1, y2, s)
+}
+
+/// [`Keyframe`] pins the transform of a fragment at `time_ms`, composition time. `easing`
+/// shapes the way to the next keyframe.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct Keyframe {
+    time_ms: u32,
+    transform: FragmentTransform2DData,
+    easing: Easing,
+}
+impl Keyframe {
+    pub fn new(time: Duration, transform: FragmentTransform2DData, easing: Easing) -> Self {
+        Self {
+            time_ms: time.as_millis().min(u32::MAX as u128) as u32,
+            transform,
+            easing,
+        }
+    }
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time_ms as u64)
+    }
+    pub fn transform(&self) -> &FragmentTransform2DData {
+        &self.transform
+    }
+    pub fn easing(&self) -> Easing {
+        self.easing
+    }
+}
+
+/// [`TransformTrack`] moves a fragment over time. Keyframes are sorted by time, the transform
+/// holds the first one before it and the last one after it.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct TransformTrack {
+    keyframes: Vec<Keyframe>,
+}
+impl TransformTrack {
+    /// Sorts `keyframes` by time, `None` when there is none.
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Option<Self> {
+        if keyframes.is_empty() {
+            return None;
+        }
+        keyframes.sort_by_key(|it| it.time_ms);
+        Some(Self { keyframes })
+    }
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+    pub fn duration(&self) -> Duration {
+        self.keyframes.last().map_or(Duration::ZERO, |it| it.time())
+    }
+    /// `None` for a track without keyframes, which only a decoded track can be.
+    pub fn transform_at(&self, time: Duration) -> Option<FragmentTransform2DData> {
+        let time_ms = time.as_secs_f64() * 1000.0;
+        let next = self
+            .keyframes
+            .partition_point(|it| (it.time_ms as f64) <= time_ms);
+        match next {
+            0 => self.keyframes.first().map(|it| it.transform.clone()),
+            next if next == self.keyframes.len() => {
+                self.keyframes.last().map(|it| it.transform.clone())
+            }
+            next => {
+                let [from, to] = [&self.keyframes[next - 1], &self.keyframes[next]];
+                let span = (to.time_ms - from.time_ms) as f64;
+                let t = ((time_ms - from.time_ms as f64) / span) as f32;
+                Some(from.transform.lerp(&to.transform, from.easing.apply(t)))
+            }
+        }
+    }
+}
+
+impl DecodeLimited for TransformTrack {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_collection_len(MAX_KEYFRAMES)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("track.keyframes", self.keyframes.len())
+    }
+}
+
+impl FragmentTransform2DData {
+    /// Transform `t` of the way from `self` to `other`, component by component. Rotations are
+    /// interpolated as given so that a track can make full turns.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        let [pos, other_pos] = [self.pos(), other.pos()];
+        let [dimensions, other_dimensions] = [self.dimensions(), other.dimensions()];
+        let [scale, other_scale] = [self.scale(), other.scale()];
+        Self::new(
+            [0, 1, 2].map(|it| mix(pos[it], other_pos[it])),
+            [0, 1].map(|it| mix(dimensions[it], other_dimensions[it])),
+            [0, 1].map(|it| mix(scale[it], other_scale[it])),
+            mix(self.rot(), other.rot()),
+            mix(self.z(), other.z()),
+        )
+    }
+}
+
+impl CompositionResourceData {
+    /// Transform at `time`, composition time: the one of the track when there is one.
+    pub fn transform_at(&self, time: Duration) -> FragmentTransform2DData {
+        self.track()
+            .and_then(|track| track.transform_at(time))
+            .unwrap_or_else(|| self.transform().clone())
+    }
+}
+impl AnimVariableContext {
+    /// Transform at `time`, composition time: the one of the track when there is one.
+    pub fn transform_at(&self, time: Duration) -> FragmentTransform2DData {
+        self.track()
+            .and_then(|track| track.transform_at(time))
+            .unwrap_or_else(|| self.world_transform())
+    }
+}
+
+impl Validate for Easing {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        if let Self::CubicBezier { x1, y1, x2, y2 } = *self {
+            for (field, value) in [("x1", x1), ("y1", y1), ("x2", x2), ("y2", y2)] {
+                violations.check_finite(path, field, value);
+            }
+            violations.check_range(path, "x1", x1 as f64, 0.0, 1.0);
+            violations.check_range(path, "x2", x2 as f64, 0.0, 1.0);
+        }
+    }
+}
+impl Validate for Keyframe {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        self.transform
+            .validate_at(&field_path(path, "transform"), violations);
+        self.easing
+            .validate_at(&field_path(path, "easing"), violations);
+    }
+}
+impl Validate for TransformTrack {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_range(
+            path,
+            "keyframes.len",
+            self.keyframes.len() as f64,
+            1.0,
+            MAX_KEYFRAMES as f64,
+        );
+        violations.check_items(path, "keyframes", &self.keyframes);
+        for (index, pair) in self.keyframes.windows(2).enumerate() {
+            if pair[0].time_ms >= pair[1].time_ms {
+                violations.push(
+                    indexed_path(path, "keyframes", index + 1),
+                    ViolationKind::Invalid("keyframes must have increasing times"),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod keyframes_test {
+    use std::time::Duration;
+
+    use crate::{Easing, FragmentTransform2DData, Keyframe, TransformTrack, Validate};
+
+    #[test]
+    fn track_interpolates_between_keyframes() {
+        let ms = Duration::from_millis;
+        let start = FragmentTransform2DData::from_pos_size([0.0, 0.0], 10.0, 10.0);
+        let end = FragmentTransform2DData::from_pos_size([100.0, 50.0], 20.0, 10.0);
+        let track = TransformTrack::new(vec![
+            Keyframe::new(ms(1000), end.clone(), Easing::Linear),
+            Keyframe::new(ms(0), start.clone(), Easing::Linear),
+        ])
+        .unwrap();
+        assert!(track.validate().is_ok());
+        let halfway = track.transform_at(ms(500)).unwrap();
+        assert!(halfway.pos() == [50.0, 25.0, 0.0] && halfway.dimensions() == [15.0, 10.0]);
+        assert!(track.transform_at(ms(5000)) == Some(end));
+
+        for easing in [Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert!(easing.apply(0.0).abs() < 1e-4 && (easing.apply(1.0) - 1.0).abs() < 1e-4);
+        }
+        assert!(Easing::EaseIn.apply(0.25) < 0.25 && Easing::EaseOut.apply(0.25) > 0.25);
+        assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-4);
+        // A linear bezier is the identity.
+        let linear = Easing::cubic_bezier(0.25, 0.25, 0.75, 0.75);
+        assert!((linear.apply(0.3) - 0.3).abs() < 1e-4);
+        assert!(Easing::Step.apply(0.9) == 0.0);
+        assert!(Easing::cubic_bezier(1.5, 0.0, 0.5, 1.0).validate().is_err());
+
+        let encoded = bincode::encode_to_vec(&track, bincode::config::standard()).unwrap();
+        let (decoded, _): (TransformTrack, _) =
+            bincode::decode_from_slice(&encoded, bincode::config::standard()).unwrap();
+        assert!(decoded == track);
+        // Only `new` and `validate` reject a track without keyframes, a decoded one can be empty.
+        let empty = TransformTrack::new(Vec::new());
+        assert!(empty.is_none());
+        let (empty, _): (TransformTrack, _) =
+            bincode::decode_from_slice(&[0], bincode::config::standard()).unwrap();
+        assert!(empty.transform_at(ms(0)).is_none() && empty.validate().is_err());
+    }
+}
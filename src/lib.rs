@@ -1,40 +1,78 @@
+pub mod animation_batch;
+pub mod animation_mutations;
 pub mod animation_to_sync;
+pub mod chunked_upload;
 mod client_trait_impl;
+pub mod composition_diff;
+pub mod composition_ops;
+pub mod composition_tree;
 pub mod compositions;
 pub mod context_sync;
 pub mod context_version;
 pub mod db_data_types;
+pub mod decode_limits;
 pub mod display_context_types;
 pub mod encode_decodes_resources;
 pub mod error_types;
 pub mod faces_network_errors;
 pub mod fcm_token_types;
+pub mod fragment_style;
 pub mod friendships_types;
+pub mod geometry;
+pub mod id_mapping;
 pub mod invitation;
+pub mod keyframes;
+pub mod media_ressources;
 pub mod notifications_types;
+pub mod playback;
+pub mod quantization;
+pub mod ranged_fetch;
 pub mod refresh_cred;
+pub mod renditions;
 pub mod ressources_descriptors;
 pub mod signin;
 pub mod signup;
 pub mod stream_types;
+pub mod validation;
+pub mod viewport;
+pub mod z_order;
 
 pub use error_types::*;
 pub use signin::SigninData;
 pub use signup::SignupData;
 
+pub use animation_batch::*;
+pub use animation_mutations::*;
 pub use animation_to_sync::*;
+pub use chunked_upload::*;
 pub use context_version::ServerContextVersion;
 
 pub use context_sync::*;
 pub use db_data_types::{ToUserContextKind, UserContextKind, UserDisplayContext, UserPeersInfos};
+pub use decode_limits::*;
 pub use faces_quic_server::prelude::StreamMessageCapsule;
 
+pub use composition_diff::*;
+pub use composition_ops::*;
+pub use composition_tree::*;
 pub use compositions::*;
 pub use display_context_types::*;
 pub use fcm_token_types::*;
+pub use fragment_style::*;
 pub use friendships_types::*;
+pub use geometry::*;
+pub use id_mapping::*;
 pub use invitation::*;
+pub use keyframes::*;
+pub use media_ressources::*;
 pub use notifications_types::*;
+pub use playback::*;
+pub use quantization::*;
+pub use ranged_fetch::*;
 pub use refresh_cred::*;
+pub use renditions::*;
 pub use ressources_descriptors::*;
 pub use stream_types::*;
+pub use validation::*;
+pub use viewport::*;
+pub use z_order::*;
@@ -0,0 +1,596 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    ContentHash, DecodeLimitError, DecodeLimited, DecodeLimits, MAX_FRAME_DIMENSION, MIB,
+    RessourcesDescriptorsKind, Validate, ViolationKind, Violations, content_hash, field_path,
+};
+
+/// Longest text a [`TextBlockRessource`] may hold, in bytes.
+pub const MAX_TEXT_BLOCK_LEN: usize = 4096;
+pub const MAX_IMAGE_BYTES: usize = 16 * MIB;
+pub const MAX_STICKER_BYTES: usize = 2 * MIB;
+pub const MAX_AUDIO_BYTES: usize = 32 * MIB;
+
+/// Kind of media a ressource holds.
+#[derive(
+    Encode, Deserialize, Serialize, Decode, Debug, Clone, Copy, Default, PartialEq, Eq, Hash,
+)]
+pub enum RessourceKind {
+    #[default]
+    Animation,
+    TextBlock,
+    Image,
+    Sticker,
+    Audio,
+}
+impl RessourceKind {
+    /// Largest payload accepted for this kind. Animations go through [`crate::SyncNewAnim`] and
+    /// text blocks carry no payload.
+    pub fn max_payload_bytes(&self) -> usize {
+        match self {
+            Self::Animation | Self::TextBlock => 0,
+            Self::Image => MAX_IMAGE_BYTES,
+            Self::Sticker => MAX_STICKER_BYTES,
+            Self::Audio => MAX_AUDIO_BYTES,
+        }
+    }
+}
+
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextStyle {
+    font_family: String,
+    font_size: u16,
+    /// rgba
+    color: [u8; 4],
+}
+impl TextStyle {
+    pub fn new(font_family: String, font_size: u16, color: [u8; 4]) -> Self {
+        Self {
+            font_family,
+            font_size,
+            color,
+        }
+    }
+    pub fn font_family(&self) -> &str {
+        &self.font_family
+    }
+    pub fn font_size(&self) -> u16 {
+        self.font_size
+    }
+    pub fn color(&self) -> [u8; 4] {
+        self.color
+    }
+}
+
+/// [`TextBlockRessource`] is a block of text laid out in a `width` x `height` box. The text
+/// travels in the descriptor itself, there is nothing to fetch.
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextBlockRessource {
+    ressource_id: [u8; 16],
+    author_id: [u8; 16],
+    content: String,
+    style: TextStyle,
+    width: u32,
+    height: u32,
+}
+impl TextBlockRessource {
+    pub fn new(
+        ressource_id: Uuid,
+        author_id: Uuid,
+        content: String,
+        style: TextStyle,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self {
+            ressource_id: ressource_id.into_bytes(),
+            author_id: author_id.into_bytes(),
+            content,
+            style,
+            width,
+            height,
+        }
+    }
+    pub fn get_id(&self) -> Uuid {
+        Uuid::from_bytes(self.ressource_id)
+    }
+    pub fn get_author_id(&self) -> Uuid {
+        Uuid::from_bytes(self.author_id)
+    }
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+    pub fn style(&self) -> &TextStyle {
+        &self.style
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// [`ImageRessource`] is a still image.
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageRessource {
+    ressource_id: [u8; 16],
+    author_id: [u8; 16],
+    width: u32,
+    height: u32,
+    byte_len: u64,
+    content_hash: ContentHash,
+    mime_type: String,
+}
+impl ImageRessource {
+    pub fn new(
+        ressource_id: Uuid,
+        author_id: Uuid,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        mime_type: String,
+    ) -> Self {
+        Self {
+            ressource_id: ressource_id.into_bytes(),
+            author_id: author_id.into_bytes(),
+            width,
+            height,
+            byte_len: data.len() as u64,
+            content_hash: content_hash(data),
+            mime_type,
+        }
+    }
+    pub fn get_id(&self) -> Uuid {
+        Uuid::from_bytes(self.ressource_id)
+    }
+    pub fn get_author_id(&self) -> Uuid {
+        Uuid::from_bytes(self.author_id)
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn byte_len(&self) -> u64 {
+        self.byte_len
+    }
+    pub fn content_hash(&self) -> &ContentHash {
+        &self.content_hash
+    }
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+}
+
+/// [`StickerRessource`] is a small image, possibly animated, optionally part of a pack.
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StickerRessource {
+    ressource_id: [u8; 16],
+    author_id: [u8; 16],
+    pack_id: Option<[u8; 16]>,
+    width: u32,
+    height: u32,
+    byte_len: u64,
+    content_hash: ContentHash,
+    animated: bool,
+}
+impl StickerRessource {
+    pub fn new(
+        ressource_id: Uuid,
+        author_id: Uuid,
+        pack_id: Option<Uuid>,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        animated: bool,
+    ) -> Self {
+        Self {
+            ressource_id: ressource_id.into_bytes(),
+            author_id: author_id.into_bytes(),
+            pack_id: pack_id.map(|it| it.into_bytes()),
+            width,
+            height,
+            byte_len: data.len() as u64,
+            content_hash: content_hash(data),
+            animated,
+        }
+    }
+    pub fn get_id(&self) -> Uuid {
+        Uuid::from_bytes(self.ressource_id)
+    }
+    pub fn get_author_id(&self) -> Uuid {
+        Uuid::from_bytes(self.author_id)
+    }
+    pub fn pack_id(&self) -> Option<Uuid> {
+        self.pack_id.map(Uuid::from_bytes)
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn byte_len(&self) -> u64 {
+        self.byte_len
+    }
+    pub fn content_hash(&self) -> &ContentHash {
+        &self.content_hash
+    }
+    pub fn animated(&self) -> bool {
+        self.animated
+    }
+}
+
+/// [`AudioRessource`] is an audio clip. It has no size on screen.
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AudioRessource {
+    ressource_id: [u8; 16],
+    author_id: [u8; 16],
+    duration_ms: u32,
+    sample_rate: u32,
+    channels: u8,
+    byte_len: u64,
+    content_hash: ContentHash,
+    mime_type: String,
+}
+impl AudioRessource {
+    pub fn new(
+        ressource_id: Uuid,
+        author_id: Uuid,
+        duration_ms: u32,
+        sample_rate: u32,
+        channels: u8,
+        data: &[u8],
+        mime_type: String,
+    ) -> Self {
+        Self {
+            ressource_id: ressource_id.into_bytes(),
+            author_id: author_id.into_bytes(),
+            duration_ms,
+            sample_rate,
+            channels,
+            byte_len: data.len() as u64,
+            content_hash: content_hash(data),
+            mime_type,
+        }
+    }
+    pub fn get_id(&self) -> Uuid {
+        Uuid::from_bytes(self.ressource_id)
+    }
+    pub fn get_author_id(&self) -> Uuid {
+        Uuid::from_bytes(self.author_id)
+    }
+    pub fn duration_ms(&self) -> u32 {
+        self.duration_ms
+    }
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+    pub fn byte_len(&self) -> u64 {
+        self.byte_len
+    }
+    pub fn content_hash(&self) -> &ContentHash {
+        &self.content_hash
+    }
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+}
+
+/// [`SyncNewRessource`] uploads a ressource that is not an animation (those go through
+/// [`crate::SyncNewAnim`]). `data` is empty for a text block.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
+pub struct SyncNewRessource {
+    descriptor: RessourcesDescriptorsKind,
+    data: Vec<u8>,
+}
+impl SyncNewRessource {
+    pub fn new_text_block(text_block: TextBlockRessource) -> Self {
+        Self {
+            descriptor: RessourcesDescriptorsKind::TextBlock(text_block),
+            data: vec![],
+        }
+    }
+    pub fn new_image(image: ImageRessource, data: Vec<u8>) -> Self {
+        Self {
+            descriptor: RessourcesDescriptorsKind::Image(image),
+            data,
+        }
+    }
+    pub fn new_sticker(sticker: StickerRessource, data: Vec<u8>) -> Self {
+        Self {
+            descriptor: RessourcesDescriptorsKind::Sticker(sticker),
+            data,
+        }
+    }
+    pub fn new_audio(audio: AudioRessource, data: Vec<u8>) -> Self {
+        Self {
+            descriptor: RessourcesDescriptorsKind::Audio(audio),
+            data,
+        }
+    }
+    pub fn descriptor(&self) -> &RessourcesDescriptorsKind {
+        &self.descriptor
+    }
+    pub fn ressource_id(&self) -> Uuid {
+        self.descriptor.get_id()
+    }
+    pub fn kind(&self) -> RessourceKind {
+        self.descriptor.kind()
+    }
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn take_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.data)
+    }
+}
+
+/// [`RessourceRefToFetch`] asks for a ressource of any kind, see
+/// [`crate::AnimationRefToFetch`] for animations.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct RessourceRefToFetch {
+    ressource_id: [u8; 16],
+    kind: RessourceKind,
+    context_id: [u8; 16],
+    user_id: [u8; 16],
+}
+impl RessourceRefToFetch {
+    pub fn new(ressource_id: Uuid, kind: RessourceKind, context_id: Uuid, user_id: Uuid) -> Self {
+        Self {
+            ressource_id: ressource_id.into_bytes(),
+            kind,
+            context_id: context_id.into_bytes(),
+            user_id: user_id.into_bytes(),
+        }
+    }
+    pub fn ressource_id(&self) -> Uuid {
+        Uuid::from_bytes(self.ressource_id)
+    }
+    pub fn kind(&self) -> RessourceKind {
+        self.kind
+    }
+    pub fn context_id(&self) -> Uuid {
+        Uuid::from_bytes(self.context_id)
+    }
+    pub fn user_id(&self) -> Uuid {
+        Uuid::from_bytes(self.user_id)
+    }
+}
+
+/// Answer to a [`RessourceRefToFetch`]: the descriptor and the payload it describes.
+#[derive(Debug, Clone, Serialize, Deserialize, Decode, Encode)]
+pub struct FetchRessource {
+    descriptor: RessourcesDescriptorsKind,
+    data: Vec<u8>,
+}
+impl FetchRessource {
+    pub fn new(descriptor: RessourcesDescriptorsKind, data: Vec<u8>) -> Self {
+        Self { descriptor, data }
+    }
+    pub fn descriptor(&self) -> &RessourcesDescriptorsKind {
+        &self.descriptor
+    }
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn take_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.data)
+    }
+}
+
+fn check_descriptor_strings(
+    descriptor: &RessourcesDescriptorsKind,
+    limits: &DecodeLimits,
+) -> Result<(), DecodeLimitError> {
+    match descriptor {
+        RessourcesDescriptorsKind::TextBlock(text_block) => {
+            if text_block.content.len() > MAX_TEXT_BLOCK_LEN {
+                return Err(DecodeLimitError::StringTooLong {
+                    field: "descriptor.content",
+                    len: text_block.content.len(),
+                    max: MAX_TEXT_BLOCK_LEN,
+                });
+            }
+            limits.check_string(
+                "descriptor.style.font_family",
+                &text_block.style.font_family,
+            )
+        }
+        RessourcesDescriptorsKind::Image(image) => {
+            limits.check_string("descriptor.mime_type", &image.mime_type)
+        }
+        RessourcesDescriptorsKind::Audio(audio) => {
+            limits.check_string("descriptor.mime_type", &audio.mime_type)
+        }
+        RessourcesDescriptorsKind::Animation(_)
+        | RessourcesDescriptorsKind::AnimationWithMetadata(..)
+        | RessourcesDescriptorsKind::AnimationWithRenditions(..)
+        | RessourcesDescriptorsKind::Sticker(_) => Ok(()),
+    }
+}
+
+impl DecodeLimited for SyncNewRessource {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(MAX_AUDIO_BYTES + 64 * crate::KIB)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes("data", self.data.len())?;
+        check_descriptor_strings(&self.descriptor, limits)
+    }
+}
+impl DecodeLimited for FetchRessource {
+    fn default_decode_limits() -> DecodeLimits {
+        SyncNewRessource::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes("data", self.data.len())?;
+        check_descriptor_strings(&self.descriptor, limits)
+    }
+}
+impl DecodeLimited for RessourceRefToFetch {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+
+fn check_dimensions(path: &str, violations: &mut Violations, width: u32, height: u32) {
+    let max = MAX_FRAME_DIMENSION as f64;
+    violations.check_range(path, "width", width as f64, 1.0, max);
+    violations.check_range(path, "height", height as f64, 1.0, max);
+}
+
+impl Validate for TextBlockRessource {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_empty(path, "content", self.content.len());
+        violations.check_range(
+            path,
+            "content.len",
+            self.content.len() as f64,
+            0.0,
+            MAX_TEXT_BLOCK_LEN as f64,
+        );
+        violations.check_not_empty(path, "style.font_family", self.style.font_family.len());
+        violations.check_range(
+            path,
+            "style.font_size",
+            self.style.font_size as f64,
+            1.0,
+            1024.0,
+        );
+        check_dimensions(path, violations, self.width, self.height);
+    }
+}
+impl Validate for ImageRessource {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        check_dimensions(path, violations, self.width, self.height);
+        violations.check_range(
+            path,
+            "byte_len",
+            self.byte_len as f64,
+            1.0,
+            MAX_IMAGE_BYTES as f64,
+        );
+        violations.check_not_empty(path, "mime_type", self.mime_type.len());
+    }
+}
+impl Validate for StickerRessource {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        check_dimensions(path, violations, self.width, self.height);
+        violations.check_range(
+            path,
+            "byte_len",
+            self.byte_len as f64,
+            1.0,
+            MAX_STICKER_BYTES as f64,
+        );
+    }
+}
+impl Validate for AudioRessource {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_range(
+            path,
+            "duration_ms",
+            self.duration_ms as f64,
+            1.0,
+            u32::MAX as f64,
+        );
+        violations.check_range(path, "channels", self.channels as f64, 1.0, 8.0);
+        violations.check_range(
+            path,
+            "byte_len",
+            self.byte_len as f64,
+            1.0,
+            MAX_AUDIO_BYTES as f64,
+        );
+        violations.check_not_empty(path, "mime_type", self.mime_type.len());
+    }
+}
+impl Validate for SyncNewRessource {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        self.descriptor
+            .validate_at(&field_path(path, "descriptor"), violations);
+        let kind = self.kind();
+        if kind == RessourceKind::Animation {
+            violations.push(
+                field_path(path, "descriptor"),
+                ViolationKind::Invalid("animations are sent with SyncNewAnim"),
+            );
+        }
+        violations.check_range(
+            path,
+            "data",
+            self.data.len() as f64,
+            0.0,
+            kind.max_payload_bytes() as f64,
+        );
+        if let Some(expected) = self.descriptor.content_hash()
+            && content_hash(&self.data) != *expected
+        {
+            violations.push(
+                field_path(path, "data"),
+                ViolationKind::Invalid("data does not match the descriptor content hash"),
+            );
+        }
+    }
+}
+impl Validate for RessourceRefToFetch {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "ressource_id", &self.ressource_id);
+        violations.check_not_nil(path, "context_id", &self.context_id);
+        violations.check_not_nil(path, "user_id", &self.user_id);
+    }
+}
+
+#[cfg(test)]
+mod media_ressources_test {
+    use uuid::Uuid;
+
+    use crate::{
+        CompositionData, CompositionResourceData, IdKind, IdMapping, RemapIds, RessourceKind,
+        StickerRessource, SyncNewRessource, Validate,
+    };
+
+    #[test]
+    fn compositions_mix_media() {
+        let data = vec![7u8; 512];
+        let sticker_id = Uuid::now_v7();
+        let sticker =
+            StickerRessource::new(sticker_id, Uuid::now_v7(), None, 128, 128, &data, false);
+        let upload = SyncNewRessource::new_sticker(sticker.clone(), data);
+        assert!(upload.validate().is_ok());
+        assert!(upload.kind() == RessourceKind::Sticker);
+        let tampered = SyncNewRessource::new_sticker(sticker, vec![8u8; 512]);
+        assert!(tampered.validate().is_err());
+
+        let anim_id = Uuid::now_v7();
+        let server_sticker_id = Uuid::now_v7();
+        let mut mapping = IdMapping::new_empty();
+        mapping.insert(IdKind::Ressource, sticker_id, server_sticker_id);
+        // Same local id for an animation, it must not touch the sticker.
+        mapping.insert(IdKind::Animation, sticker_id, Uuid::now_v7());
+        let mut composition = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7())
+            .add_resource(CompositionResourceData::new(anim_id, [0.0, 0.0], 8.0, 8.0))
+            .add_resource(
+                CompositionResourceData::new(sticker_id, [4.0, 4.0], 2.0, 2.0)
+                    .with_kind(RessourceKind::Sticker),
+            )
+            .build()
+            .unwrap();
+        assert!(composition.remap_ids(&mapping) == 1);
+        let resources = composition.resources_collection();
+        assert!(resources[0].kind() == RessourceKind::Animation);
+        assert!(resources[0].id() == anim_id);
+        assert!(resources[1].id() == server_sticker_id);
+    }
+}
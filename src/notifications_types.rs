@@ -2,6 +2,8 @@ use bincode::{Decode, Encode};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+use crate::{DecodeLimitError, DecodeLimited, DecodeLimits, MAX_USERNAME_LEN};
+
 #[derive(Decode, Encode, Debug)]
 pub struct NotifAccrossNodes {
     notification_id: [u8; 16],
@@ -215,6 +217,29 @@ impl NotifAcrossKind {
     }
 }
 
+impl DecodeLimited for NotifAccrossNodes {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+            .with_max_total_bytes(4 * crate::KIB)
+            .with_max_string_len(MAX_USERNAME_LEN)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_string("emitter_name", self.kind.get_emitter_name())?;
+        limits.check_string("recipient_name", self.kind.get_recipient_name())?;
+        match &self.kind {
+            NotifAcrossKind::NewInvitation {
+                direction,
+                accepted,
+                ..
+            } => {
+                limits.check_string("direction", direction)?;
+                limits.check_string("accepted", accepted)
+            }
+            NotifAcrossKind::NewRessourceAvailable { .. } => Ok(()),
+        }
+    }
+}
+
 fn check_contract_on_accepted(accepted: &str) -> Result<(), String> {
     match accepted {
         "pending" => Ok(()),
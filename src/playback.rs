@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AnimVariableContext, AnimationMetadata, Validate, ViolationKind, Violations, field_path,
+};
+
+/// Fastest playback accepted, 16 times the recorded speed.
+pub const MAX_PLAYBACK_SPEED: f32 = 16.0;
+/// Largest bincode encoding of [`PlaybackParams`], `u32` varints taking up to 5 bytes.
+pub(crate) const MAX_PLAYBACK_PARAMS_BYTES: usize = 1 + 4 + 5 + 5 + (1 + 5) + 5;
+
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Hash,
+)]
+pub enum LoopMode {
+    #[default]
+    Loop,
+    /// Plays once then holds the last frame.
+    Once,
+    /// Plays forward then backward, forever.
+    PingPong,
+}
+
+/// [`PlaybackParams`] is how one placement of an animation plays inside its composition. Times
+/// are milliseconds, trim points are frames of the animation, `trim_out` being excluded. The
+/// default plays the whole animation in a loop from the start of the composition.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct PlaybackParams {
+    loop_mode: LoopMode,
+    speed: f32,
+    /// Animation time played at the first frame shown.
+    start_offset_ms: u32,
+    trim_in: u32,
+    trim_out: Option<u32>,
+    /// Composition time before which nothing is shown.
+    delay_ms: u32,
+}
+impl Default for PlaybackParams {
+    fn default() -> Self {
+        Self {
+            loop_mode: LoopMode::Loop,
+            speed: 1.0,
+            start_offset_ms: 0,
+            trim_in: 0,
+            trim_out: None,
+            delay_ms: 0,
+        }
+    }
+}
+impl PlaybackParams {
+    pub fn with_loop_mode(mut self, loop_mode: LoopMode) -> Self {
+        self.loop_mode = loop_mode;
+        self
+    }
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+    pub fn with_start_offset(mut self, start_offset: Duration) -> Self {
+        self.start_offset_ms = duration_ms(start_offset);
+        self
+    }
+    /// Plays frames `trim_in..trim_out`, `None` standing for the last frame.
+    pub fn with_trim(mut self, trim_in: u32, trim_out: Option<u32>) -> Self {
+        self.trim_in = trim_in;
+        self.trim_out = trim_out;
+        self
+    }
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay_ms = duration_ms(delay);
+        self
+    }
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+    pub fn start_offset(&self) -> Duration {
+        Duration::from_millis(self.start_offset_ms as u64)
+    }
+    pub fn trim_in(&self) -> u32 {
+        self.trim_in
+    }
+    pub fn trim_out(&self) -> Option<u32> {
+        self.trim_out
+    }
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms as u64)
+    }
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+    /// Frame of the animation to show at `composition_time`, `None` before the delay or when
+    /// the animation has no frame. Trim points past the end of the animation are clamped.
+    pub fn frame_at(&self, composition_time: Duration, fps: u8, frame_count: u32) -> Option<u32> {
+        if fps == 0 || frame_count == 0 {
+            return None;
+        }
+        let played = composition_time.checked_sub(self.delay())?;
+        let trim_in = self.trim_in.min(frame_count - 1);
+        let trim_out = self.trim_out.unwrap_or(frame_count).min(frame_count);
+        let len = trim_out.saturating_sub(trim_in).max(1) as u64;
+        // Microseconds keep whole speeds and millisecond times exact.
+        let micros = played.as_micros() as f64 * self.speed.max(0.0) as f64
+            + self.start_offset_ms as f64 * 1000.0;
+        let frame = (micros * fps as f64 / 1_000_000.0).floor() as u64;
+        let index = match self.loop_mode {
+            LoopMode::Loop => frame % len,
+            LoopMode::Once => frame.min(len - 1),
+            LoopMode::PingPong if len == 1 => 0,
+            LoopMode::PingPong => {
+                let period = 2 * len - 2;
+                let step = frame % period;
+                if step < len { step } else { period - step }
+            }
+        };
+        Some(trim_in + index as u32)
+    }
+}
+
+fn duration_ms(duration: Duration) -> u32 {
+    duration.as_millis().min(u32::MAX as u128) as u32
+}
+
+impl Validate for PlaybackParams {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_finite(path, "speed", self.speed);
+        if self.speed.is_finite() && (self.speed <= 0.0 || self.speed > MAX_PLAYBACK_SPEED) {
+            violations.push(
+                field_path(path, "speed"),
+                ViolationKind::Invalid("speed must be in 0..=MAX_PLAYBACK_SPEED, 0 excluded"),
+            );
+        }
+        if let Some(trim_out) = self.trim_out
+            && trim_out <= self.trim_in
+        {
+            violations.push(
+                field_path(path, "trim_out"),
+                ViolationKind::Invalid("trim must keep at least one frame"),
+            );
+        }
+    }
+}
+
+impl AnimVariableContext {
+    /// Frame of the placed animation to show at `composition_time`, see
+    /// [`PlaybackParams::frame_at`].
+    pub fn frame_at(
+        &self,
+        composition_time: Duration,
+        metadata: &AnimationMetadata,
+    ) -> Option<u32> {
+        self.playback()
+            .frame_at(composition_time, metadata.fps(), metadata.frame_count())
+    }
+}
+
+#[cfg(test)]
+mod playback_test {
+    use std::time::Duration;
+
+    use crate::{LoopMode, PlaybackParams, Validate};
+
+    #[test]
+    fn frame_at_follows_loop_mode_trim_and_delay() {
+        let ms = Duration::from_millis;
+        // 10 fps, 10 frames, one frame every 100 ms.
+        let looping = PlaybackParams::default();
+        assert!(looping.frame_at(ms(250), 10, 10) == Some(2));
+        assert!(looping.frame_at(ms(1250), 10, 10) == Some(2));
+
+        let once = PlaybackParams::default()
+            .with_loop_mode(LoopMode::Once)
+            .with_delay(ms(500))
+            .with_trim(2, Some(6));
+        assert!(once.frame_at(ms(400), 10, 10).is_none());
+        assert!(once.frame_at(ms(500), 10, 10) == Some(2));
+        assert!(once.frame_at(ms(5000), 10, 10) == Some(5));
+
+        let ping_pong = PlaybackParams::default()
+            .with_loop_mode(LoopMode::PingPong)
+            .with_speed(2.0)
+            .with_start_offset(ms(100));
+        // Offset of one frame, then two frames every 100 ms: 1, 3, 5, 7, 9, 7...
+        let frames: Vec<_> = (0..6)
+            .map(|it| ping_pong.frame_at(ms(it * 100), 10, 10).unwrap())
+            .collect();
+        assert!(frames == [1, 3, 5, 7, 9, 7]);
+
+        assert!(
+            PlaybackParams::default()
+                .with_speed(0.0)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            PlaybackParams::default()
+                .with_trim(4, Some(4))
+                .validate()
+                .is_err()
+        );
+        assert!(once.validate().is_ok());
+    }
+}
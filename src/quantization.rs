@@ -0,0 +1,120 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// [`QuantizationPrecision`] is the number of quantization steps per unit.
+///
+/// Round trip error bound: for a value `x` inside [`Self::signed_range`] (or inside 0.0..=1.0
+/// for the unit quantizer), `|x - dequantize(quantize(x))| <= self.max_error()`, that is half a
+/// step, plus the f32 rounding of the dequantized value (half an ulp of `x`). Values outside
+/// the range are clamped to its ends.
+///
+/// Signed values are quantized on `i64`: with [`Self::LEGACY`] the range is about ±1.4e14
+/// units, far wider than the 0.0..=65537.0 of the unsigned `u32` layout it replaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
+pub struct QuantizationPrecision {
+    steps_per_unit: u32,
+}
+
+impl QuantizationPrecision {
+    /// The fixed scale used before precision became configurable. Data quantized with the old
+    /// `QUANTIZE_V` decodes to the same values with it.
+    pub const LEGACY: QuantizationPrecision = QuantizationPrecision::new(65535);
+
+    /// A precision of 0 steps is meaningless and is raised to 1.
+    pub const fn new(steps_per_unit: u32) -> Self {
+        Self {
+            steps_per_unit: if steps_per_unit == 0 {
+                1
+            } else {
+                steps_per_unit
+            },
+        }
+    }
+    /// Smallest precision whose round trip error stays under `max_error`.
+    pub fn from_max_error(max_error: f32) -> Self {
+        let steps = (0.5 / max_error.abs() as f64).ceil();
+        Self::new(steps.clamp(1.0, u32::MAX as f64) as u32)
+    }
+    pub fn steps_per_unit(&self) -> u32 {
+        self.steps_per_unit
+    }
+    pub fn step(&self) -> f32 {
+        (1.0 / self.steps_per_unit as f64) as f32
+    }
+    pub fn max_error(&self) -> f32 {
+        (0.5 / self.steps_per_unit as f64) as f32
+    }
+    /// Values [`Self::quantize_signed`] can represent without clamping.
+    pub fn signed_range(&self) -> (f32, f32) {
+        let steps = self.steps_per_unit as f64;
+        (
+            (i64::MIN as f64 / steps) as f32,
+            (i64::MAX as f64 / steps) as f32,
+        )
+    }
+    /// Quantizes a value normalized in 0.0..=1.0.
+    pub fn quantize_unit(&self, x: f32) -> u32 {
+        if x.is_nan() {
+            return 0;
+        }
+        (x.clamp(0.0, 1.0) as f64 * self.steps_per_unit as f64).round() as u32
+    }
+    pub fn dequantize_unit(&self, q: u32) -> f32 {
+        (q.min(self.steps_per_unit) as f64 / self.steps_per_unit as f64) as f32
+    }
+    /// Quantizes any coordinate, negative ones included.
+    pub fn quantize_signed(&self, x: f32) -> i64 {
+        if x.is_nan() {
+            return 0;
+        }
+        // `as` saturates to the i64 range.
+        (x as f64 * self.steps_per_unit as f64).round() as i64
+    }
+    pub fn dequantize_signed(&self, q: i64) -> f32 {
+        (q as f64 / self.steps_per_unit as f64) as f32
+    }
+}
+
+impl Default for QuantizationPrecision {
+    fn default() -> Self {
+        Self::LEGACY
+    }
+}
+
+#[cfg(test)]
+mod quantization_test {
+    use crate::QuantizationPrecision;
+
+    #[test]
+    fn signed_round_trip_stays_within_bound() {
+        for precision in [
+            QuantizationPrecision::LEGACY,
+            QuantizationPrecision::new(16),
+            QuantizationPrecision::from_max_error(0.01),
+        ] {
+            for x in [
+                -1234.567f32,
+                -0.3,
+                0.0,
+                0.49,
+                812.25,
+                20_000.125,
+                65_537.0,
+                -90_000.5,
+            ] {
+                let round_trip = precision.dequantize_signed(precision.quantize_signed(x));
+                let bound = precision.max_error() + x.abs() * f32::EPSILON;
+                assert!((x - round_trip).abs() <= bound);
+            }
+        }
+    }
+
+    #[test]
+    fn legacy_unit_values_decode_unchanged() {
+        let precision = QuantizationPrecision::LEGACY;
+        // 0.25 was stored as round(0.25 * 65535) by the fixed scale quantizer.
+        assert!(precision.dequantize_unit(16384) == 16384.0 / 65535.0);
+        assert!(precision.quantize_unit(0.25) == 16384);
+        assert!(precision.quantize_unit(-3.0) == 0);
+    }
+}
@@ -0,0 +1,560 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    ContentHash, DecodeLimitError, DecodeLimited, DecodeLimits, MAX_ANIMATION_BYTES,
+    MAX_UPLOAD_CHUNK_BYTES, Validate, ViolationKind, Violations, content_hash, field_path,
+};
+
+/// [`FetchAnimationRange`] asks for the bytes `range.0..range.1` of an animation container,
+/// header included. A range spans at most [`MAX_UPLOAD_CHUNK_BYTES`], so that its
+/// [`PartialAnimationResponse`] fits in the decode limits. `rendition_id` names a rendition, `None` being the full animation.
+/// The first request of a download sets `with_frame_index` to learn where each frame lies.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct FetchAnimationRange {
+    anim_id: [u8; 16],
+    context_id: [u8; 16],
+    user_id: [u8; 16],
+    rendition_id: Option<[u8; 16]>,
+    range: (u64, u64),
+    with_frame_index: bool,
+}
+impl FetchAnimationRange {
+    pub fn new(
+        anim_id: Uuid,
+        context_id: Uuid,
+        user_id: Uuid,
+        rendition_id: Option<Uuid>,
+        range: (u64, u64),
+        with_frame_index: bool,
+    ) -> Self {
+        Self {
+            anim_id: anim_id.into_bytes(),
+            context_id: context_id.into_bytes(),
+            user_id: user_id.into_bytes(),
+            rendition_id: rendition_id.map(|it| it.into_bytes()),
+            range,
+            with_frame_index,
+        }
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn context_id(&self) -> Uuid {
+        Uuid::from_bytes(self.context_id)
+    }
+    pub fn user_id(&self) -> Uuid {
+        Uuid::from_bytes(self.user_id)
+    }
+    pub fn rendition_id(&self) -> Option<Uuid> {
+        self.rendition_id.map(Uuid::from_bytes)
+    }
+    pub fn range(&self) -> (u64, u64) {
+        self.range
+    }
+    pub fn with_frame_index(&self) -> bool {
+        self.with_frame_index
+    }
+}
+
+/// Position of one frame inside an animation container.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub struct FrameIndexEntry {
+    offset: u64,
+    len: u32,
+    keyframe: bool,
+}
+impl FrameIndexEntry {
+    pub fn new(offset: u64, len: u32, keyframe: bool) -> Self {
+        Self {
+            offset,
+            len,
+            keyframe,
+        }
+    }
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Saturates for entries decoded with an offset near `u64::MAX`.
+    pub fn end(&self) -> u64 {
+        self.offset.saturating_add(self.len as u64)
+    }
+    pub fn keyframe(&self) -> bool {
+        self.keyframe
+    }
+}
+
+/// [`FrameIndex`] lists the frames of an animation container in playback order. Offsets are
+/// relative to the start of the container, so they match the ranges of [`FetchAnimationRange`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub struct FrameIndex {
+    frames: Vec<FrameIndexEntry>,
+}
+impl FrameIndex {
+    pub fn new(frames: Vec<FrameIndexEntry>) -> Self {
+        Self { frames }
+    }
+    pub fn frames(&self) -> &[FrameIndexEntry] {
+        &self.frames
+    }
+    pub fn frame(&self, frame: usize) -> Option<&FrameIndexEntry> {
+        self.frames.get(frame)
+    }
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    /// Number of leading frames fully held by a download of the first `received_len` bytes.
+    pub fn playable_frames(&self, received_len: u64) -> usize {
+        self.frames
+            .iter()
+            .take_while(|it| it.end() <= received_len)
+            .count()
+    }
+    /// Byte range covering `count` frames from `first`, `None` when out of the index.
+    pub fn byte_range_for_frames(&self, first: usize, count: usize) -> Option<(u64, u64)> {
+        let frames = self.frames.get(first..first.checked_add(count)?)?;
+        let start = frames.iter().map(|it| it.offset).min()?;
+        let end = frames.iter().map(|it| it.end()).max()?;
+        Some((start, end))
+    }
+}
+
+/// [`PartialAnimationResponse`] holds the bytes `range.0..range.1` of an animation container of
+/// `total_len` bytes. `content_hash` is the hash of the whole container, to check it once
+/// every range arrived.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct PartialAnimationResponse {
+    anim_id: [u8; 16],
+    rendition_id: Option<[u8; 16]>,
+    range: (u64, u64),
+    total_len: u64,
+    content_hash: ContentHash,
+    frame_index: Option<FrameIndex>,
+    bytes: Vec<u8>,
+}
+impl PartialAnimationResponse {
+    pub fn new(
+        anim_id: Uuid,
+        rendition_id: Option<Uuid>,
+        offset: u64,
+        total_len: u64,
+        content_hash: ContentHash,
+        frame_index: Option<FrameIndex>,
+        bytes: Vec<u8>,
+    ) -> Result<Self, RangedFetchError> {
+        let end =
+            offset
+                .checked_add(bytes.len() as u64)
+                .ok_or(RangedFetchError::RangeOverflow {
+                    offset,
+                    len: bytes.len(),
+                })?;
+        Ok(Self {
+            anim_id: anim_id.into_bytes(),
+            rendition_id: rendition_id.map(|it| it.into_bytes()),
+            range: (offset, end),
+            total_len,
+            content_hash,
+            frame_index,
+            bytes,
+        })
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn rendition_id(&self) -> Option<Uuid> {
+        self.rendition_id.map(Uuid::from_bytes)
+    }
+    pub fn range(&self) -> (u64, u64) {
+        self.range
+    }
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+    pub fn content_hash(&self) -> &ContentHash {
+        &self.content_hash
+    }
+    pub fn frame_index(&self) -> Option<&FrameIndex> {
+        self.frame_index.as_ref()
+    }
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.bytes)
+    }
+    pub fn is_last(&self) -> bool {
+        self.range.1 >= self.total_len
+    }
+}
+
+/// [`ProgressiveAnimationBuffer`] appends the ranges of a download in order. Playback can start
+/// on [`Self::playable_frames`], and [`Self::next_request`] resumes an interrupted download
+/// where it stopped.
+#[derive(Debug, Clone)]
+pub struct ProgressiveAnimationBuffer {
+    anim_id: Uuid,
+    rendition_id: Option<Uuid>,
+    total_len: u64,
+    content_hash: ContentHash,
+    frame_index: Option<FrameIndex>,
+    data: Vec<u8>,
+}
+impl ProgressiveAnimationBuffer {
+    /// Starts from the first response of the download, which must begin at offset 0. The
+    /// container is held in memory, so `total_len` is held to [`MAX_ANIMATION_BYTES`].
+    pub fn new(first: PartialAnimationResponse) -> Result<Self, RangedFetchError> {
+        if first.total_len > MAX_ANIMATION_BYTES as u64 {
+            return Err(RangedFetchError::TooLarge {
+                total_len: first.total_len,
+                max: MAX_ANIMATION_BYTES,
+            });
+        }
+        let mut buffer = Self {
+            anim_id: first.anim_id(),
+            rendition_id: first.rendition_id(),
+            total_len: first.total_len,
+            content_hash: first.content_hash,
+            frame_index: None,
+            data: vec![],
+        };
+        buffer.push(first)?;
+        Ok(buffer)
+    }
+    pub fn push(&mut self, mut response: PartialAnimationResponse) -> Result<(), RangedFetchError> {
+        if response.anim_id() != self.anim_id || response.rendition_id() != self.rendition_id {
+            return Err(RangedFetchError::AnimMismatch);
+        }
+        if response.total_len != self.total_len || response.content_hash != self.content_hash {
+            return Err(RangedFetchError::ContentChanged);
+        }
+        // the range announced on the wire is not trusted, the bytes are
+        let start = response.range.0;
+        let end = start.saturating_add(response.bytes.len() as u64);
+        if start != self.data.len() as u64 {
+            return Err(RangedFetchError::NotContiguous {
+                expected: self.data.len() as u64,
+                received: start,
+            });
+        }
+        if end > self.total_len {
+            return Err(RangedFetchError::OutOfBounds {
+                end,
+                total_len: self.total_len,
+            });
+        }
+        if let Some(frame_index) = response.frame_index.take() {
+            self.frame_index = Some(frame_index);
+        }
+        self.data.extend_from_slice(&response.bytes);
+        Ok(())
+    }
+    pub fn received_len(&self) -> u64 {
+        self.data.len() as u64
+    }
+    pub fn is_complete(&self) -> bool {
+        self.received_len() == self.total_len
+    }
+    pub fn frame_index(&self) -> Option<&FrameIndex> {
+        self.frame_index.as_ref()
+    }
+    /// Leading frames already received, 0 until a frame index arrived.
+    pub fn playable_frames(&self) -> usize {
+        self.frame_index
+            .as_ref()
+            .map(|it| it.playable_frames(self.received_len()))
+            .unwrap_or(0)
+    }
+    pub fn received(&self) -> &[u8] {
+        &self.data
+    }
+    /// Request for the next `max_len` bytes, `None` once complete. `max_len` is clamped to
+    /// [`MAX_UPLOAD_CHUNK_BYTES`].
+    pub fn next_request(
+        &self,
+        context_id: Uuid,
+        user_id: Uuid,
+        max_len: u64,
+    ) -> Option<FetchAnimationRange> {
+        if self.is_complete() {
+            return None;
+        }
+        let start = self.received_len();
+        Some(FetchAnimationRange::new(
+            self.anim_id,
+            context_id,
+            user_id,
+            self.rendition_id,
+            (
+                start,
+                start
+                    .saturating_add(max_len.clamp(1, MAX_UPLOAD_CHUNK_BYTES as u64))
+                    .min(self.total_len),
+            ),
+            self.frame_index.is_none(),
+        ))
+    }
+    /// Checks the content hash of the completed download and returns it.
+    pub fn finalize(self) -> Result<Vec<u8>, RangedFetchError> {
+        if !self.is_complete() {
+            return Err(RangedFetchError::Incomplete {
+                received: self.received_len(),
+                total_len: self.total_len,
+            });
+        }
+        if content_hash(&self.data) != self.content_hash {
+            return Err(RangedFetchError::HashMismatch);
+        }
+        Ok(self.data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangedFetchError {
+    TooLarge {
+        total_len: u64,
+        max: usize,
+    },
+    RangeOverflow {
+        offset: u64,
+        len: usize,
+    },
+    AnimMismatch,
+    /// The animation was replaced on the server since the download started.
+    ContentChanged,
+    NotContiguous {
+        expected: u64,
+        received: u64,
+    },
+    OutOfBounds {
+        end: u64,
+        total_len: u64,
+    },
+    Incomplete {
+        received: u64,
+        total_len: u64,
+    },
+    HashMismatch,
+}
+
+impl std::fmt::Display for RangedFetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge { total_len, max } => {
+                write!(
+                    f,
+                    "RangedFetchError TooLarge [{}] bytes, max [{}]",
+                    total_len, max
+                )
+            }
+            Self::RangeOverflow { offset, len } => {
+                write!(
+                    f,
+                    "RangedFetchError RangeOverflow offset [{}] len [{}]",
+                    offset, len
+                )
+            }
+            Self::AnimMismatch => write!(f, "RangedFetchError AnimMismatch"),
+            Self::ContentChanged => write!(f, "RangedFetchError ContentChanged"),
+            Self::NotContiguous { expected, received } => {
+                write!(
+                    f,
+                    "RangedFetchError NotContiguous expected offset [{}] received [{}]",
+                    expected, received
+                )
+            }
+            Self::OutOfBounds { end, total_len } => {
+                write!(
+                    f,
+                    "RangedFetchError OutOfBounds end [{}] total len [{}]",
+                    end, total_len
+                )
+            }
+            Self::Incomplete {
+                received,
+                total_len,
+            } => {
+                write!(
+                    f,
+                    "RangedFetchError Incomplete [{}] of [{}] bytes",
+                    received, total_len
+                )
+            }
+            Self::HashMismatch => write!(f, "RangedFetchError HashMismatch"),
+        }
+    }
+}
+
+impl std::error::Error for RangedFetchError {}
+
+impl DecodeLimited for FetchAnimationRange {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+impl DecodeLimited for PartialAnimationResponse {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+            .with_max_total_bytes(2 * MAX_UPLOAD_CHUNK_BYTES)
+            .with_max_collection_len(u16::MAX as usize)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_bytes("bytes", self.bytes.len())?;
+        if let Some(frame_index) = &self.frame_index {
+            limits.check_collection("frame_index.frames", frame_index.frames.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl Validate for FetchAnimationRange {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_nil(path, "context_id", &self.context_id);
+        violations.check_not_nil(path, "user_id", &self.user_id);
+        let (start, end) = self.range;
+        if start >= end {
+            violations.push(
+                field_path(path, "range"),
+                ViolationKind::InvertedBounds {
+                    min: start as f64,
+                    max: end as f64,
+                },
+            );
+        } else if end - start > MAX_UPLOAD_CHUNK_BYTES as u64 {
+            violations.push(
+                field_path(path, "range"),
+                ViolationKind::OutOfRange {
+                    value: (end - start) as f64,
+                    min: 1.0,
+                    max: MAX_UPLOAD_CHUNK_BYTES as f64,
+                },
+            );
+        }
+    }
+}
+impl Validate for PartialAnimationResponse {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        if self.range.1 > self.total_len {
+            violations.push(
+                field_path(path, "range"),
+                ViolationKind::OutOfRange {
+                    value: self.range.1 as f64,
+                    min: 0.0,
+                    max: self.total_len as f64,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod ranged_fetch_test {
+    use uuid::Uuid;
+
+    use crate::{
+        FetchAnimationRange, FrameIndex, FrameIndexEntry, MAX_ANIMATION_BYTES,
+        MAX_UPLOAD_CHUNK_BYTES, PartialAnimationResponse, ProgressiveAnimationBuffer,
+        RangedFetchError, Validate, content_hash,
+    };
+
+    #[test]
+    fn download_resumes_and_plays_first_frames() {
+        let container: Vec<u8> = (0..100u8).collect();
+        let hash = content_hash(&container);
+        let anim_id = Uuid::now_v7();
+        let index = FrameIndex::new(vec![
+            FrameIndexEntry::new(16, 30, true),
+            FrameIndexEntry::new(46, 30, false),
+            FrameIndexEntry::new(76, 24, false),
+        ]);
+        let response = |offset: usize, end: usize, frame_index: Option<FrameIndex>| {
+            PartialAnimationResponse::new(
+                anim_id,
+                None,
+                offset as u64,
+                100,
+                hash,
+                frame_index,
+                container[offset..end].to_vec(),
+            )
+            .unwrap()
+        };
+
+        let mut buffer = ProgressiveAnimationBuffer::new(response(0, 50, Some(index))).unwrap();
+        assert!(buffer.playable_frames() == 1);
+        assert!(
+            buffer.push(response(60, 100, None))
+                == Err(RangedFetchError::NotContiguous {
+                    expected: 50,
+                    received: 60
+                })
+        );
+
+        let next = buffer
+            .next_request(Uuid::now_v7(), Uuid::now_v7(), 1000)
+            .unwrap();
+        assert!(next.range() == (50, 100) && !next.with_frame_index());
+        let unbounded = buffer
+            .next_request(Uuid::now_v7(), Uuid::now_v7(), u64::MAX)
+            .unwrap();
+        assert!(unbounded.range() == (50, 100));
+        assert!(unbounded.validate().is_ok());
+        assert!(FrameIndexEntry::new(u64::MAX - 1, 30, false).end() == u64::MAX);
+        buffer.push(response(50, 100, None)).unwrap();
+        assert!(buffer.playable_frames() == 3);
+        assert!(buffer.finalize() == Ok(container));
+    }
+
+    #[test]
+    fn oversized_ranges_and_containers_are_rejected() {
+        let anim_id = Uuid::now_v7();
+        let hash = content_hash(&[]);
+        assert!(
+            PartialAnimationResponse::new(anim_id, None, u64::MAX, 100, hash, None, vec![0; 2])
+                == Err(RangedFetchError::RangeOverflow {
+                    offset: u64::MAX,
+                    len: 2
+                })
+        );
+        let too_large = MAX_ANIMATION_BYTES as u64 + 1;
+        let first =
+            PartialAnimationResponse::new(anim_id, None, 0, too_large, hash, None, vec![]).unwrap();
+        assert!(
+            ProgressiveAnimationBuffer::new(first).err()
+                == Some(RangedFetchError::TooLarge {
+                    total_len: too_large,
+                    max: MAX_ANIMATION_BYTES
+                })
+        );
+
+        let full = (0, MAX_UPLOAD_CHUNK_BYTES as u64);
+        let fetch = |range| {
+            FetchAnimationRange::new(anim_id, Uuid::now_v7(), Uuid::now_v7(), None, range, true)
+        };
+        assert!(fetch(full).validate().is_ok());
+        assert!(fetch((0, full.1 + 1)).validate().is_err());
+        let large = (MAX_UPLOAD_CHUNK_BYTES * 4) as u64;
+        let first =
+            PartialAnimationResponse::new(anim_id, None, 0, large, hash, None, vec![]).unwrap();
+        let buffer = ProgressiveAnimationBuffer::new(first).unwrap();
+        let next = buffer
+            .next_request(Uuid::now_v7(), Uuid::now_v7(), u64::MAX)
+            .unwrap();
+        assert!(next.range() == full);
+    }
+}
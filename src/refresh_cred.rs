@@ -1,5 +1,7 @@
 use bincode::{Decode, Encode};
 
+use crate::{DecodeLimitError, DecodeLimited, DecodeLimits};
+
 #[derive(Clone, Encode, Decode)]
 pub struct RefreshToken {
     token: String,
@@ -51,3 +53,31 @@ impl NewGeneratedAuthTokens {
         &self.jwt_token.token()
     }
 }
+
+impl DecodeLimited for RefreshToken {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT
+            .with_max_total_bytes(16 * crate::KIB)
+            .with_max_string_len(8 * crate::KIB)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_string("token", &self.token)
+    }
+}
+impl DecodeLimited for JwtToken {
+    fn default_decode_limits() -> DecodeLimits {
+        RefreshToken::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_string("token", &self.token)
+    }
+}
+impl DecodeLimited for NewGeneratedAuthTokens {
+    fn default_decode_limits() -> DecodeLimits {
+        RefreshToken::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        self.refresh_token.check_decode_limits(limits)?;
+        self.jwt_token.check_decode_limits(limits)
+    }
+}
@@ -0,0 +1,178 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    DecodeLimitError, DecodeLimited, DecodeLimits, MAX_ANIMATION_BYTES, MAX_FRAME_DIMENSION,
+    Validate, Violations,
+};
+
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenditionKind {
+    /// Still frame only.
+    Poster,
+    /// Downscaled animation.
+    Preview,
+    /// The animation as uploaded.
+    Full,
+}
+
+/// [`AnimationRendition`] is one encoding of an animation resource the server can send.
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnimationRendition {
+    rendition_id: [u8; 16],
+    kind: RenditionKind,
+    width: u32,
+    height: u32,
+    byte_len: u64,
+}
+impl AnimationRendition {
+    pub fn new(
+        rendition_id: Uuid,
+        kind: RenditionKind,
+        width: u32,
+        height: u32,
+        byte_len: u64,
+    ) -> Self {
+        Self {
+            rendition_id: rendition_id.into_bytes(),
+            kind,
+            width,
+            height,
+            byte_len,
+        }
+    }
+    pub fn rendition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.rendition_id)
+    }
+    pub fn kind(&self) -> RenditionKind {
+        self.kind
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn byte_len(&self) -> u64 {
+        self.byte_len
+    }
+    fn covers(&self, target_width: u32, target_height: u32) -> bool {
+        self.width >= target_width && self.height >= target_height
+    }
+}
+
+/// Picks the rendition to fetch for a display of `target_width` x `target_height` pixels when
+/// at most `max_bytes` can be downloaded:
+/// - the smallest animated rendition within budget covering the target size,
+/// - else the largest animated rendition within budget,
+/// - else the smallest rendition, a poster most of the time, so that something can be shown.
+///
+/// Returns `None` only for an empty list.
+pub fn select_rendition(
+    renditions: &[AnimationRendition],
+    max_bytes: u64,
+    target_width: u32,
+    target_height: u32,
+) -> Option<&AnimationRendition> {
+    let animated_within_budget = || {
+        renditions
+            .iter()
+            .filter(|it| it.kind != RenditionKind::Poster && it.byte_len <= max_bytes)
+    };
+    animated_within_budget()
+        .filter(|it| it.covers(target_width, target_height))
+        .min_by_key(|it| it.byte_len)
+        .or_else(|| animated_within_budget().max_by_key(|it| it.byte_len))
+        .or_else(|| renditions.iter().min_by_key(|it| it.byte_len))
+}
+
+/// [`FetchAnimationRendition`] asks for one rendition of an animation, see
+/// [`crate::AnimationRefToFetch`] for the full animation.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq)]
+pub struct FetchAnimationRendition {
+    anim_id: [u8; 16],
+    context_id: [u8; 16],
+    user_id: [u8; 16],
+    rendition_id: [u8; 16],
+}
+impl FetchAnimationRendition {
+    pub fn new(anim_id: Uuid, context_id: Uuid, user_id: Uuid, rendition_id: Uuid) -> Self {
+        Self {
+            anim_id: anim_id.into_bytes(),
+            context_id: context_id.into_bytes(),
+            user_id: user_id.into_bytes(),
+            rendition_id: rendition_id.into_bytes(),
+        }
+    }
+    pub fn anim_id(&self) -> Uuid {
+        Uuid::from_bytes(self.anim_id)
+    }
+    pub fn context_id(&self) -> Uuid {
+        Uuid::from_bytes(self.context_id)
+    }
+    pub fn user_id(&self) -> Uuid {
+        Uuid::from_bytes(self.user_id)
+    }
+    pub fn rendition_id(&self) -> Uuid {
+        Uuid::from_bytes(self.rendition_id)
+    }
+}
+
+impl DecodeLimited for FetchAnimationRendition {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(1024)
+    }
+    fn check_decode_limits(&self, _limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        Ok(())
+    }
+}
+
+impl Validate for AnimationRendition {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "rendition_id", &self.rendition_id);
+        let max = MAX_FRAME_DIMENSION as f64;
+        violations.check_range(path, "width", self.width as f64, 1.0, max);
+        violations.check_range(path, "height", self.height as f64, 1.0, max);
+        violations.check_range(
+            path,
+            "byte_len",
+            self.byte_len as f64,
+            1.0,
+            MAX_ANIMATION_BYTES as f64,
+        );
+    }
+}
+impl Validate for FetchAnimationRendition {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_nil(path, "anim_id", &self.anim_id);
+        violations.check_not_nil(path, "context_id", &self.context_id);
+        violations.check_not_nil(path, "user_id", &self.user_id);
+        violations.check_not_nil(path, "rendition_id", &self.rendition_id);
+    }
+}
+
+#[cfg(test)]
+mod renditions_test {
+    use uuid::Uuid;
+
+    use crate::{AnimationRendition, RenditionKind, select_rendition};
+
+    #[test]
+    fn selection_respects_budget_then_size() {
+        let renditions = [
+            AnimationRendition::new(Uuid::now_v7(), RenditionKind::Full, 1080, 1920, 8_000_000),
+            AnimationRendition::new(Uuid::now_v7(), RenditionKind::Poster, 1080, 1920, 90_000),
+            AnimationRendition::new(Uuid::now_v7(), RenditionKind::Preview, 360, 640, 600_000),
+        ];
+        let kind = |max_bytes, width, height| {
+            select_rendition(&renditions, max_bytes, width, height).map(|it| it.kind())
+        };
+
+        assert!(kind(u64::MAX, 300, 500) == Some(RenditionKind::Preview));
+        assert!(kind(u64::MAX, 720, 1280) == Some(RenditionKind::Full));
+        assert!(kind(1_000_000, 720, 1280) == Some(RenditionKind::Preview));
+        assert!(kind(10_000, 720, 1280) == Some(RenditionKind::Poster));
+        assert!(select_rendition(&[], 10_000, 1, 1).is_none());
+    }
+}
@@ -1,14 +1,17 @@
-use std::ops::Deref;
+use std::{ops::Deref, time::Duration};
 
 use bincode::{Decode, Encode};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    AnimVariableContext, ressources_descriptors::ressources_descriptors_kind::AnimationRessource,
+    AnimVariableContext, AnimationRendition, AudioRessource, ContentHash, DecodeLimitError,
+    DecodeLimited, DecodeLimits, ImageRessource, LegacyAnimVariableContext, MAX_ANIMATION_BYTES,
+    MAX_FPS, MAX_FRAME_DIMENSION, RessourceKind, StickerRessource, SyncNewAnim, TextBlockRessource,
+    Validate, ViolationKind, Violations, field_path, indexed_path,
+    ressources_descriptors::ressources_descriptors_kind::AnimationRessource, select_rendition,
 };
-type CtxId = [u8; 16];
-
 /// [`RessourcesDescritors`] represents a collection of ressources a client needs to fetch from the
 /// server
 #[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq)]
@@ -40,13 +43,76 @@ impl RessourcesDescriptors {
         }
     }
 }
-impl<'a> From<BorrowedRessourcesDescriptorsKind<'a>> for RessourcesDescriptorsKind {
-    fn from(value: BorrowedRessourcesDescriptorsKind<'a>) -> Self {
-        match &value.0.0 {
-            RessourcesDescriptorsKind::Animation(anim_resources) => {
-                Self::Animation(anim_resources.clone())
+impl DecodeLimited for RessourcesDescriptors {
+    fn default_decode_limits() -> DecodeLimits {
+        DecodeLimits::DEFAULT.with_max_total_bytes(4 * crate::MIB)
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("ressources_collection", self.ressources_collection.len())?;
+        for (descriptor, contexts) in &self.ressources_collection {
+            if let Some(metadata) = descriptor.animation_metadata() {
+                limits.check_string(
+                    "ressources_collection.metadata.mime_type",
+                    metadata.mime_type(),
+                )?;
+            }
+            limits.check_collection(
+                "ressources_collection.renditions",
+                descriptor.renditions().len(),
+            )?;
+            limits.check_collection("ressources_collection.contexts", contexts.len())?;
+            for context in contexts {
+                context.check_decode_limits(limits)?;
             }
         }
+        Ok(())
+    }
+}
+
+/// Layout of [`RessourcesDescriptors`] holding [`LegacyAnimVariableContext`]. Decode already
+/// stored data with it and convert with `into()`.
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq)]
+pub struct LegacyRessourcesDescriptors {
+    ressources_collection: Vec<(RessourcesDescriptorsKind, Vec<LegacyAnimVariableContext>)>,
+}
+impl DecodeLimited for LegacyRessourcesDescriptors {
+    fn default_decode_limits() -> DecodeLimits {
+        RessourcesDescriptors::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        limits.check_collection("ressources_collection", self.ressources_collection.len())?;
+        for (_, contexts) in &self.ressources_collection {
+            limits.check_collection("ressources_collection.contexts", contexts.len())?;
+        }
+        Ok(())
+    }
+}
+impl From<LegacyRessourcesDescriptors> for RessourcesDescriptors {
+    fn from(value: LegacyRessourcesDescriptors) -> Self {
+        Self {
+            ressources_collection: value
+                .ressources_collection
+                .into_iter()
+                .map(|(descriptor, contexts)| {
+                    (descriptor, contexts.into_iter().map(Into::into).collect())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Validate for RessourcesDescriptors {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        for (index, (descriptor, contexts)) in self.ressources_collection.iter().enumerate() {
+            let item_path = indexed_path(path, "ressources_collection", index);
+            descriptor.validate_at(&field_path(&item_path, "descriptor"), violations);
+            violations.check_items(&item_path, "contexts", contexts);
+        }
+    }
+}
+impl<'a> From<BorrowedRessourcesDescriptorsKind<'a>> for RessourcesDescriptorsKind {
+    fn from(value: BorrowedRessourcesDescriptorsKind<'a>) -> Self {
+        value.0.0.clone()
     }
 }
 
@@ -75,9 +141,21 @@ impl<'a> Deref for BorrowedRessourcesDescriptorsKind<'a> {
 pub struct BorrowedRessourcesDescriptorsKind<'a>(
     &'a (RessourcesDescriptorsKind, Vec<AnimVariableContext>),
 );
+/// Variants are only ever appended: descriptors encoded before a variant existed keep decoding.
 #[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RessourcesDescriptorsKind {
     Animation(AnimationRessource),
+    AnimationWithMetadata(AnimationRessource, AnimationMetadata),
+    TextBlock(TextBlockRessource),
+    Image(ImageRessource),
+    Sticker(StickerRessource),
+    Audio(AudioRessource),
+    /// Animation with the renditions the server can send instead of the full animation.
+    AnimationWithRenditions(
+        AnimationRessource,
+        AnimationMetadata,
+        Vec<AnimationRendition>,
+    ),
 }
 impl RessourcesDescriptorsKind {
     pub fn new_animation_descriptor(
@@ -93,26 +171,251 @@ impl RessourcesDescriptorsKind {
             height,
         ))
     }
+    pub fn new_animation_descriptor_with_metadata(
+        animation_id: Uuid,
+        author_id: Uuid,
+        width: u32,
+        height: u32,
+        metadata: AnimationMetadata,
+    ) -> Self {
+        Self::AnimationWithMetadata(
+            AnimationRessource::new(animation_id, author_id, width, height),
+            metadata,
+        )
+    }
+    pub fn new_animation_descriptor_with_renditions(
+        animation_id: Uuid,
+        author_id: Uuid,
+        width: u32,
+        height: u32,
+        metadata: AnimationMetadata,
+        renditions: Vec<AnimationRendition>,
+    ) -> Self {
+        Self::AnimationWithRenditions(
+            AnimationRessource::new(animation_id, author_id, width, height),
+            metadata,
+            renditions,
+        )
+    }
+    pub fn kind(&self) -> RessourceKind {
+        match self {
+            Self::Animation(_)
+            | Self::AnimationWithMetadata(..)
+            | Self::AnimationWithRenditions(..) => RessourceKind::Animation,
+            Self::TextBlock(_) => RessourceKind::TextBlock,
+            Self::Image(_) => RessourceKind::Image,
+            Self::Sticker(_) => RessourceKind::Sticker,
+            Self::Audio(_) => RessourceKind::Audio,
+        }
+    }
     pub fn get_id(&self) -> Uuid {
         match self {
-            Self::Animation(animation_desc) => animation_desc.get_id(),
+            Self::Animation(animation_desc)
+            | Self::AnimationWithMetadata(animation_desc, ..)
+            | Self::AnimationWithRenditions(animation_desc, ..) => animation_desc.get_id(),
+            Self::TextBlock(text_block) => text_block.get_id(),
+            Self::Image(image) => image.get_id(),
+            Self::Sticker(sticker) => sticker.get_id(),
+            Self::Audio(audio) => audio.get_id(),
         }
     }
     pub fn get_author_id(&self) -> Uuid {
         match self {
-            Self::Animation(animation_desc) => animation_desc.get_author_id(),
+            Self::Animation(animation_desc)
+            | Self::AnimationWithMetadata(animation_desc, ..)
+            | Self::AnimationWithRenditions(animation_desc, ..) => animation_desc.get_author_id(),
+            Self::TextBlock(text_block) => text_block.get_author_id(),
+            Self::Image(image) => image.get_author_id(),
+            Self::Sticker(sticker) => sticker.get_author_id(),
+            Self::Audio(audio) => audio.get_author_id(),
         }
     }
+    /// 0 for ressources without a size on screen (audio), see [`Self::size`].
     pub fn width(&self) -> u32 {
+        self.size().map_or(0, |it| it[0])
+    }
+    /// 0 for ressources without a size on screen (audio), see [`Self::size`].
+    pub fn height(&self) -> u32 {
+        self.size().map_or(0, |it| it[1])
+    }
+    /// Width and height, `None` for ressources without a size on screen (audio).
+    pub fn size(&self) -> Option<[u32; 2]> {
         match self {
-            Self::Animation(animation_desc) => animation_desc.width(),
+            Self::Animation(animation_desc)
+            | Self::AnimationWithMetadata(animation_desc, ..)
+            | Self::AnimationWithRenditions(animation_desc, ..) => {
+                Some([animation_desc.width(), animation_desc.height()])
+            }
+            Self::TextBlock(text_block) => Some([text_block.width(), text_block.height()]),
+            Self::Image(image) => Some([image.width(), image.height()]),
+            Self::Sticker(sticker) => Some([sticker.width(), sticker.height()]),
+            Self::Audio(_) => None,
         }
     }
-    pub fn height(&self) -> u32 {
+    /// Hash of the payload to fetch, `None` for text blocks and for animations stored before
+    /// metadata existed.
+    pub fn content_hash(&self) -> Option<&ContentHash> {
+        match self {
+            Self::Animation(_) | Self::TextBlock(_) => None,
+            Self::AnimationWithMetadata(_, metadata)
+            | Self::AnimationWithRenditions(_, metadata, _) => Some(metadata.content_hash()),
+            Self::Image(image) => Some(image.content_hash()),
+            Self::Sticker(sticker) => Some(sticker.content_hash()),
+            Self::Audio(audio) => Some(audio.content_hash()),
+        }
+    }
+    /// `None` for other kinds and for descriptors stored before metadata existed.
+    pub fn animation_metadata(&self) -> Option<&AnimationMetadata> {
         match self {
-            Self::Animation(animation_desc) => animation_desc.height(),
+            Self::AnimationWithMetadata(_, metadata)
+            | Self::AnimationWithRenditions(_, metadata, _) => Some(metadata),
+            _ => None,
         }
     }
+    /// Renditions the server can send instead of the full animation. Empty for descriptors
+    /// stored before renditions existed: only the full animation can be fetched.
+    pub fn renditions(&self) -> &[AnimationRendition] {
+        match self {
+            Self::AnimationWithRenditions(_, _, renditions) => renditions,
+            _ => &[],
+        }
+    }
+    /// See [`select_rendition`].
+    pub fn select_rendition(
+        &self,
+        max_bytes: u64,
+        target_width: u32,
+        target_height: u32,
+    ) -> Option<&AnimationRendition> {
+        select_rendition(self.renditions(), max_bytes, target_width, target_height)
+    }
+}
+
+/// [`AnimationMetadata`] lets a client show an animation duration, preallocate its buffers or
+/// defer the download on a metered connection before fetching it.
+#[derive(Encode, Deserialize, Serialize, Decode, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnimationMetadata {
+    fps: u8,
+    frame_count: u32,
+    byte_len: u64,
+    content_hash: ContentHash,
+    mime_type: String,
+    created_at: i64,
+    has_mask: bool,
+}
+impl AnimationMetadata {
+    pub fn new(
+        fps: u8,
+        frame_count: u32,
+        byte_len: u64,
+        content_hash: ContentHash,
+        mime_type: String,
+        created_at: DateTime<Utc>,
+        has_mask: bool,
+    ) -> Self {
+        Self {
+            fps,
+            frame_count,
+            byte_len,
+            content_hash,
+            mime_type,
+            created_at: created_at.timestamp(),
+            has_mask,
+        }
+    }
+    /// Metadata of a received animation. The frame count and the container format are not
+    /// part of [`SyncNewAnim`], the server reads them from the data.
+    pub fn from_sync_new_anim(
+        anim: &SyncNewAnim,
+        frame_count: u32,
+        mime_type: String,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self::new(
+            anim.fps(),
+            frame_count,
+            anim.data_len() as u64,
+            anim.content_hash(),
+            mime_type,
+            created_at,
+            anim.mask_png_buffer_len() > 0,
+        )
+    }
+    pub fn fps(&self) -> u8 {
+        self.fps
+    }
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+    pub fn duration(&self) -> Duration {
+        if self.fps == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.frame_count as f64 / self.fps as f64)
+    }
+    pub fn byte_len(&self) -> u64 {
+        self.byte_len
+    }
+    pub fn content_hash(&self) -> &ContentHash {
+        &self.content_hash
+    }
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.created_at, 0)
+    }
+    pub fn has_mask(&self) -> bool {
+        self.has_mask
+    }
+}
+
+impl Validate for RessourcesDescriptorsKind {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        if self.get_id().is_nil() {
+            violations.push(field_path(path, "ressource_id"), ViolationKind::NilId);
+        }
+        if self.get_author_id().is_nil() {
+            violations.push(field_path(path, "author_id"), ViolationKind::NilId);
+        }
+        match self {
+            Self::Animation(animation_desc)
+            | Self::AnimationWithMetadata(animation_desc, ..)
+            | Self::AnimationWithRenditions(animation_desc, ..) => {
+                let max = MAX_FRAME_DIMENSION as f64;
+                violations.check_range(path, "width", animation_desc.width() as f64, 1.0, max);
+                violations.check_range(path, "height", animation_desc.height() as f64, 1.0, max);
+            }
+            Self::TextBlock(text_block) => text_block.validate_at(path, violations),
+            Self::Image(image) => image.validate_at(path, violations),
+            Self::Sticker(sticker) => sticker.validate_at(path, violations),
+            Self::Audio(audio) => audio.validate_at(path, violations),
+        }
+        if let Some(metadata) = self.animation_metadata() {
+            metadata.validate_at(&field_path(path, "metadata"), violations);
+        }
+        violations.check_items(path, "renditions", self.renditions());
+    }
+}
+impl Validate for AnimationMetadata {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_range(path, "fps", self.fps as f64, 1.0, MAX_FPS as f64);
+        violations.check_range(
+            path,
+            "frame_count",
+            self.frame_count as f64,
+            1.0,
+            u32::MAX as f64,
+        );
+        violations.check_range(
+            path,
+            "byte_len",
+            self.byte_len as f64,
+            1.0,
+            MAX_ANIMATION_BYTES as f64,
+        );
+        violations.check_not_empty(path, "mime_type", self.mime_type.len());
+    }
 }
 
 mod ressources_descriptors_kind {
@@ -150,3 +453,84 @@ mod ressources_descriptors_kind {
         }
     }
 }
+
+#[cfg(test)]
+mod ressources_descriptors_test {
+    use bincode::Encode;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::{
+        AnimVariableContext, AnimationMetadata, ClipShape, DecodeLimited, DecodeLimits,
+        FragmentStyle, RessourcesDescriptors, RessourcesDescriptorsKind, content_hash,
+        ressources_descriptors::ressources_descriptors_kind::AnimationRessource,
+    };
+
+    /// Layout of [`RessourcesDescriptorsKind`] before `AnimationWithMetadata` was appended.
+    #[derive(Encode)]
+    enum BaselineRessourcesDescriptorsKind {
+        Animation(AnimationRessource),
+    }
+
+    #[test]
+    fn animation_metadata_round_trips_and_baseline_descriptors_decode() {
+        let [anim_id, author_id] = [Uuid::now_v7(), Uuid::now_v7()];
+        let metadata = AnimationMetadata::new(
+            24,
+            48,
+            1024,
+            content_hash(&[1, 2, 3]),
+            "video/webm".to_string(),
+            Utc::now(),
+            true,
+        );
+        let descriptor = RessourcesDescriptorsKind::new_animation_descriptor_with_metadata(
+            anim_id,
+            author_id,
+            64,
+            32,
+            metadata.clone(),
+        );
+        let encoded = bincode::encode_to_vec(&descriptor, bincode::config::standard()).unwrap();
+        let (decoded, _): (RessourcesDescriptorsKind, _) =
+            bincode::decode_from_slice(&encoded, bincode::config::standard()).unwrap();
+        assert!(decoded == descriptor);
+        assert!(decoded.animation_metadata() == Some(&metadata));
+        let json = serde_json::to_string(&descriptor).unwrap();
+        assert!(serde_json::from_str::<RessourcesDescriptorsKind>(&json).unwrap() == descriptor);
+
+        let baseline = BaselineRessourcesDescriptorsKind::Animation(AnimationRessource::new(
+            anim_id, author_id, 64, 32,
+        ));
+        let encoded = bincode::encode_to_vec(&baseline, bincode::config::standard()).unwrap();
+        let (decoded, _): (RessourcesDescriptorsKind, _) =
+            bincode::decode_from_slice(&encoded, bincode::config::standard()).unwrap();
+        assert!(
+            decoded
+                == RessourcesDescriptorsKind::new_animation_descriptor(anim_id, author_id, 64, 32)
+        );
+        assert!(decoded.animation_metadata().is_none());
+    }
+
+    #[test]
+    fn nested_context_styles_are_limited() {
+        let anim_id = Uuid::now_v7();
+        let mut context =
+            AnimVariableContext::new(anim_id, Uuid::now_v7(), Uuid::now_v7(), 1, 1, [0.0; 2]);
+        context.set_style(FragmentStyle::default().with_clip(ClipShape::Polygon {
+            points: vec![[0, 0]; 16],
+        }));
+        let mut descriptors = RessourcesDescriptors::new_empty();
+        descriptors.add_ressource_descriptor((
+            RessourcesDescriptorsKind::new_animation_descriptor(anim_id, Uuid::now_v7(), 64, 32),
+            vec![context],
+        ));
+        let limits = DecodeLimits::DEFAULT.with_max_collection_len(8);
+        assert!(descriptors.check_decode_limits(&limits).is_err());
+        assert!(
+            descriptors
+                .check_decode_limits(&DecodeLimits::DEFAULT)
+                .is_ok()
+        );
+    }
+}
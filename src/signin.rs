@@ -2,7 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{UserDisplayContext, UserPeersInfos};
+use crate::{UserDisplayContext, UserPeersInfos, Validate, ViolationKind, Violations};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SigninData {
@@ -73,4 +73,133 @@ impl SigninResponseData {
     pub fn user_creation_ts(&self) -> DateTime<Utc> {
         self.user_creation_ts
     }
+    pub fn builder(username: &str, uuid: Uuid) -> SigninResponseDataBuilder {
+        SigninResponseDataBuilder::new(username, uuid)
+    }
+}
+
+/// [`SigninResponseDataBuilder`] names each field of a [`SigninResponseData`]. Both tokens are
+/// required.
+#[derive(Debug, Clone)]
+pub struct SigninResponseDataBuilder {
+    username: String,
+    unique_id: Uuid,
+    user_creation_ts: Option<DateTime<Utc>>,
+    refresh_token: String,
+    jwt: String,
+    user_display_contexts: Vec<UserDisplayContext>,
+    user_peer_infos: Vec<UserPeersInfos>,
+}
+
+impl SigninResponseDataBuilder {
+    pub fn new(username: &str, uuid: Uuid) -> Self {
+        Self {
+            username: username.to_owned(),
+            unique_id: uuid,
+            user_creation_ts: None,
+            refresh_token: String::new(),
+            jwt: String::new(),
+            user_display_contexts: vec![],
+            user_peer_infos: vec![],
+        }
+    }
+    pub fn user_creation_ts(mut self, user_creation_ts: DateTime<Utc>) -> Self {
+        self.user_creation_ts = Some(user_creation_ts);
+        self
+    }
+    pub fn refresh_token(mut self, refresh_token: String) -> Self {
+        self.refresh_token = refresh_token;
+        self
+    }
+    pub fn jwt(mut self, jwt: String) -> Self {
+        self.jwt = jwt;
+        self
+    }
+    pub fn user_display_contexts(mut self, user_display_contexts: Vec<UserDisplayContext>) -> Self {
+        self.user_display_contexts = user_display_contexts;
+        self
+    }
+    pub fn user_peer_infos(mut self, user_peer_infos: Vec<UserPeersInfos>) -> Self {
+        self.user_peer_infos = user_peer_infos;
+        self
+    }
+    pub fn build(self) -> Result<SigninResponseData, Violations> {
+        let mut violations = Violations::default();
+        if self.user_creation_ts.is_none() {
+            violations.push("user_creation_ts".to_string(), ViolationKind::Missing);
+        }
+        let signin_response = SigninResponseData {
+            username: self.username,
+            unique_id: self.unique_id,
+            user_creation_ts: self.user_creation_ts.unwrap_or_default(),
+            refresh_token: self.refresh_token,
+            jwt: self.jwt,
+            user_display_contexts: self.user_display_contexts,
+            user_peer_infos: self.user_peer_infos,
+        };
+        signin_response.validate_at("", &mut violations);
+        violations.into_result()?;
+        Ok(signin_response)
+    }
+}
+
+impl Validate for SigninResponseData {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_empty(path, "username", self.username.trim().len());
+        violations.check_not_nil(path, "unique_id", self.unique_id.as_bytes());
+        violations.check_not_empty(path, "refresh_token", self.refresh_token.len());
+        violations.check_not_empty(path, "jwt", self.jwt.len());
+    }
+}
+
+impl Validate for SigninData {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_empty(path, "username", self.username.trim().len());
+        violations.check_not_empty(path, "password", self.password.len());
+    }
+}
+
+#[cfg(test)]
+mod signin_test {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::SigninResponseData;
+    use crate::{Validate, ViolationKind};
+
+    #[test]
+    fn signin_response_rejects_blank_username_nil_id_and_empty_tokens() {
+        let signin_response = SigninResponseData::new(
+            "  ",
+            Uuid::nil(),
+            Utc::now(),
+            String::new(),
+            "jwt".to_string(),
+            vec![],
+            vec![],
+        );
+        let violations = signin_response.validate().unwrap_err();
+        let paths: Vec<_> = violations.iter().map(|it| it.field_path()).collect();
+        assert!(paths == ["username", "unique_id", "refresh_token"]);
+    }
+
+    #[test]
+    fn builder_requires_creation_time_and_both_tokens() {
+        let user_id = Uuid::now_v7();
+        let signin_response = SigninResponseData::builder("ada", user_id)
+            .user_creation_ts(Utc::now())
+            .refresh_token("refresh".to_string())
+            .jwt("jwt".to_string())
+            .build()
+            .unwrap();
+        assert!(signin_response.id() == user_id && signin_response.jwt() == "jwt");
+
+        let violations = SigninResponseData::builder(" ", user_id)
+            .jwt("jwt".to_string())
+            .build()
+            .unwrap_err();
+        let paths: Vec<_> = violations.iter().map(|it| it.field_path()).collect();
+        assert!(paths == ["user_creation_ts", "username", "refresh_token"]);
+        assert!(violations.iter().next().unwrap().kind() == &ViolationKind::Missing);
+    }
 }
@@ -2,6 +2,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::{Validate, Violations};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignupData {
     username: String,
@@ -72,3 +74,10 @@ impl SignupResponseData {
         self.experiment_display_context_creation_ts
     }
 }
+
+impl Validate for SignupData {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        violations.check_not_empty(path, "username", self.username.trim().len());
+        violations.check_not_empty(path, "password", self.password.len());
+    }
+}
@@ -3,7 +3,10 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{DisplayContext, InvitationOrientation};
+use crate::{
+    DecodeLimitError, DecodeLimited, DecodeLimits, DisplayContext, InvitationOrientation,
+    MAX_USERNAME_LEN, Validate, Violations, field_path,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct UserStreamSessionInfo {
@@ -237,6 +240,126 @@ impl StreamMessage {
     }
 }
 
+impl DecodeLimited for StreamMessage {
+    fn default_decode_limits() -> DecodeLimits {
+        DisplayContext::default_decode_limits()
+    }
+    fn check_decode_limits(&self, limits: &DecodeLimits) -> Result<(), DecodeLimitError> {
+        match self {
+            Self::InvitationRequest {
+                inviter_name,
+                invitee_name,
+                ..
+            } => {
+                // a stricter bound of the caller is kept
+                let limits =
+                    limits.with_max_string_len(limits.max_string_len().min(MAX_USERNAME_LEN));
+                limits.check_string("inviter_name", inviter_name)?;
+                limits.check_string("invitee_name", invitee_name)
+            }
+            Self::InvitationResponse {
+                first_common_display_context: Some(display_context),
+                ..
+            } => display_context.check_decode_limits(limits),
+            Self::NewSessionVersionAvailable { context, .. } => {
+                limits.check_collection("context", context.len())
+            }
+            Self::InvitationResponse { .. }
+            | Self::RemovedNotification { .. }
+            | Self::ContactRequest { .. } => Ok(()),
+        }
+    }
+}
+
+impl Validate for StreamMessage {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        match self {
+            Self::InvitationRequest {
+                req_id,
+                invitation_message_id,
+                inviter_id,
+                inviter_name,
+                invitee_name,
+                invitee_id,
+                ..
+            } => {
+                violations.check_not_nil(path, "req_id", req_id);
+                violations.check_not_nil(path, "invitation_message_id", invitation_message_id);
+                violations.check_not_nil(path, "inviter_id", inviter_id);
+                violations.check_not_nil(path, "invitee_id", invitee_id);
+                violations.check_not_empty(path, "inviter_name", inviter_name.len());
+                violations.check_not_empty(path, "invitee_name", invitee_name.len());
+            }
+            Self::InvitationResponse {
+                req_id,
+                invitation_message_id,
+                inviter_id,
+                invited_id,
+                first_common_display_context,
+                ..
+            } => {
+                violations.check_not_nil(path, "req_id", req_id);
+                violations.check_not_nil(path, "invitation_message_id", invitation_message_id);
+                violations.check_not_nil(path, "inviter_id", inviter_id);
+                violations.check_not_nil(path, "invited_id", invited_id);
+                if let Some(display_context) = first_common_display_context {
+                    display_context.validate_at(
+                        &field_path(path, "first_common_display_context"),
+                        violations,
+                    );
+                }
+            }
+            Self::RemovedNotification {
+                req_id,
+                peer_id,
+                removed_notification_id,
+            } => {
+                violations.check_not_nil(path, "req_id", req_id);
+                violations.check_not_nil(path, "peer_id", peer_id);
+                violations.check_not_nil(path, "removed_notification_id", removed_notification_id);
+            }
+            Self::ContactRequest {
+                req_id, peer_id, ..
+            }
+            | Self::NewSessionVersionAvailable {
+                req_id, peer_id, ..
+            } => {
+                violations.check_not_nil(path, "req_id", req_id);
+                violations.check_not_nil(path, "peer_id", peer_id);
+            }
+        }
+    }
+}
+
 pub struct StreamMessageId {
     req_id: Uuid,
 }
+
+#[cfg(test)]
+mod stream_types_test {
+    use uuid::Uuid;
+
+    use crate::{DecodeLimited, DecodeLimits, MAX_USERNAME_LEN, StreamMessage};
+
+    #[test]
+    fn usernames_are_bounded_whatever_the_limits() {
+        let name = "a".repeat(MAX_USERNAME_LEN + 1);
+        let (_, message) = StreamMessage::new_invitation_request_for_sender(
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            &name,
+            Uuid::now_v7(),
+        );
+        let loose = DecodeLimits::DEFAULT.with_max_string_len(4096);
+        assert!(message.check_decode_limits(&loose).is_err());
+        let (_, message) = StreamMessage::new_invitation_request_for_sender(
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            "ann",
+            Uuid::now_v7(),
+        );
+        assert!(message.check_decode_limits(&loose).is_ok());
+        let strict = DecodeLimits::DEFAULT.with_max_string_len(2);
+        assert!(message.check_decode_limits(&strict).is_err());
+    }
+}
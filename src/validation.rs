@@ -0,0 +1,160 @@
+/// Product constraints shared by the client and the server.
+pub const MAX_FRAME_DIMENSION: u32 = 4096;
+pub const MAX_FPS: u8 = 120;
+
+/// [`Validate`] checks the semantic invariants of a payload: nil ids, zero fps, inverted
+/// bounds... Both sides call it before sending or persisting. Every violation is reported, not
+/// only the first one.
+pub trait Validate {
+    /// Pushes the violations of `self` into `violations`. `path` is the field path of `self`
+    /// in its parent, empty for the root.
+    fn validate_at(&self, path: &str, violations: &mut Violations);
+
+    fn validate(&self) -> Result<(), Violations> {
+        let mut violations = Violations::default();
+        self.validate_at("", &mut violations);
+        violations.into_result()
+    }
+}
+
+/// Joins a parent path and a field name: `compositions[2]` + `min_x` gives
+/// `compositions[2].min_x`.
+pub fn field_path(parent: &str, field: &str) -> String {
+    if parent.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", parent, field)
+    }
+}
+
+pub fn indexed_path(parent: &str, field: &str, index: usize) -> String {
+    format!("{}[{}]", field_path(parent, field), index)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    field_path: String,
+    kind: ViolationKind,
+}
+
+impl Violation {
+    pub fn new(field_path: String, kind: ViolationKind) -> Self {
+        Self { field_path, kind }
+    }
+    pub fn field_path(&self) -> &str {
+        &self.field_path
+    }
+    pub fn kind(&self) -> &ViolationKind {
+        &self.kind
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViolationKind {
+    NilId,
+    Missing,
+    Empty,
+    NotFinite,
+    OutOfRange { value: f64, min: f64, max: f64 },
+    InvertedBounds { min: f64, max: f64 },
+    Invalid(&'static str),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Violations {
+    items: Vec<Violation>,
+}
+
+impl Violations {
+    pub fn push(&mut self, field_path: String, kind: ViolationKind) {
+        self.items.push(Violation::new(field_path, kind));
+    }
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Violation> {
+        self.items.iter()
+    }
+    pub fn into_result(self) -> Result<(), Violations> {
+        if self.items.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+    pub fn check_not_nil(&mut self, path: &str, field: &str, id: &[u8; 16]) {
+        if id == &[0u8; 16] {
+            self.push(field_path(path, field), ViolationKind::NilId);
+        }
+    }
+    pub fn check_not_empty(&mut self, path: &str, field: &str, len: usize) {
+        if len == 0 {
+            self.push(field_path(path, field), ViolationKind::Empty);
+        }
+    }
+    pub fn check_finite(&mut self, path: &str, field: &str, value: f32) {
+        if !value.is_finite() {
+            self.push(field_path(path, field), ViolationKind::NotFinite);
+        }
+    }
+    pub fn check_range(&mut self, path: &str, field: &str, value: f64, min: f64, max: f64) {
+        if !(min..=max).contains(&value) {
+            self.push(
+                field_path(path, field),
+                ViolationKind::OutOfRange { value, min, max },
+            );
+        }
+    }
+    pub fn check_bounds(&mut self, path: &str, field: &str, min: f32, max: f32) {
+        if min > max {
+            self.push(
+                field_path(path, field),
+                ViolationKind::InvertedBounds {
+                    min: min as f64,
+                    max: max as f64,
+                },
+            );
+        }
+    }
+    pub fn check_items<T: Validate>(&mut self, path: &str, field: &str, items: &[T]) {
+        for (index, item) in items.iter().enumerate() {
+            item.validate_at(&indexed_path(path, field, index), self);
+        }
+    }
+}
+
+impl std::fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NilId => write!(f, "nil id"),
+            Self::Missing => write!(f, "missing"),
+            Self::Empty => write!(f, "empty"),
+            Self::NotFinite => write!(f, "not finite"),
+            Self::OutOfRange { value, min, max } => {
+                write!(f, "[{}] out of range [{}..={}]", value, min, max)
+            }
+            Self::InvertedBounds { min, max } => {
+                write!(f, "inverted bounds min [{}] > max [{}]", min, max)
+            }
+            Self::Invalid(reason) => write!(f, "invalid : {}", reason),
+        }
+    }
+}
+
+impl std::fmt::Display for Violations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Violations [")?;
+        for (index, violation) in self.items.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} : {}", violation.field_path, violation.kind)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl std::error::Error for Violations {}
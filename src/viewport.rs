@@ -0,0 +1,233 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::{Aabb, CompositionData};
+
+// Coordinate systems:
+// - world: the composition space every peer stores, in world units,
+// - normalized: 0.0..=1.0 inside the bounds of a composition,
+// - screen: logical pixels from the top left corner of the device screen, `x` to the right and
+//   `y` down like the world axes. Physical pixels are logical pixels times the pixel ratio.
+
+/// Point in world units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct WorldPoint {
+    pub x: f32,
+    pub y: f32,
+}
+/// Point in logical pixels, from the top left corner of the screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct ScreenPoint {
+    pub x: f32,
+    pub y: f32,
+}
+/// Point relative to the bounds of a composition, 0.0..=1.0 inside them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct NormalizedPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+macro_rules! impl_point {
+    ($point:ident) => {
+        impl $point {
+            pub fn new(x: f32, y: f32) -> Self {
+                Self { x, y }
+            }
+            pub fn to_array(self) -> [f32; 2] {
+                [self.x, self.y]
+            }
+        }
+        impl From<[f32; 2]> for $point {
+            fn from(value: [f32; 2]) -> Self {
+                Self::new(value[0], value[1])
+            }
+        }
+        impl From<$point> for [f32; 2] {
+            fn from(value: $point) -> Self {
+                value.to_array()
+            }
+        }
+    };
+}
+impl_point!(WorldPoint);
+impl_point!(ScreenPoint);
+impl_point!(NormalizedPoint);
+
+impl CompositionData {
+    pub fn to_normalized(&self, point: WorldPoint) -> NormalizedPoint {
+        self.normalized_from_world(point.to_array()).into()
+    }
+    pub fn to_world(&self, point: NormalizedPoint) -> WorldPoint {
+        self.world_from_normalized(point.to_array()).into()
+    }
+}
+
+/// [`Viewport`] is the camera of a device: the world point drawn at the center of the screen,
+/// the zoom in logical pixels per world unit, the screen size in logical pixels and the number
+/// of physical pixels per logical pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct Viewport {
+    center: WorldPoint,
+    zoom: f32,
+    device_size: [f32; 2],
+    pixel_ratio: f32,
+}
+impl Viewport {
+    pub fn new(center: WorldPoint, zoom: f32, device_size: [f32; 2], pixel_ratio: f32) -> Self {
+        Self {
+            center,
+            zoom,
+            device_size,
+            pixel_ratio,
+        }
+    }
+    /// Centers the bounds of `composition` and zooms so that they fit the screen, keeping
+    /// their aspect ratio. Two devices fitting the same composition show the same layout, only
+    /// letterboxed differently. An empty composition is shown at zoom 1.0.
+    pub fn fit_composition(
+        composition: &CompositionData,
+        device_size: [f32; 2],
+        pixel_ratio: f32,
+    ) -> Self {
+        let bounds = Aabb::new(
+            [composition.min_x(), composition.min_y()],
+            [composition.max_x(), composition.max_y()],
+        );
+        let zoom = [
+            device_size[0] / bounds.width(),
+            device_size[1] / bounds.height(),
+        ]
+        .into_iter()
+        .filter(|it| it.is_finite() && *it > 0.0)
+        .reduce(f32::min)
+        .unwrap_or(1.0);
+        Self::new(bounds.center().into(), zoom, device_size, pixel_ratio)
+    }
+    pub fn center(&self) -> WorldPoint {
+        self.center
+    }
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+    pub fn device_size(&self) -> [f32; 2] {
+        self.device_size
+    }
+    pub fn pixel_ratio(&self) -> f32 {
+        self.pixel_ratio
+    }
+    pub fn physical_size(&self) -> [f32; 2] {
+        self.device_size.map(|it| it * self.pixel_ratio)
+    }
+    pub fn set_center(&mut self, center: WorldPoint) {
+        self.center = center;
+    }
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom;
+    }
+    /// Multiplies the zoom by `factor` keeping the world point under `anchor` in place, as a
+    /// pinch does.
+    pub fn zoom_at(&mut self, anchor: ScreenPoint, factor: f32) {
+        let world_anchor = self.world_from_screen(anchor);
+        self.zoom *= factor;
+        let drifted = self.world_from_screen(anchor);
+        self.center.x += world_anchor.x - drifted.x;
+        self.center.y += world_anchor.y - drifted.y;
+    }
+    /// Moves the world by a drag of `delta` logical pixels.
+    pub fn pan(&mut self, delta: ScreenPoint) {
+        self.center.x -= delta.x / self.zoom;
+        self.center.y -= delta.y / self.zoom;
+    }
+    pub fn screen_from_world(&self, point: WorldPoint) -> ScreenPoint {
+        ScreenPoint::new(
+            (point.x - self.center.x) * self.zoom + self.device_size[0] / 2.0,
+            (point.y - self.center.y) * self.zoom + self.device_size[1] / 2.0,
+        )
+    }
+    /// Inverse of [`Self::screen_from_world`]. A zero zoom maps every pixel to the center.
+    pub fn world_from_screen(&self, point: ScreenPoint) -> WorldPoint {
+        if self.zoom == 0.0 {
+            return self.center;
+        }
+        WorldPoint::new(
+            (point.x - self.device_size[0] / 2.0) / self.zoom + self.center.x,
+            (point.y - self.device_size[1] / 2.0) / self.zoom + self.center.y,
+        )
+    }
+    pub fn screen_from_normalized(
+        &self,
+        composition: &CompositionData,
+        point: NormalizedPoint,
+    ) -> ScreenPoint {
+        self.screen_from_world(composition.to_world(point))
+    }
+    pub fn normalized_from_screen(
+        &self,
+        composition: &CompositionData,
+        point: ScreenPoint,
+    ) -> NormalizedPoint {
+        composition.to_normalized(self.world_from_screen(point))
+    }
+    /// Position in physical pixels, to address a framebuffer.
+    pub fn physical_from_screen(&self, point: ScreenPoint) -> [f32; 2] {
+        [point.x * self.pixel_ratio, point.y * self.pixel_ratio]
+    }
+    /// Length in logical pixels of `world_len` world units.
+    pub fn screen_len(&self, world_len: f32) -> f32 {
+        world_len * self.zoom
+    }
+    /// World area visible on the screen.
+    pub fn visible_world(&self) -> Aabb {
+        Aabb::new(
+            self.world_from_screen(ScreenPoint::new(0.0, 0.0))
+                .to_array(),
+            self.world_from_screen(self.device_size.into()).to_array(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod viewport_test {
+    use uuid::Uuid;
+
+    use crate::{CompositionData, NormalizedPoint, ScreenPoint, Viewport, WorldPoint};
+
+    #[test]
+    fn layout_matches_across_devices() {
+        let composition = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7())
+            .min_x(-100.0)
+            .max_x(100.0)
+            .min_y(0.0)
+            .max_y(400.0)
+            .build()
+            .unwrap();
+        let small = Viewport::fit_composition(&composition, [360.0, 640.0], 2.0);
+        let large = Viewport::fit_composition(&composition, [430.0, 932.0], 3.0);
+        assert!(small.zoom() == 1.6);
+        assert!(small.center() == WorldPoint::new(0.0, 200.0));
+
+        // The same normalized point lands at the same place relative to the composition.
+        let point = NormalizedPoint::new(0.25, 0.75);
+        for viewport in [small, large] {
+            let screen = viewport.screen_from_normalized(&composition, point);
+            let top_left = viewport.screen_from_world(WorldPoint::new(-100.0, 0.0));
+            let relative = [
+                (screen.x - top_left.x) / viewport.screen_len(200.0),
+                (screen.y - top_left.y) / viewport.screen_len(400.0),
+            ];
+            assert!((relative[0] - 0.25).abs() < 1e-5 && (relative[1] - 0.75).abs() < 1e-5);
+            let back = viewport.normalized_from_screen(&composition, screen);
+            assert!((back.x - 0.25).abs() < 1e-5 && (back.y - 0.75).abs() < 1e-5);
+        }
+
+        let mut pinched = small;
+        let anchor = ScreenPoint::new(50.0, 100.0);
+        let under_finger = pinched.world_from_screen(anchor);
+        pinched.zoom_at(anchor, 2.5);
+        let after = pinched.world_from_screen(anchor);
+        assert!((after.x - under_finger.x).abs() < 1e-4);
+        assert!((after.y - under_finger.y).abs() < 1e-4);
+        assert!(small.physical_from_screen(anchor) == [100.0, 200.0]);
+    }
+}
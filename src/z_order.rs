@@ -0,0 +1,197 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::{Validate, ViolationKind, Violations};
+
+/// Longest [`ZOrderKey`] accepted. Every insertion at the same place adds about one bit.
+pub const MAX_Z_ORDER_KEY_LEN: usize = 256;
+
+/// [`ZOrderKey`] is a fractional index: the bytes are the base 256 digits of a number in
+/// `0.0..1.0`, so that keys compare like the numbers they stand for and a key can always be
+/// generated between two others. Valid keys are not empty and do not end with a zero byte.
+///
+/// The empty key is the default, it sorts before every valid key and stands for resources
+/// stored before keys existed.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    Serialize,
+    Deserialize,
+    Encode,
+    Decode,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub struct ZOrderKey(Vec<u8>);
+
+impl ZOrderKey {
+    /// Key of the first resource of an empty stack, in the middle of the range.
+    pub fn first() -> Self {
+        Self(vec![128])
+    }
+    pub fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        let key = Self(bytes);
+        key.is_valid().then_some(key)
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+    pub fn is_valid(&self) -> bool {
+        self.0.last().is_some_and(|it| *it != 0)
+    }
+    /// Key sorting right below `self`, `None` for the empty key.
+    pub fn before(&self) -> Option<Self> {
+        Self::between(None, Some(self))
+    }
+    /// Key sorting right above `self`.
+    pub fn after(&self) -> Self {
+        Self(midpoint(self.digits(), None))
+    }
+    /// Key sorting between `below` and `above`, a missing bound standing for the bottom or the
+    /// top of the stack. `None` when `below` is not strictly lower than `above`.
+    pub fn between(below: Option<&ZOrderKey>, above: Option<&ZOrderKey>) -> Option<Self> {
+        let below = below.map(|it| it.digits()).unwrap_or_default();
+        match above.map(|it| it.digits()) {
+            Some(above) if below >= above => None,
+            above => Some(Self(midpoint(below, above))),
+        }
+    }
+    /// Digits without trailing zeros, which do not change the number.
+    fn digits(&self) -> &[u8] {
+        let len = self
+            .0
+            .iter()
+            .rposition(|it| *it != 0)
+            .map_or(0, |it| it + 1);
+        &self.0[..len]
+    }
+}
+
+/// Digits strictly between `low` and `high` (`None` standing for 1.0), `low < high`. The
+/// result never ends with a zero digit.
+fn midpoint(low: &[u8], high: Option<&[u8]>) -> Vec<u8> {
+    let mut digits = vec![];
+    let mut index = 0;
+    // Common prefix, then the first differing digit.
+    if let Some(high) = high {
+        loop {
+            let low_digit = low.get(index).copied().unwrap_or(0) as u16;
+            let high_digit = high.get(index).copied().unwrap_or(0) as u16;
+            index += 1;
+            if low_digit == high_digit {
+                digits.push(low_digit as u8);
+                continue;
+            }
+            if high_digit - low_digit > 1 {
+                digits.push(((low_digit + high_digit) / 2) as u8);
+                return digits;
+            }
+            // Adjacent digits: keep the low one, anything above the rest of `low` fits.
+            digits.push(low_digit as u8);
+            break;
+        }
+    }
+    loop {
+        let low_digit = low.get(index).copied().unwrap_or(0) as u16;
+        index += 1;
+        if low_digit < 255 {
+            digits.push(((low_digit + 256) / 2) as u8);
+            return digits;
+        }
+        digits.push(255);
+    }
+}
+
+impl Validate for ZOrderKey {
+    fn validate_at(&self, path: &str, violations: &mut Violations) {
+        // The empty key is only read from older data.
+        if !self.0.is_empty() && !self.is_valid() {
+            violations.push(
+                path.to_string(),
+                ViolationKind::Invalid("z order key must not end with a zero byte"),
+            );
+        }
+        violations.check_range(
+            path,
+            "len",
+            self.0.len() as f64,
+            0.0,
+            MAX_Z_ORDER_KEY_LEN as f64,
+        );
+    }
+}
+
+#[cfg(test)]
+mod z_order_test {
+    use uuid::Uuid;
+
+    use crate::{CompositionData, CompositionResourceData, ZOrderKey};
+
+    #[test]
+    fn keys_fit_between_neighbours() {
+        let first = ZOrderKey::first();
+        let mut above = first.after();
+        let below = first.before().unwrap();
+        assert!(below < first && first < above);
+
+        // Repeated insertions at the same place keep finding room.
+        for _ in 0..200 {
+            let key = ZOrderKey::between(Some(&first), Some(&above)).unwrap();
+            assert!(first < key && key < above && key.is_valid());
+            above = key;
+        }
+        let mut bottom = below;
+        for _ in 0..100 {
+            let key = bottom.before().unwrap();
+            assert!(key < bottom && key.is_valid());
+            bottom = key;
+        }
+        let tight = ZOrderKey::from_bytes(vec![7, 255]).unwrap();
+        let next = ZOrderKey::from_bytes(vec![8]).unwrap();
+        let key = ZOrderKey::between(Some(&tight), Some(&next)).unwrap();
+        assert!(tight < key && key < next);
+        assert!(ZOrderKey::between(Some(&next), Some(&tight)).is_none());
+        assert!(ZOrderKey::from_bytes(vec![3, 0]).is_none());
+        assert!(ZOrderKey::default().before().is_none());
+        assert!(ZOrderKey::default().after() == ZOrderKey::first());
+    }
+
+    #[test]
+    fn reordering_rekeys_the_moved_resource_only() {
+        let ids = [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+        let mut builder = CompositionData::builder(Uuid::now_v7(), Uuid::now_v7());
+        let mut key = ZOrderKey::first();
+        for id in ids {
+            builder = builder.add_resource(
+                CompositionResourceData::new(id, [0.0, 0.0], 1.0, 1.0).with_z_order(key.clone()),
+            );
+            key = key.after();
+        }
+        let composition = builder.build().unwrap();
+
+        // The bottom resource goes between the two others.
+        let moved_key = composition.z_order_key_above(None, Some(ids[1])).unwrap();
+        let mut resources = composition.resources_collection().to_vec();
+        resources[0] = resources[0].clone().with_z_order(moved_key);
+        let reordered = CompositionData::builder(composition.id(), composition.author_id())
+            .resources(resources)
+            .build()
+            .unwrap();
+        let order: Vec<_> = reordered
+            .resources_by_z_order()
+            .iter()
+            .map(|it| it.id())
+            .collect();
+        assert!(order == [ids[1], ids[0], ids[2]]);
+        assert!(reordered.z_order_key_on_top() > *composition.resources_collection()[2].z_order());
+        assert!(
+            composition
+                .z_order_key_above(None, Some(Uuid::now_v7()))
+                .is_none()
+        );
+    }
+}